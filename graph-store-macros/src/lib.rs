@@ -0,0 +1,197 @@
+//! Compile-time checked SPARQL queries, re-exported by the `graph-store` crate.
+
+extern crate proc_macro;
+
+use graph_store_sparql::{parse_query, Query, QueryForm};
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use std::path::PathBuf;
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Attribute, Ident, LitStr, Token, Visibility};
+
+/// Checks a query at compile time and expands to a `Selection`, `AskQuery`, `DescribeQuery` or
+/// `ConstructQuery`, depending on its form.
+///
+/// ```ignore
+/// let query = sparql!("SELECT ?s WHERE { ?s a rdfs:Class }");
+/// ```
+///
+/// With a struct name, a SELECT query expands to a struct with a field for each projected
+/// variable, which reads the rows of a result table:
+///
+/// ```ignore
+/// sparql! {
+///     struct Label = "SELECT ?s ?label WHERE { ?s rdfs:label ?label }";
+/// }
+///
+/// for row in Label::rows(dataset.select(Label::selection()).await?) {
+///     println!("{:?} {:?}", row.s, row.label);
+/// }
+/// ```
+#[proc_macro]
+pub fn sparql(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as Input);
+    let text = input.literal().value();
+    expand(&input, &text, quote!()).into()
+}
+
+/// Like `sparql!`, but reads the query from a file, such as `queries/labels.rq`, relative to
+/// the directory of the crate manifest.
+#[proc_macro]
+pub fn sparql_file(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as Input);
+    let literal = input.literal();
+    let mut path = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default());
+    path.push(literal.value());
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) => {
+            let message = format!("Cannot read {}: {}", path.display(), e);
+            return syn::Error::new(literal.span(), message)
+                .to_compile_error()
+                .into();
+        }
+    };
+    let path = path.to_string_lossy();
+    expand(
+        &input,
+        &text,
+        quote!(
+            const _: &str = include_str!(#path);
+        ),
+    )
+    .into()
+}
+
+enum Input {
+    Query(LitStr),
+    Row {
+        attributes: Vec<Attribute>,
+        visibility: Visibility,
+        name: Ident,
+        query: LitStr,
+    },
+}
+
+impl Input {
+    fn literal(&self) -> &LitStr {
+        match self {
+            Input::Query(query) | Input::Row { query, .. } => query,
+        }
+    }
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            return Ok(Input::Query(input.parse()?));
+        }
+        let attributes = input.call(Attribute::parse_outer)?;
+        let visibility = input.parse()?;
+        input.parse::<Token![struct]>()?;
+        let name = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let query = input.parse()?;
+        input.parse::<Option<Token![;]>>()?;
+        Ok(Input::Row {
+            attributes,
+            visibility,
+            name,
+            query,
+        })
+    }
+}
+
+/// Expands to the query, or to a compile error at the query literal.
+fn expand(input: &Input, text: &str, dependency: TokenStream2) -> TokenStream2 {
+    let span = input.literal().span();
+    let query = match parse_query(text) {
+        Ok(query) => query,
+        Err(e) => {
+            return syn::Error::new(span, format!("Invalid SPARQL: {}", e)).to_compile_error()
+        }
+    };
+    match input {
+        Input::Query(_) => {
+            let value = query_value(&query, text);
+            quote! {
+                {
+                    #dependency
+                    #value
+                }
+            }
+        }
+        Input::Row {
+            attributes,
+            visibility,
+            name,
+            ..
+        } => match row(&query, text, attributes, visibility, name) {
+            Ok(row) => quote! {
+                #dependency
+                #row
+            },
+            Err(message) => syn::Error::new(span, message).to_compile_error(),
+        },
+    }
+}
+
+fn query_value(query: &Query, text: &str) -> TokenStream2 {
+    match query.form {
+        QueryForm::Select(_) => quote!(::graph_store::Selection::unsafe_from(#text)),
+        QueryForm::Ask => quote!(::graph_store::AskQuery::unsafe_from(#text)),
+        QueryForm::Describe(_) => quote!(::graph_store::DescribeQuery::unsafe_from(#text)),
+        QueryForm::Construct(_) => quote!(::graph_store::ConstructQuery::unsafe_from(#text)),
+    }
+}
+
+fn row(
+    query: &Query,
+    text: &str,
+    attributes: &[Attribute],
+    visibility: &Visibility,
+    name: &Ident,
+) -> Result<TokenStream2, String> {
+    if !matches!(query.form, QueryForm::Select(_)) {
+        return Err("A row struct needs a SELECT query".to_string());
+    }
+    let variables = query.projected_variables();
+    let fields = variables
+        .iter()
+        .map(|variable| field(variable))
+        .collect::<Result<Vec<_>, _>>()?;
+    let node = quote!(::graph_store::rdf::node::Node);
+    Ok(quote! {
+        #(#attributes)*
+        #[allow(non_snake_case)]
+        #visibility struct #name {
+            #(pub #fields: ::std::option::Option<#node>,)*
+        }
+
+        impl #name {
+            /// The query that this type reads the results of.
+            pub fn selection() -> ::graph_store::Selection {
+                ::graph_store::Selection::unsafe_from(#text)
+            }
+
+            /// Reads the rows of a result table, where variables may be unbound.
+            pub fn rows(table: ::graph_store::table::Table<#node>) -> ::std::vec::Vec<#name> {
+                table
+                    .bindings
+                    .into_iter()
+                    .map(|mut row| #name {
+                        #(#fields: row.remove(&::graph_store::table::Variable::from(#variables)),)*
+                    })
+                    .collect()
+            }
+        }
+    })
+}
+
+/// Names a field after a variable, escaping keywords such as `type`.
+fn field(variable: &str) -> Result<Ident, String> {
+    syn::parse_str::<Ident>(variable)
+        .or_else(|_| syn::parse_str::<Ident>(&format!("r#{}", variable)))
+        .map_err(|_| format!("Variable ?{} cannot be a field name", variable))
+}
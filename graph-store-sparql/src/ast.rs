@@ -0,0 +1,371 @@
+/// A SPARQL 1.1 query.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Query {
+    pub prologue: Vec<Declaration>,
+    pub form: QueryForm,
+    pub dataset: Vec<DatasetClause>,
+    /// The WHERE clause, which is empty for a DESCRIBE query without one.
+    pub pattern: GroupPattern,
+    pub modifiers: Modifiers,
+    pub values: Option<Values>,
+}
+
+/// A BASE or PREFIX declaration.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Declaration {
+    Base(String),
+    Prefix(String, String),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum QueryForm {
+    Select(Projection),
+    /// A template, or none for the `CONSTRUCT WHERE` short form.
+    Construct(Option<Vec<TriplePattern>>),
+    /// The resources to describe, or none for `DESCRIBE *`.
+    Describe(Vec<VarOrIri>),
+    Ask,
+}
+
+/// The projection of a SELECT query. No items means `SELECT *`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Projection {
+    pub modifier: Option<SelectModifier>,
+    pub items: Vec<SelectItem>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SelectModifier {
+    Distinct,
+    Reduced,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SelectItem {
+    Variable(String),
+    /// An expression bound to a variable with AS.
+    Expression(Expression, String),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DatasetClause {
+    From(Iri),
+    FromNamed(Iri),
+}
+
+/// An IRI as written, either in full or as a prefixed name.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Iri {
+    Full(String),
+    Prefixed { prefix: String, local: String },
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum VarOrIri {
+    Variable(String),
+    Iri(Iri),
+}
+
+/// A literal. Numbers keep their lexical form, including a sign.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Literal {
+    String {
+        value: String,
+        language: Option<String>,
+        datatype: Option<Iri>,
+    },
+    Integer(String),
+    Decimal(String),
+    Double(String),
+    Boolean(bool),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Term {
+    Variable(String),
+    Iri(Iri),
+    Literal(Literal),
+    BlankNode(String),
+    /// The anonymous blank node `[]`.
+    Anonymous,
+    /// The empty collection `()`.
+    Nil,
+}
+
+/// A subject or object in a triple pattern.
+#[derive(Debug, PartialEq, Clone)]
+pub enum GraphNode {
+    Term(Term),
+    Collection(Vec<GraphNode>),
+    /// A blank node with properties, written as `[ … ]`.
+    Properties(Vec<Property>),
+}
+
+/// A predicate with its objects.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Property {
+    pub verb: Verb,
+    pub objects: Vec<GraphNode>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Verb {
+    Variable(String),
+    Path(Path),
+}
+
+/// A property path. A single IRI or `a` is a plain predicate.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Path {
+    Iri(Iri),
+    A,
+    Inverse(Box<Path>),
+    Sequence(Vec<Path>),
+    Alternative(Vec<Path>),
+    ZeroOrMore(Box<Path>),
+    OneOrMore(Box<Path>),
+    ZeroOrOne(Box<Path>),
+    /// A negated property set, of IRIs, `a` or their inverses.
+    Negated(Vec<Path>),
+}
+
+/// A subject with its properties, written as one statement.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TriplePattern {
+    pub subject: GraphNode,
+    pub properties: Vec<Property>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum GroupPattern {
+    Elements(Vec<Element>),
+    SubSelect(Box<SubSelect>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Element {
+    Triples(Vec<TriplePattern>),
+    Group(GroupPattern),
+    Union(Vec<GroupPattern>),
+    Optional(GroupPattern),
+    Minus(GroupPattern),
+    Graph(VarOrIri, GroupPattern),
+    Service {
+        silent: bool,
+        name: VarOrIri,
+        pattern: GroupPattern,
+    },
+    Filter(Expression),
+    Bind(Expression, String),
+    Values(Values),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SubSelect {
+    pub projection: Projection,
+    pub pattern: GroupPattern,
+    pub modifiers: Modifiers,
+    pub values: Option<Values>,
+}
+
+/// Inline data, where `None` is UNDEF.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Values {
+    pub variables: Vec<String>,
+    pub rows: Vec<Vec<Option<Term>>>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expression {
+    Term(Term),
+    Or(Box<Expression>, Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Equal(Box<Expression>, Box<Expression>),
+    NotEqual(Box<Expression>, Box<Expression>),
+    Less(Box<Expression>, Box<Expression>),
+    Greater(Box<Expression>, Box<Expression>),
+    LessOrEqual(Box<Expression>, Box<Expression>),
+    GreaterOrEqual(Box<Expression>, Box<Expression>),
+    In(Box<Expression>, Vec<Expression>),
+    NotIn(Box<Expression>, Vec<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Subtract(Box<Expression>, Box<Expression>),
+    Multiply(Box<Expression>, Box<Expression>),
+    Divide(Box<Expression>, Box<Expression>),
+    Plus(Box<Expression>),
+    Minus(Box<Expression>),
+    Not(Box<Expression>),
+    /// A call of a built-in function, named as in the specification, such as `STRLEN`.
+    BuiltIn(String, Vec<Expression>),
+    Function {
+        iri: Iri,
+        distinct: bool,
+        arguments: Vec<Expression>,
+    },
+    Exists(Box<GroupPattern>),
+    NotExists(Box<GroupPattern>),
+    Aggregate(Aggregate),
+}
+
+/// An aggregate such as `COUNT(DISTINCT ?x)`. No argument means `COUNT(*)`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Aggregate {
+    pub name: String,
+    pub distinct: bool,
+    pub argument: Option<Box<Expression>>,
+    pub separator: Option<String>,
+}
+
+/// Solution modifiers.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Modifiers {
+    pub group_by: Vec<GroupCondition>,
+    pub having: Vec<Expression>,
+    pub order_by: Vec<OrderCondition>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct GroupCondition {
+    pub expression: Expression,
+    pub variable: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct OrderCondition {
+    pub expression: Expression,
+    pub descending: bool,
+}
+
+impl Query {
+    /// The variables in the results of a SELECT query, in order. Other forms have none.
+    pub fn projected_variables(&self) -> Vec<String> {
+        match &self.form {
+            QueryForm::Select(projection) => {
+                let mut variables = projection.variables(&self.pattern);
+                if projection.items.is_empty() {
+                    if let Some(values) = &self.values {
+                        add_all(&mut variables, &values.variables);
+                    }
+                }
+                variables
+            }
+            _ => vec![],
+        }
+    }
+}
+
+impl Projection {
+    /// The projected variables, where `SELECT *` projects those in scope of the pattern.
+    pub fn variables(&self, pattern: &GroupPattern) -> Vec<String> {
+        if self.items.is_empty() {
+            return pattern.variables();
+        }
+        self.items
+            .iter()
+            .map(|item| match item {
+                SelectItem::Variable(name) | SelectItem::Expression(_, name) => name.to_string(),
+            })
+            .collect()
+    }
+}
+
+impl GroupPattern {
+    /// The variables in scope, in order of appearance, as defined in section 18.2.1 of the
+    /// specification.
+    pub fn variables(&self) -> Vec<String> {
+        let mut variables = vec![];
+        match self {
+            GroupPattern::Elements(elements) => {
+                for element in elements {
+                    element.add_variables(&mut variables);
+                }
+            }
+            GroupPattern::SubSelect(select) => {
+                add_all(
+                    &mut variables,
+                    &select.projection.variables(&select.pattern),
+                );
+                if let (true, Some(values)) = (select.projection.items.is_empty(), &select.values) {
+                    add_all(&mut variables, &values.variables);
+                }
+            }
+        }
+        variables
+    }
+}
+
+impl Element {
+    pub(crate) fn add_variables(&self, variables: &mut Vec<String>) {
+        match self {
+            Element::Triples(triples) => {
+                for triple in triples {
+                    triple.subject.add_variables(variables);
+                    for property in &triple.properties {
+                        property.add_variables(variables);
+                    }
+                }
+            }
+            Element::Group(pattern) | Element::Optional(pattern) => {
+                add_all(variables, &pattern.variables())
+            }
+            Element::Union(patterns) => {
+                for pattern in patterns {
+                    add_all(variables, &pattern.variables());
+                }
+            }
+            Element::Graph(name, pattern) | Element::Service { name, pattern, .. } => {
+                if let VarOrIri::Variable(name) = name {
+                    add(variables, name);
+                }
+                add_all(variables, &pattern.variables());
+            }
+            Element::Bind(_, name) => add(variables, name),
+            Element::Values(values) => add_all(variables, &values.variables),
+            Element::Minus(_) | Element::Filter(_) => (),
+        }
+    }
+}
+
+impl GraphNode {
+    fn add_variables(&self, variables: &mut Vec<String>) {
+        match self {
+            GraphNode::Term(Term::Variable(name)) => add(variables, name),
+            GraphNode::Term(_) => (),
+            GraphNode::Collection(nodes) => {
+                for node in nodes {
+                    node.add_variables(variables);
+                }
+            }
+            GraphNode::Properties(properties) => {
+                for property in properties {
+                    property.add_variables(variables);
+                }
+            }
+        }
+    }
+}
+
+impl Property {
+    fn add_variables(&self, variables: &mut Vec<String>) {
+        if let Verb::Variable(name) = &self.verb {
+            add(variables, name);
+        }
+        for object in &self.objects {
+            object.add_variables(variables);
+        }
+    }
+}
+
+fn add(variables: &mut Vec<String>, name: &str) {
+    if !variables.iter().any(|v| v == name) {
+        variables.push(name.to_string());
+    }
+}
+
+fn add_all(variables: &mut Vec<String>, names: &[String]) {
+    for name in names {
+        add(variables, name);
+    }
+}
@@ -0,0 +1,462 @@
+use crate::SyntaxError;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum Token {
+    IriRef(String),
+    PrefixedName {
+        prefix: String,
+        local: String,
+    },
+    Namespace(String),
+    BlankNodeLabel(String),
+    Variable(String),
+    LanguageTag(String),
+    String(String),
+    Integer(String),
+    Decimal(String),
+    Double(String),
+    /// A keyword, a built-in function name, `a`, `true` or `false`.
+    Word(String),
+    Symbol(&'static str),
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::IriRef(iri) => write!(f, "<{}>", iri),
+            Token::PrefixedName { prefix, local } => write!(f, "'{}:{}'", prefix, local),
+            Token::Namespace(prefix) => write!(f, "'{}:'", prefix),
+            Token::BlankNodeLabel(label) => write!(f, "'_:{}'", label),
+            Token::Variable(name) => write!(f, "variable ?{}", name),
+            Token::LanguageTag(tag) => write!(f, "language tag @{}", tag),
+            Token::String(_) => write!(f, "string"),
+            Token::Integer(n) | Token::Decimal(n) | Token::Double(n) => write!(f, "number {}", n),
+            Token::Word(word) => write!(f, "'{}'", word),
+            Token::Symbol(symbol) => write!(f, "'{}'", symbol),
+            Token::End => write!(f, "end of input"),
+        }
+    }
+}
+
+pub(crate) type Positioned = (Token, usize, usize);
+
+fn is_pn_chars_base(c: char) -> bool {
+    matches!(
+        c,
+        'A'..='Z'
+        | 'a'..='z'
+        | '\u{00C0}'..='\u{00D6}'
+        | '\u{00D8}'..='\u{00F6}'
+        | '\u{00F8}'..='\u{02FF}'
+        | '\u{0370}'..='\u{037D}'
+        | '\u{037F}'..='\u{1FFF}'
+        | '\u{200C}'..='\u{200D}'
+        | '\u{2070}'..='\u{218F}'
+        | '\u{2C00}'..='\u{2FEF}'
+        | '\u{3001}'..='\u{D7FF}'
+        | '\u{F900}'..='\u{FDCF}'
+        | '\u{FDF0}'..='\u{FFFD}'
+        | '\u{10000}'..='\u{EFFFF}'
+    )
+}
+
+fn is_pn_chars_u(c: char) -> bool {
+    is_pn_chars_base(c) || c == '_'
+}
+
+fn is_pn_chars(c: char) -> bool {
+    match c {
+        '-' | '0'..='9' | '\u{00B7}' | '\u{0300}'..='\u{036F}' | '\u{203F}'..='\u{2040}' => true,
+        c => is_pn_chars_u(c),
+    }
+}
+
+fn is_var_char(c: char) -> bool {
+    match c {
+        '0'..='9' | '\u{00B7}' | '\u{0300}'..='\u{036F}' | '\u{203F}'..='\u{2040}' => true,
+        c => is_pn_chars_u(c),
+    }
+}
+
+/// Splits a query into tokens, ending with `Token::End`.
+pub(crate) fn tokenize(input: &str) -> Result<Vec<Positioned>, SyntaxError> {
+    let mut lexer = Lexer {
+        input,
+        chars: input.char_indices().peekable(),
+        line: 1,
+        column: 1,
+    };
+    let mut tokens = vec![];
+    loop {
+        let token = lexer.next_token()?;
+        let end = token.0 == Token::End;
+        tokens.push(token);
+        if end {
+            return Ok(tokens);
+        }
+    }
+}
+
+struct Lexer<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn error<T>(&self, message: &str) -> Result<T, SyntaxError> {
+        Err(SyntaxError {
+            line: self.line,
+            column: self.column,
+            message: message.to_string(),
+        })
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|(_, c)| *c)
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        let mut chars = self.chars.clone();
+        chars.next();
+        chars.next().map(|(_, c)| c)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next().map(|(_, c)| c);
+        match c {
+            Some('\n') => {
+                self.line += 1;
+                self.column = 1;
+            }
+            Some(_) => self.column += 1,
+            None => (),
+        }
+        c
+    }
+
+    fn rest(&mut self) -> &'a str {
+        match self.chars.peek() {
+            Some((i, _)) => &self.input[*i..],
+            None => "",
+        }
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' | '\r' | '\n' => {
+                    self.bump();
+                }
+                '#' => {
+                    while !matches!(self.peek(), None | Some('\n')) {
+                        self.bump();
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Positioned, SyntaxError> {
+        self.skip_whitespace_and_comments();
+        let (line, column) = (self.line, self.column);
+        let token = match self.peek() {
+            None => Token::End,
+            Some('<') if self.is_iri_ref() => self.iri_ref()?,
+            Some('"') | Some('\'') => self.string()?,
+            Some('@') => self.language_tag()?,
+            Some('?') if matches!(self.peek_second(), Some(c) if is_pn_chars_u(c) || c.is_ascii_digit()) => {
+                self.variable()
+            }
+            Some('$') => self.variable(),
+            Some('_') if self.peek_second() == Some(':') => self.blank_node_label()?,
+            Some('.') if matches!(self.peek_second(), Some('0'..='9')) => self.number()?,
+            Some('0'..='9') => self.number()?,
+            Some(':') => self.prefixed_name(String::new())?,
+            Some(c) if is_pn_chars_base(c) => self.word()?,
+            Some(_) => self.symbol()?,
+        };
+        Ok((token, line, column))
+    }
+
+    fn symbol(&mut self) -> Result<Token, SyntaxError> {
+        const SYMBOLS: [&str; 26] = [
+            "^^", "&&", "||", "!=", "<=", ">=", "{", "}", "(", ")", "[", "]", ".", ";", ",", "*",
+            "/", "+", "-", "?", "^", "|", "!", "=", "<", ">",
+        ];
+        let rest = self.rest();
+        match SYMBOLS.iter().find(|s| rest.starts_with(*s)) {
+            Some(symbol) => {
+                for _ in 0..symbol.len() {
+                    self.bump();
+                }
+                Ok(Token::Symbol(symbol))
+            }
+            None => self.error(&format!(
+                "Unexpected character '{}'",
+                rest.chars().next().unwrap()
+            )),
+        }
+    }
+
+    /// Tells whether a `<` starts an IRI rather than a comparison.
+    fn is_iri_ref(&mut self) -> bool {
+        for c in self.rest().chars().skip(1) {
+            match c {
+                '>' => return true,
+                c if c <= ' ' || ['<', '"', '{', '}', '|', '^', '`'].contains(&c) => return false,
+                _ => (),
+            }
+        }
+        false
+    }
+
+    fn uchar(&mut self) -> Result<char, SyntaxError> {
+        let length = match self.bump() {
+            Some('u') => 4,
+            Some('U') => 8,
+            _ => return self.error("Invalid escape sequence"),
+        };
+        let mut code = 0;
+        for _ in 0..length {
+            match self.bump().and_then(|c| c.to_digit(16)) {
+                Some(digit) => code = code * 16 + digit,
+                None => return self.error("Invalid escape sequence"),
+            }
+        }
+        match std::char::from_u32(code) {
+            Some(c) => Ok(c),
+            None => self.error("Invalid escape sequence"),
+        }
+    }
+
+    fn iri_ref(&mut self) -> Result<Token, SyntaxError> {
+        self.bump();
+        let mut iri = String::new();
+        loop {
+            match self.bump() {
+                Some('>') => return Ok(Token::IriRef(iri)),
+                Some('\\') => iri.push(self.uchar()?),
+                Some(c) => iri.push(c),
+                None => return self.error("Unterminated IRI"),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<Token, SyntaxError> {
+        let quote = self.bump().unwrap();
+        let long = self.peek() == Some(quote) && self.peek_second() == Some(quote);
+        if long {
+            self.bump();
+            self.bump();
+        }
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None => return self.error("Unterminated string"),
+                Some('\\') => {
+                    self.bump();
+                    let c = match self.peek() {
+                        Some('t') => '\t',
+                        Some('b') => '\u{8}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('f') => '\u{c}',
+                        Some(c @ '"') | Some(c @ '\'') | Some(c @ '\\') => c,
+                        _ => {
+                            value.push(self.uchar()?);
+                            continue;
+                        }
+                    };
+                    self.bump();
+                    value.push(c);
+                }
+                Some(c) if c == quote && !long => {
+                    self.bump();
+                    return Ok(Token::String(value));
+                }
+                Some(c) if c == quote && self.rest().starts_with(&quote.to_string().repeat(3)) => {
+                    self.bump();
+                    self.bump();
+                    self.bump();
+                    return Ok(Token::String(value));
+                }
+                Some('\n') | Some('\r') if !long => return self.error("Line break in string"),
+                Some(c) => {
+                    self.bump();
+                    value.push(c);
+                }
+            }
+        }
+    }
+
+    fn language_tag(&mut self) -> Result<Token, SyntaxError> {
+        self.bump();
+        let mut tag = String::new();
+        while let Some(c) = self.peek() {
+            let valid = if tag.contains('-') {
+                c.is_ascii_alphanumeric()
+            } else {
+                c.is_ascii_alphabetic()
+            };
+            if valid || (c == '-' && !tag.is_empty() && !tag.ends_with('-')) {
+                tag.push(c);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        if tag.is_empty() || tag.ends_with('-') {
+            return self.error("Invalid language tag");
+        }
+        Ok(Token::LanguageTag(tag))
+    }
+
+    fn variable(&mut self) -> Token {
+        self.bump();
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if is_var_char(c) {
+                name.push(c);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        Token::Variable(name)
+    }
+
+    fn blank_node_label(&mut self) -> Result<Token, SyntaxError> {
+        self.bump();
+        self.bump();
+        match self.peek() {
+            Some(c) if is_pn_chars_u(c) || c.is_ascii_digit() => (),
+            _ => return self.error("Invalid blank node label"),
+        }
+        Ok(Token::BlankNodeLabel(self.name_chars(false)?))
+    }
+
+    /// Consumes name characters, which may contain but not end with dots.
+    fn name_chars(&mut self, local: bool) -> Result<String, SyntaxError> {
+        let mut name = String::new();
+        loop {
+            match self.peek() {
+                Some('.') => match self.peek_second() {
+                    Some(c) if is_pn_chars(c) || (local && [':', '%', '\\'].contains(&c)) => {
+                        self.bump();
+                        name.push('.');
+                    }
+                    _ => return Ok(name),
+                },
+                Some(':') if local => {
+                    self.bump();
+                    name.push(':');
+                }
+                Some('%') if local => {
+                    self.bump();
+                    name.push('%');
+                    for _ in 0..2 {
+                        match self.bump() {
+                            Some(c) if c.is_ascii_hexdigit() => name.push(c),
+                            _ => return self.error("Invalid percent encoding"),
+                        }
+                    }
+                }
+                Some('\\') if local => {
+                    self.bump();
+                    match self.bump() {
+                        Some(c) if "_~.-!$&'()*+,;=/?#@%".contains(c) => {
+                            name.push('\\');
+                            name.push(c);
+                        }
+                        _ => return self.error("Invalid escape in local name"),
+                    }
+                }
+                Some(c) if is_pn_chars(c) => {
+                    self.bump();
+                    name.push(c);
+                }
+                _ => return Ok(name),
+            }
+        }
+    }
+
+    fn prefixed_name(&mut self, prefix: String) -> Result<Token, SyntaxError> {
+        self.bump();
+        match self.peek() {
+            Some(c) if is_pn_chars_u(c) || c.is_ascii_digit() || [':', '%', '\\'].contains(&c) => {
+                let local = self.name_chars(true)?;
+                Ok(Token::PrefixedName { prefix, local })
+            }
+            _ => Ok(Token::Namespace(prefix)),
+        }
+    }
+
+    fn word(&mut self) -> Result<Token, SyntaxError> {
+        let start = self.rest();
+        let mut length = 0;
+        while let Some(c) = self.peek() {
+            if is_pn_chars(c)
+                || (c == '.' && matches!(self.peek_second(), Some(c) if is_pn_chars(c)))
+            {
+                length += c.len_utf8();
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        let word = &start[..length];
+        if self.peek() == Some(':') {
+            return self.prefixed_name(word.to_string());
+        }
+        if !word.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return self.error(&format!("Unexpected word '{}'", word));
+        }
+        Ok(Token::Word(word.to_string()))
+    }
+
+    fn digits(&mut self, number: &mut String) -> usize {
+        let mut count = 0;
+        while let Some(c @ '0'..='9') = self.peek() {
+            self.bump();
+            number.push(c);
+            count += 1;
+        }
+        count
+    }
+
+    fn number(&mut self) -> Result<Token, SyntaxError> {
+        let mut number = String::new();
+        self.digits(&mut number);
+        let mut decimal = false;
+        if self.peek() == Some('.') && matches!(self.peek_second(), Some('0'..='9')) {
+            self.bump();
+            number.push('.');
+            self.digits(&mut number);
+            decimal = true;
+        }
+        if let Some(e @ 'e') | Some(e @ 'E') = self.peek() {
+            self.bump();
+            number.push(e);
+            if let Some(sign @ '+') | Some(sign @ '-') = self.peek() {
+                self.bump();
+                number.push(sign);
+            }
+            if self.digits(&mut number) == 0 {
+                return self.error("Invalid exponent");
+            }
+            return Ok(Token::Double(number));
+        }
+        Ok(if decimal {
+            Token::Decimal(number)
+        } else {
+            Token::Integer(number)
+        })
+    }
+}
@@ -0,0 +1,151 @@
+//! A parser and writer for SPARQL 1.1 queries, shared by the `graph-store` crate and its macros.
+
+mod ast;
+mod lexer;
+mod parser;
+mod rewrite;
+mod writer;
+
+pub use crate::ast::*;
+pub use crate::parser::parse_query;
+pub use crate::rewrite::VariableInUse;
+use std::fmt;
+
+/// A syntax error at a position in a query, both starting at 1.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.message, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn parses_and_writes_queries() {
+        let queries = [
+            "PREFIX ex: <http://example.org/>
+SELECT DISTINCT ?s (COUNT(*) AS ?n)
+FROM <http://example.org/g1>
+WHERE {
+  ?s a ex:Thing ; ex:p/ex:q* ?o , [ ex:r (1 -2.5 \"x\"@en) ] .
+  OPTIONAL { ?o ^ex:p|!(a|^ex:q) ?x }
+  { ?s ?p ?o } UNION { GRAPH ?g { ?s ?p ?o } } MINUS { ?s ex:hidden true }
+  FILTER(?o > 1 && !BOUND(?x) || ?o IN (1, 2) && NOT EXISTS { ?s ex:q ?o })
+  BIND(STRLEN(STR(?o)) * -2 AS ?length)
+  VALUES (?s ?t) { (ex:a UNDEF) (<http://example.org/b> \"\"\"b\"\"\"^^ex:t) }
+}
+GROUP BY ?s HAVING (COUNT(*) > 1) ORDER BY DESC(?n) ?s LIMIT 10 OFFSET 5",
+            "SELECT * { { SELECT ?s WHERE { ?s ?p ?o } LIMIT 1 } SERVICE SILENT <http://x/> {} }",
+            "CONSTRUCT { ?s <http://p> _:b } WHERE { ?s ?p ?o }",
+            "CONSTRUCT WHERE { ?s ?p ?o }",
+            "DESCRIBE <http://example.org/a> ?x",
+            "DESCRIBE *",
+            "ASK { ?s ?p ?o FILTER regex(?o, \"^a\", \"i\") }",
+            "SELECT (GROUP_CONCAT(DISTINCT ?o; SEPARATOR=\", \") AS ?os) { ?s ?p ?o }",
+        ];
+
+        for query in queries.iter() {
+            let parsed = parse_query(query).unwrap_or_else(|e| panic!("{} in {}", e, query));
+            let written = parsed.to_string();
+            assert_eq!(parse_query(&written), Ok(parsed), "{}", written);
+        }
+    }
+
+    #[test]
+    fn builds_ast() {
+        let query = parse_query("SELECT ?s WHERE { ?s a <http://example.org/T> } LIMIT 1").unwrap();
+
+        assert_eq!(
+            query,
+            Query {
+                prologue: vec![],
+                form: QueryForm::Select(Projection {
+                    modifier: None,
+                    items: vec![SelectItem::Variable("s".to_string())],
+                }),
+                dataset: vec![],
+                pattern: GroupPattern::Elements(vec![Element::Triples(vec![TriplePattern {
+                    subject: GraphNode::Term(Term::Variable("s".to_string())),
+                    properties: vec![Property {
+                        verb: Verb::Path(Path::A),
+                        objects: vec![GraphNode::Term(Term::Iri(Iri::Full(
+                            "http://example.org/T".to_string()
+                        )))],
+                    }],
+                }])]),
+                modifiers: Modifiers {
+                    limit: Some(1),
+                    ..Modifiers::default()
+                },
+                values: None,
+            }
+        );
+    }
+
+    #[test]
+    fn finds_projected_variables() {
+        let variables = |query| parse_query(query).unwrap().projected_variables();
+
+        assert_eq!(
+            variables("SELECT ?b (1 AS ?a) { ?a0 ?b ?c }"),
+            vec!["b", "a"]
+        );
+        assert_eq!(
+            variables(
+                "SELECT * { ?s ?p ?o OPTIONAL { ?o ?q ?x } MINUS { ?y ?z ?s } \
+                 FILTER(?w) BIND(1 AS ?v) } VALUES ?u { 1 }"
+            ),
+            vec!["s", "p", "o", "q", "x", "v", "u"]
+        );
+        assert!(variables("ASK { ?s ?p ?o }").is_empty());
+    }
+
+    #[test]
+    fn reports_errors() {
+        let error = |query| parse_query(query).unwrap_err();
+
+        assert_eq!(
+            error("SELECT ?s\nWHERE { ?s ?p }"),
+            SyntaxError {
+                line: 2,
+                column: 15,
+                message: "Expected an RDF term, found '}'".to_string(),
+            }
+        );
+        assert_eq!(
+            error("SELECT * { ?s ?p ?o } GROUP BY ?s").message,
+            "SELECT * is not allowed with GROUP BY"
+        );
+        assert_eq!(
+            error("SELECT (1 AS ?s) { ?s ?p ?o }").message,
+            "Variable ?s is already in scope"
+        );
+        assert_eq!(
+            error("ASK { FILTER(STRLEN(?a, ?b)) }").message,
+            "Wrong number of arguments for STRLEN: expected 1, found 2"
+        );
+        assert_eq!(
+            error("SELECT ?s { ?s ?p \"a }").message,
+            "Unterminated string"
+        );
+        assert_eq!(
+            error("SELECT ?s { } LIMIT 1 ?x").message,
+            "Expected end of input, found variable ?x"
+        );
+    }
+}
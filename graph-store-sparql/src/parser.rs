@@ -0,0 +1,1164 @@
+use crate::ast::*;
+use crate::lexer::{tokenize, Positioned, Token};
+use crate::SyntaxError;
+
+/// Built-in functions, other than aggregates and EXISTS, with their minimum and maximum
+/// number of arguments.
+const BUILT_INS: [(&str, usize, usize); 52] = [
+    ("STR", 1, 1),
+    ("LANG", 1, 1),
+    ("LANGMATCHES", 2, 2),
+    ("DATATYPE", 1, 1),
+    ("BOUND", 1, 1),
+    ("IRI", 1, 1),
+    ("URI", 1, 1),
+    ("BNODE", 0, 1),
+    ("RAND", 0, 0),
+    ("ABS", 1, 1),
+    ("CEIL", 1, 1),
+    ("FLOOR", 1, 1),
+    ("ROUND", 1, 1),
+    ("CONCAT", 0, usize::MAX),
+    ("SUBSTR", 2, 3),
+    ("STRLEN", 1, 1),
+    ("REPLACE", 3, 4),
+    ("UCASE", 1, 1),
+    ("LCASE", 1, 1),
+    ("ENCODE_FOR_URI", 1, 1),
+    ("CONTAINS", 2, 2),
+    ("STRSTARTS", 2, 2),
+    ("STRENDS", 2, 2),
+    ("STRBEFORE", 2, 2),
+    ("STRAFTER", 2, 2),
+    ("YEAR", 1, 1),
+    ("MONTH", 1, 1),
+    ("DAY", 1, 1),
+    ("HOURS", 1, 1),
+    ("MINUTES", 1, 1),
+    ("SECONDS", 1, 1),
+    ("TIMEZONE", 1, 1),
+    ("TZ", 1, 1),
+    ("NOW", 0, 0),
+    ("UUID", 0, 0),
+    ("STRUUID", 0, 0),
+    ("MD5", 1, 1),
+    ("SHA1", 1, 1),
+    ("SHA256", 1, 1),
+    ("SHA384", 1, 1),
+    ("SHA512", 1, 1),
+    ("COALESCE", 0, usize::MAX),
+    ("IF", 3, 3),
+    ("STRLANG", 2, 2),
+    ("STRDT", 2, 2),
+    ("sameTerm", 2, 2),
+    ("isIRI", 1, 1),
+    ("isURI", 1, 1),
+    ("isBLANK", 1, 1),
+    ("isLITERAL", 1, 1),
+    ("isNUMERIC", 1, 1),
+    ("REGEX", 2, 3),
+];
+
+const AGGREGATES: [&str; 7] = [
+    "COUNT",
+    "SUM",
+    "MIN",
+    "MAX",
+    "AVG",
+    "SAMPLE",
+    "GROUP_CONCAT",
+];
+
+/// Parses a SPARQL 1.1 query.
+pub fn parse_query(input: &str) -> Result<Query, SyntaxError> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        position: 0,
+    };
+    parser.query()
+}
+
+struct Parser {
+    tokens: Vec<Positioned>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> &Token {
+        let i = (self.position + offset).min(self.tokens.len() - 1);
+        &self.tokens[i].0
+    }
+
+    fn next(&mut self) -> Positioned {
+        let token = self.tokens[self.position].clone();
+        if token.0 != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, SyntaxError> {
+        let (_, line, column) = &self.tokens[self.position];
+        Err(SyntaxError {
+            line: *line,
+            column: *column,
+            message: message.to_string(),
+        })
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> Result<T, SyntaxError> {
+        self.error(&format!("Expected {}, found {}", expected, self.peek()))
+    }
+
+    fn is_word(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Token::Word(w) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_word(&mut self, keyword: &str) -> bool {
+        let found = self.is_word(keyword);
+        if found {
+            self.next();
+        }
+        found
+    }
+
+    fn expect_word(&mut self, keyword: &str) -> Result<(), SyntaxError> {
+        if self.eat_word(keyword) {
+            Ok(())
+        } else {
+            self.unexpected(&format!("'{}'", keyword))
+        }
+    }
+
+    fn is_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Token::Symbol(s) if *s == symbol)
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let found = self.is_symbol(symbol);
+        if found {
+            self.next();
+        }
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), SyntaxError> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            self.unexpected(&format!("'{}'", symbol))
+        }
+    }
+
+    fn is_nil(&self) -> bool {
+        self.is_symbol("(") && *self.peek_at(1) == Token::Symbol(")")
+    }
+
+    fn query(&mut self) -> Result<Query, SyntaxError> {
+        let prologue = self.prologue()?;
+        let (form, dataset, pattern) = if self.eat_word("SELECT") {
+            let (projection, aliases) = self.projection()?;
+            let dataset = self.dataset_clauses()?;
+            let pattern = self.where_clause()?;
+            check_aliases(&projection, &pattern, aliases)?;
+            self.check_grouping(&projection)?;
+            (QueryForm::Select(projection), dataset, pattern)
+        } else if self.eat_word("CONSTRUCT") {
+            if self.is_symbol("{") {
+                let template = self.construct_template()?;
+                let dataset = self.dataset_clauses()?;
+                let pattern = self.where_clause()?;
+                (QueryForm::Construct(Some(template)), dataset, pattern)
+            } else {
+                let dataset = self.dataset_clauses()?;
+                self.expect_word("WHERE")?;
+                let template = self.construct_template()?;
+                let elements = match template.is_empty() {
+                    true => vec![],
+                    false => vec![Element::Triples(template)],
+                };
+                (
+                    QueryForm::Construct(None),
+                    dataset,
+                    GroupPattern::Elements(elements),
+                )
+            }
+        } else if self.eat_word("DESCRIBE") {
+            let mut resources = vec![];
+            if !self.eat_symbol("*") {
+                while matches!(
+                    self.peek(),
+                    Token::Variable(_)
+                        | Token::IriRef(_)
+                        | Token::PrefixedName { .. }
+                        | Token::Namespace(_)
+                ) {
+                    resources.push(self.var_or_iri()?);
+                }
+                if resources.is_empty() {
+                    return self.unexpected("a variable, IRI or '*'");
+                }
+            }
+            let dataset = self.dataset_clauses()?;
+            let pattern = if self.is_word("WHERE") || self.is_symbol("{") {
+                self.where_clause()?
+            } else {
+                GroupPattern::Elements(vec![])
+            };
+            (QueryForm::Describe(resources), dataset, pattern)
+        } else if self.eat_word("ASK") {
+            let dataset = self.dataset_clauses()?;
+            let pattern = self.where_clause()?;
+            (QueryForm::Ask, dataset, pattern)
+        } else {
+            return self.unexpected("'SELECT', 'CONSTRUCT', 'DESCRIBE' or 'ASK'");
+        };
+        let modifiers = self.modifiers()?;
+        let values = self.values_clause()?;
+        if *self.peek() != Token::End {
+            return self.unexpected("end of input");
+        }
+        Ok(Query {
+            prologue,
+            form,
+            dataset,
+            pattern,
+            modifiers,
+            values,
+        })
+    }
+
+    fn prologue(&mut self) -> Result<Vec<Declaration>, SyntaxError> {
+        let mut declarations = vec![];
+        loop {
+            if self.eat_word("BASE") {
+                match self.peek().clone() {
+                    Token::IriRef(iri) => {
+                        self.next();
+                        declarations.push(Declaration::Base(iri));
+                    }
+                    _ => return self.unexpected("an IRI"),
+                }
+            } else if self.eat_word("PREFIX") {
+                let prefix = match self.peek().clone() {
+                    Token::Namespace(prefix) => prefix,
+                    _ => return self.unexpected("a prefix such as 'ex:'"),
+                };
+                self.next();
+                match self.peek().clone() {
+                    Token::IriRef(iri) => {
+                        self.next();
+                        declarations.push(Declaration::Prefix(prefix, iri));
+                    }
+                    _ => return self.unexpected("an IRI"),
+                }
+            } else {
+                return Ok(declarations);
+            }
+        }
+    }
+
+    /// Parses a projection, along with the positions of variables bound with AS.
+    fn projection(&mut self) -> Result<(Projection, Vec<Positioned>), SyntaxError> {
+        let modifier = if self.eat_word("DISTINCT") {
+            Some(SelectModifier::Distinct)
+        } else if self.eat_word("REDUCED") {
+            Some(SelectModifier::Reduced)
+        } else {
+            None
+        };
+        let mut items = vec![];
+        let mut aliases = vec![];
+        if !self.eat_symbol("*") {
+            loop {
+                match self.peek().clone() {
+                    Token::Variable(name) => {
+                        self.next();
+                        items.push(SelectItem::Variable(name));
+                    }
+                    Token::Symbol("(") => {
+                        self.next();
+                        let expression = self.expression()?;
+                        self.expect_word("AS")?;
+                        aliases.push(self.tokens[self.position].clone());
+                        let name = self.variable()?;
+                        self.expect_symbol(")")?;
+                        items.push(SelectItem::Expression(expression, name));
+                    }
+                    _ => break,
+                }
+            }
+            if items.is_empty() {
+                return self.unexpected("a variable, '(' or '*'");
+            }
+        }
+        Ok((Projection { modifier, items }, aliases))
+    }
+
+    fn check_grouping(&self, projection: &Projection) -> Result<(), SyntaxError> {
+        if projection.items.is_empty() && self.is_word("GROUP") {
+            return self.error("SELECT * is not allowed with GROUP BY");
+        }
+        Ok(())
+    }
+
+    fn dataset_clauses(&mut self) -> Result<Vec<DatasetClause>, SyntaxError> {
+        let mut clauses = vec![];
+        while self.eat_word("FROM") {
+            clauses.push(if self.eat_word("NAMED") {
+                DatasetClause::FromNamed(self.iri()?)
+            } else {
+                DatasetClause::From(self.iri()?)
+            });
+        }
+        Ok(clauses)
+    }
+
+    fn where_clause(&mut self) -> Result<GroupPattern, SyntaxError> {
+        self.eat_word("WHERE");
+        self.group_pattern()
+    }
+
+    fn sub_select(&mut self) -> Result<SubSelect, SyntaxError> {
+        self.expect_word("SELECT")?;
+        let (projection, aliases) = self.projection()?;
+        let pattern = self.where_clause()?;
+        check_aliases(&projection, &pattern, aliases)?;
+        self.check_grouping(&projection)?;
+        let modifiers = self.modifiers()?;
+        let values = self.values_clause()?;
+        Ok(SubSelect {
+            projection,
+            pattern,
+            modifiers,
+            values,
+        })
+    }
+
+    fn values_clause(&mut self) -> Result<Option<Values>, SyntaxError> {
+        if self.eat_word("VALUES") {
+            Ok(Some(self.data_block()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn modifiers(&mut self) -> Result<Modifiers, SyntaxError> {
+        let mut modifiers = Modifiers::default();
+        if self.eat_word("GROUP") {
+            self.expect_word("BY")?;
+            loop {
+                modifiers.group_by.push(match self.peek().clone() {
+                    Token::Variable(name) => {
+                        self.next();
+                        GroupCondition {
+                            expression: Expression::Term(Term::Variable(name)),
+                            variable: None,
+                        }
+                    }
+                    Token::Symbol("(") => {
+                        self.next();
+                        let expression = self.expression()?;
+                        let variable = match self.eat_word("AS") {
+                            true => Some(self.variable()?),
+                            false => None,
+                        };
+                        self.expect_symbol(")")?;
+                        GroupCondition {
+                            expression,
+                            variable,
+                        }
+                    }
+                    _ if self.is_constraint() => GroupCondition {
+                        expression: self.constraint()?,
+                        variable: None,
+                    },
+                    _ if modifiers.group_by.is_empty() => {
+                        return self.unexpected("a group condition")
+                    }
+                    _ => break,
+                });
+            }
+        }
+        if self.eat_word("HAVING") {
+            modifiers.having.push(self.constraint()?);
+            while self.is_constraint() {
+                modifiers.having.push(self.constraint()?);
+            }
+        }
+        if self.eat_word("ORDER") {
+            self.expect_word("BY")?;
+            loop {
+                let descending = self.is_word("DESC");
+                modifiers
+                    .order_by
+                    .push(if self.eat_word("ASC") || self.eat_word("DESC") {
+                        if !self.is_symbol("(") {
+                            return self.unexpected("'('");
+                        }
+                        OrderCondition {
+                            expression: self.constraint()?,
+                            descending,
+                        }
+                    } else if let Token::Variable(name) = self.peek().clone() {
+                        self.next();
+                        OrderCondition {
+                            expression: Expression::Term(Term::Variable(name)),
+                            descending,
+                        }
+                    } else if self.is_constraint() {
+                        OrderCondition {
+                            expression: self.constraint()?,
+                            descending,
+                        }
+                    } else if modifiers.order_by.is_empty() {
+                        return self.unexpected("an order condition");
+                    } else {
+                        break;
+                    });
+            }
+        }
+        for _ in 0..2 {
+            if modifiers.limit.is_none() && self.eat_word("LIMIT") {
+                modifiers.limit = Some(self.count()?);
+            } else if modifiers.offset.is_none() && self.eat_word("OFFSET") {
+                modifiers.offset = Some(self.count()?);
+            }
+        }
+        Ok(modifiers)
+    }
+
+    fn count(&mut self) -> Result<u64, SyntaxError> {
+        match self.peek().clone() {
+            Token::Integer(n) => match n.parse() {
+                Ok(n) => {
+                    self.next();
+                    Ok(n)
+                }
+                Err(_) => self.error("Number is too large"),
+            },
+            _ => self.unexpected("an integer"),
+        }
+    }
+
+    fn group_pattern(&mut self) -> Result<GroupPattern, SyntaxError> {
+        self.expect_symbol("{")?;
+        if self.is_word("SELECT") {
+            let select = self.sub_select()?;
+            self.expect_symbol("}")?;
+            return Ok(GroupPattern::SubSelect(Box::new(select)));
+        }
+        let mut elements = vec![];
+        loop {
+            if self.eat_symbol("}") {
+                return Ok(GroupPattern::Elements(elements));
+            }
+            let element = if self.is_symbol("{") {
+                let mut patterns = vec![self.group_pattern()?];
+                while self.eat_word("UNION") {
+                    patterns.push(self.group_pattern()?);
+                }
+                match patterns.len() {
+                    1 => Element::Group(patterns.pop().unwrap()),
+                    _ => Element::Union(patterns),
+                }
+            } else if self.eat_word("OPTIONAL") {
+                Element::Optional(self.group_pattern()?)
+            } else if self.eat_word("MINUS") {
+                Element::Minus(self.group_pattern()?)
+            } else if self.eat_word("GRAPH") {
+                let name = self.var_or_iri()?;
+                Element::Graph(name, self.group_pattern()?)
+            } else if self.eat_word("SERVICE") {
+                let silent = self.eat_word("SILENT");
+                let name = self.var_or_iri()?;
+                Element::Service {
+                    silent,
+                    name,
+                    pattern: self.group_pattern()?,
+                }
+            } else if self.eat_word("FILTER") {
+                Element::Filter(self.constraint()?)
+            } else if self.eat_word("BIND") {
+                self.expect_symbol("(")?;
+                let expression = self.expression()?;
+                self.expect_word("AS")?;
+                let mut in_scope = vec![];
+                for element in &elements {
+                    element.add_variables(&mut in_scope);
+                }
+                if let Token::Variable(name) = self.peek() {
+                    if in_scope.contains(name) {
+                        return self.error(&format!("Variable ?{} is already in scope", name));
+                    }
+                }
+                let name = self.variable()?;
+                self.expect_symbol(")")?;
+                Element::Bind(expression, name)
+            } else if self.eat_word("VALUES") {
+                Element::Values(self.data_block()?)
+            } else {
+                elements.push(Element::Triples(self.triples_block()?));
+                continue;
+            };
+            elements.push(element);
+            self.eat_symbol(".");
+        }
+    }
+
+    fn triples_block(&mut self) -> Result<Vec<TriplePattern>, SyntaxError> {
+        let mut triples = vec![self.triple_pattern(true)?];
+        while self.eat_symbol(".") {
+            if !self.starts_term() {
+                break;
+            }
+            triples.push(self.triple_pattern(true)?);
+        }
+        Ok(triples)
+    }
+
+    fn construct_template(&mut self) -> Result<Vec<TriplePattern>, SyntaxError> {
+        self.expect_symbol("{")?;
+        let mut triples = vec![];
+        while !self.eat_symbol("}") {
+            triples.push(self.triple_pattern(false)?);
+            if !self.eat_symbol(".") {
+                self.expect_symbol("}")?;
+                break;
+            }
+        }
+        Ok(triples)
+    }
+
+    fn starts_term(&self) -> bool {
+        match self.peek() {
+            Token::Variable(_)
+            | Token::IriRef(_)
+            | Token::PrefixedName { .. }
+            | Token::Namespace(_)
+            | Token::BlankNodeLabel(_)
+            | Token::String(_)
+            | Token::Integer(_)
+            | Token::Decimal(_)
+            | Token::Double(_) => true,
+            Token::Word(w) => w == "true" || w == "false",
+            Token::Symbol(s) => ["(", "[", "+", "-"].contains(s),
+            _ => false,
+        }
+    }
+
+    fn starts_verb(&self, path: bool) -> bool {
+        match self.peek() {
+            Token::Variable(_)
+            | Token::IriRef(_)
+            | Token::PrefixedName { .. }
+            | Token::Namespace(_) => true,
+            Token::Word(w) => w == "a",
+            Token::Symbol(s) => path && ["^", "!", "("].contains(s),
+            _ => false,
+        }
+    }
+
+    /// Parses a subject with its properties, where properties may have paths if allowed.
+    fn triple_pattern(&mut self, path: bool) -> Result<TriplePattern, SyntaxError> {
+        if !self.starts_term() {
+            return self.unexpected("a subject");
+        }
+        let subject = self.graph_node(path)?;
+        let properties = match subject {
+            GraphNode::Term(_) => self.property_list(path)?,
+            _ if self.starts_verb(path) => self.property_list(path)?,
+            _ => vec![],
+        };
+        Ok(TriplePattern {
+            subject,
+            properties,
+        })
+    }
+
+    fn property_list(&mut self, path: bool) -> Result<Vec<Property>, SyntaxError> {
+        let mut properties = vec![];
+        loop {
+            let verb = self.verb(path)?;
+            let mut objects = vec![self.graph_node(path)?];
+            while self.eat_symbol(",") {
+                objects.push(self.graph_node(path)?);
+            }
+            properties.push(Property { verb, objects });
+            if !self.eat_symbol(";") {
+                return Ok(properties);
+            }
+            while self.eat_symbol(";") {}
+            if !self.starts_verb(path) {
+                return Ok(properties);
+            }
+        }
+    }
+
+    fn verb(&mut self, path: bool) -> Result<Verb, SyntaxError> {
+        if let Token::Variable(name) = self.peek().clone() {
+            self.next();
+            return Ok(Verb::Variable(name));
+        }
+        if !self.starts_verb(path) {
+            return self.unexpected("a predicate");
+        }
+        Ok(Verb::Path(match path {
+            true => self.path()?,
+            false => self.path_primary()?,
+        }))
+    }
+
+    fn graph_node(&mut self, path: bool) -> Result<GraphNode, SyntaxError> {
+        if self.is_nil() {
+            self.next();
+            self.next();
+            Ok(GraphNode::Term(Term::Nil))
+        } else if self.eat_symbol("(") {
+            let mut nodes = vec![];
+            while !self.eat_symbol(")") {
+                nodes.push(self.graph_node(path)?);
+            }
+            Ok(GraphNode::Collection(nodes))
+        } else if self.is_symbol("[") && *self.peek_at(1) == Token::Symbol("]") {
+            self.next();
+            self.next();
+            Ok(GraphNode::Term(Term::Anonymous))
+        } else if self.eat_symbol("[") {
+            let properties = self.property_list(path)?;
+            self.expect_symbol("]")?;
+            Ok(GraphNode::Properties(properties))
+        } else {
+            Ok(GraphNode::Term(self.term()?))
+        }
+    }
+
+    fn term(&mut self) -> Result<Term, SyntaxError> {
+        match self.peek().clone() {
+            Token::Variable(name) => {
+                self.next();
+                Ok(Term::Variable(name))
+            }
+            Token::BlankNodeLabel(label) => {
+                self.next();
+                Ok(Term::BlankNode(label))
+            }
+            Token::IriRef(_) | Token::PrefixedName { .. } | Token::Namespace(_) => {
+                Ok(Term::Iri(self.iri()?))
+            }
+            _ => match self.literal(true)? {
+                Some(literal) => Ok(Term::Literal(literal)),
+                None => self.unexpected("an RDF term"),
+            },
+        }
+    }
+
+    /// Parses a literal, if one follows, where numbers may have a sign if allowed.
+    fn literal(&mut self, signed: bool) -> Result<Option<Literal>, SyntaxError> {
+        let sign = match self.peek() {
+            Token::Symbol(s) if signed && (*s == "+" || *s == "-") => {
+                match self.peek_at(1) {
+                    Token::Integer(_) | Token::Decimal(_) | Token::Double(_) => (),
+                    _ => return Ok(None),
+                }
+                let sign = s.to_string();
+                self.next();
+                sign
+            }
+            _ => String::new(),
+        };
+        let literal = match self.peek().clone() {
+            Token::Integer(n) => Literal::Integer(sign + &n),
+            Token::Decimal(n) => Literal::Decimal(sign + &n),
+            Token::Double(n) => Literal::Double(sign + &n),
+            Token::Word(w) if w == "true" || w == "false" => Literal::Boolean(w == "true"),
+            Token::String(value) => {
+                self.next();
+                let (language, datatype) = match self.peek().clone() {
+                    Token::LanguageTag(tag) => {
+                        self.next();
+                        (Some(tag), None)
+                    }
+                    Token::Symbol("^^") => {
+                        self.next();
+                        (None, Some(self.iri()?))
+                    }
+                    _ => (None, None),
+                };
+                return Ok(Some(Literal::String {
+                    value,
+                    language,
+                    datatype,
+                }));
+            }
+            _ => return Ok(None),
+        };
+        self.next();
+        Ok(Some(literal))
+    }
+
+    fn iri(&mut self) -> Result<Iri, SyntaxError> {
+        let iri = match self.peek().clone() {
+            Token::IriRef(iri) => Iri::Full(iri),
+            Token::PrefixedName { prefix, local } => Iri::Prefixed { prefix, local },
+            Token::Namespace(prefix) => Iri::Prefixed {
+                prefix,
+                local: String::new(),
+            },
+            _ => return self.unexpected("an IRI"),
+        };
+        self.next();
+        Ok(iri)
+    }
+
+    fn variable(&mut self) -> Result<String, SyntaxError> {
+        match self.peek().clone() {
+            Token::Variable(name) => {
+                self.next();
+                Ok(name)
+            }
+            _ => self.unexpected("a variable"),
+        }
+    }
+
+    fn var_or_iri(&mut self) -> Result<VarOrIri, SyntaxError> {
+        match self.peek() {
+            Token::Variable(_) => Ok(VarOrIri::Variable(self.variable()?)),
+            _ => Ok(VarOrIri::Iri(self.iri()?)),
+        }
+    }
+
+    fn path(&mut self) -> Result<Path, SyntaxError> {
+        let mut alternatives = vec![self.path_sequence()?];
+        while self.eat_symbol("|") {
+            alternatives.push(self.path_sequence()?);
+        }
+        Ok(match alternatives.len() {
+            1 => alternatives.pop().unwrap(),
+            _ => Path::Alternative(alternatives),
+        })
+    }
+
+    fn path_sequence(&mut self) -> Result<Path, SyntaxError> {
+        let mut steps = vec![self.path_step()?];
+        while self.eat_symbol("/") {
+            steps.push(self.path_step()?);
+        }
+        Ok(match steps.len() {
+            1 => steps.pop().unwrap(),
+            _ => Path::Sequence(steps),
+        })
+    }
+
+    fn path_step(&mut self) -> Result<Path, SyntaxError> {
+        let inverse = self.eat_symbol("^");
+        let primary = if self.eat_symbol("(") {
+            let path = self.path()?;
+            self.expect_symbol(")")?;
+            path
+        } else if self.eat_symbol("!") {
+            let mut negated = vec![];
+            if self.eat_symbol("(") {
+                if !self.eat_symbol(")") {
+                    negated.push(self.negated_step()?);
+                    while self.eat_symbol("|") {
+                        negated.push(self.negated_step()?);
+                    }
+                    self.expect_symbol(")")?;
+                }
+            } else {
+                negated.push(self.negated_step()?);
+            }
+            Path::Negated(negated)
+        } else {
+            self.path_primary()?
+        };
+        let number_follows = matches!(
+            self.peek_at(1),
+            Token::Integer(_) | Token::Decimal(_) | Token::Double(_)
+        );
+        let step = if self.eat_symbol("*") {
+            Path::ZeroOrMore(Box::new(primary))
+        } else if !number_follows && self.eat_symbol("+") {
+            Path::OneOrMore(Box::new(primary))
+        } else if self.eat_symbol("?") {
+            Path::ZeroOrOne(Box::new(primary))
+        } else {
+            primary
+        };
+        Ok(match inverse {
+            true => Path::Inverse(Box::new(step)),
+            false => step,
+        })
+    }
+
+    fn negated_step(&mut self) -> Result<Path, SyntaxError> {
+        match self.eat_symbol("^") {
+            true => Ok(Path::Inverse(Box::new(self.path_primary()?))),
+            false => self.path_primary(),
+        }
+    }
+
+    /// Parses an IRI or `a`.
+    fn path_primary(&mut self) -> Result<Path, SyntaxError> {
+        if let Token::Word(w) = self.peek() {
+            if w == "a" {
+                self.next();
+                return Ok(Path::A);
+            }
+        }
+        match self.peek() {
+            Token::IriRef(_) | Token::PrefixedName { .. } | Token::Namespace(_) => {
+                Ok(Path::Iri(self.iri()?))
+            }
+            _ => self.unexpected("a predicate"),
+        }
+    }
+
+    fn data_block(&mut self) -> Result<Values, SyntaxError> {
+        let mut variables = vec![];
+        let mut rows = vec![];
+        if let Token::Variable(_) = self.peek() {
+            variables.push(self.variable()?);
+            self.expect_symbol("{")?;
+            while !self.eat_symbol("}") {
+                rows.push(vec![self.data_value()?]);
+            }
+            return Ok(Values { variables, rows });
+        }
+        self.expect_symbol("(")?;
+        while !self.eat_symbol(")") {
+            variables.push(self.variable()?);
+        }
+        self.expect_symbol("{")?;
+        while !self.eat_symbol("}") {
+            self.expect_symbol("(")?;
+            let mut row = vec![];
+            while !self.is_symbol(")") {
+                row.push(self.data_value()?);
+            }
+            if row.len() != variables.len() {
+                return self.error(&format!(
+                    "Expected {} values, found {}",
+                    variables.len(),
+                    row.len()
+                ));
+            }
+            self.next();
+            rows.push(row);
+        }
+        Ok(Values { variables, rows })
+    }
+
+    fn data_value(&mut self) -> Result<Option<Term>, SyntaxError> {
+        if self.eat_word("UNDEF") {
+            return Ok(None);
+        }
+        match self.peek() {
+            Token::IriRef(_) | Token::PrefixedName { .. } | Token::Namespace(_) => {
+                Ok(Some(Term::Iri(self.iri()?)))
+            }
+            _ => match self.literal(true)? {
+                Some(literal) => Ok(Some(Term::Literal(literal))),
+                None => self.unexpected("an IRI, literal or 'UNDEF'"),
+            },
+        }
+    }
+
+    fn is_constraint(&self) -> bool {
+        match self.peek() {
+            Token::Symbol("(") => true,
+            Token::IriRef(_) | Token::PrefixedName { .. } | Token::Namespace(_) => true,
+            Token::Word(w) => is_built_in(w),
+            _ => false,
+        }
+    }
+
+    /// Parses a bracketed expression, a built-in call or a function call.
+    fn constraint(&mut self) -> Result<Expression, SyntaxError> {
+        match self.peek().clone() {
+            Token::Symbol("(") => self.bracketed(),
+            Token::Word(w) if is_built_in(&w) => self.built_in_call(),
+            Token::IriRef(_) | Token::PrefixedName { .. } | Token::Namespace(_) => {
+                let iri = self.iri()?;
+                if !self.is_symbol("(") {
+                    return self.unexpected("'('");
+                }
+                self.function_call(iri)
+            }
+            _ => self.unexpected("a constraint"),
+        }
+    }
+
+    fn bracketed(&mut self) -> Result<Expression, SyntaxError> {
+        self.expect_symbol("(")?;
+        let expression = self.expression()?;
+        self.expect_symbol(")")?;
+        Ok(expression)
+    }
+
+    fn expression(&mut self) -> Result<Expression, SyntaxError> {
+        let mut left = self.and_expression()?;
+        while self.eat_symbol("||") {
+            left = Expression::Or(Box::new(left), Box::new(self.and_expression()?));
+        }
+        Ok(left)
+    }
+
+    fn and_expression(&mut self) -> Result<Expression, SyntaxError> {
+        let mut left = self.relational_expression()?;
+        while self.eat_symbol("&&") {
+            left = Expression::And(Box::new(left), Box::new(self.relational_expression()?));
+        }
+        Ok(left)
+    }
+
+    fn relational_expression(&mut self) -> Result<Expression, SyntaxError> {
+        let left = Box::new(self.additive_expression()?);
+        if self.eat_word("IN") {
+            return Ok(Expression::In(left, self.expression_list()?));
+        }
+        if self.is_word("NOT")
+            && matches!(self.peek_at(1), Token::Word(w) if w.eq_ignore_ascii_case("IN"))
+        {
+            self.next();
+            self.next();
+            return Ok(Expression::NotIn(left, self.expression_list()?));
+        }
+        let operator: fn(_, _) -> _ = match self.peek() {
+            Token::Symbol("=") => Expression::Equal,
+            Token::Symbol("!=") => Expression::NotEqual,
+            Token::Symbol("<") => Expression::Less,
+            Token::Symbol(">") => Expression::Greater,
+            Token::Symbol("<=") => Expression::LessOrEqual,
+            Token::Symbol(">=") => Expression::GreaterOrEqual,
+            _ => return Ok(*left),
+        };
+        self.next();
+        Ok(operator(left, Box::new(self.additive_expression()?)))
+    }
+
+    fn additive_expression(&mut self) -> Result<Expression, SyntaxError> {
+        let mut left = self.multiplicative_expression()?;
+        loop {
+            let operator: fn(_, _) -> _ = match self.peek() {
+                Token::Symbol("+") => Expression::Add,
+                Token::Symbol("-") => Expression::Subtract,
+                _ => return Ok(left),
+            };
+            self.next();
+            left = operator(Box::new(left), Box::new(self.multiplicative_expression()?));
+        }
+    }
+
+    fn multiplicative_expression(&mut self) -> Result<Expression, SyntaxError> {
+        let mut left = self.unary_expression()?;
+        loop {
+            let operator: fn(_, _) -> _ = match self.peek() {
+                Token::Symbol("*") => Expression::Multiply,
+                Token::Symbol("/") => Expression::Divide,
+                _ => return Ok(left),
+            };
+            self.next();
+            left = operator(Box::new(left), Box::new(self.unary_expression()?));
+        }
+    }
+
+    fn unary_expression(&mut self) -> Result<Expression, SyntaxError> {
+        let operator: fn(_) -> _ = match self.peek() {
+            Token::Symbol("!") => Expression::Not,
+            Token::Symbol("+") => Expression::Plus,
+            Token::Symbol("-") => Expression::Minus,
+            _ => return self.primary_expression(),
+        };
+        self.next();
+        Ok(operator(Box::new(self.primary_expression()?)))
+    }
+
+    fn primary_expression(&mut self) -> Result<Expression, SyntaxError> {
+        match self.peek().clone() {
+            Token::Symbol("(") => self.bracketed(),
+            Token::Variable(name) => {
+                self.next();
+                Ok(Expression::Term(Term::Variable(name)))
+            }
+            Token::IriRef(_) | Token::PrefixedName { .. } | Token::Namespace(_) => {
+                let iri = self.iri()?;
+                match self.is_symbol("(") {
+                    true => self.function_call(iri),
+                    false => Ok(Expression::Term(Term::Iri(iri))),
+                }
+            }
+            Token::Word(w) if w != "true" && w != "false" => self.built_in_call(),
+            _ => match self.literal(false)? {
+                Some(literal) => Ok(Expression::Term(Term::Literal(literal))),
+                None => self.unexpected("an expression"),
+            },
+        }
+    }
+
+    fn expression_list(&mut self) -> Result<Vec<Expression>, SyntaxError> {
+        if self.is_nil() {
+            self.next();
+            self.next();
+            return Ok(vec![]);
+        }
+        self.expect_symbol("(")?;
+        let mut expressions = vec![self.expression()?];
+        while self.eat_symbol(",") {
+            expressions.push(self.expression()?);
+        }
+        self.expect_symbol(")")?;
+        Ok(expressions)
+    }
+
+    fn function_call(&mut self, iri: Iri) -> Result<Expression, SyntaxError> {
+        if self.is_nil() {
+            self.next();
+            self.next();
+            return Ok(Expression::Function {
+                iri,
+                distinct: false,
+                arguments: vec![],
+            });
+        }
+        self.expect_symbol("(")?;
+        let distinct = self.eat_word("DISTINCT");
+        let mut arguments = vec![self.expression()?];
+        while self.eat_symbol(",") {
+            arguments.push(self.expression()?);
+        }
+        self.expect_symbol(")")?;
+        Ok(Expression::Function {
+            iri,
+            distinct,
+            arguments,
+        })
+    }
+
+    fn built_in_call(&mut self) -> Result<Expression, SyntaxError> {
+        let word = match self.peek().clone() {
+            Token::Word(w) => w,
+            _ => return self.unexpected("a function"),
+        };
+        if let Some(name) = AGGREGATES.iter().find(|a| a.eq_ignore_ascii_case(&word)) {
+            self.next();
+            return self.aggregate(name);
+        }
+        if word.eq_ignore_ascii_case("EXISTS") {
+            self.next();
+            return Ok(Expression::Exists(Box::new(self.group_pattern()?)));
+        }
+        if word.eq_ignore_ascii_case("NOT") {
+            self.next();
+            self.expect_word("EXISTS")?;
+            return Ok(Expression::NotExists(Box::new(self.group_pattern()?)));
+        }
+        let (name, min, max) = match BUILT_INS
+            .iter()
+            .find(|(name, _, _)| name.eq_ignore_ascii_case(&word))
+        {
+            Some(built_in) => *built_in,
+            None => return self.error(&format!("Unknown function '{}'", word)),
+        };
+        let position = self.position;
+        self.next();
+        if name == "BOUND" {
+            self.expect_symbol("(")?;
+            let variable = self.variable()?;
+            self.expect_symbol(")")?;
+            return Ok(Expression::BuiltIn(
+                name.to_string(),
+                vec![Expression::Term(Term::Variable(variable))],
+            ));
+        }
+        if !self.is_symbol("(") {
+            return self.unexpected("'('");
+        }
+        let arguments = self.expression_list()?;
+        if arguments.len() < min || arguments.len() > max {
+            self.position = position;
+            let expected = match (min, max) {
+                (min, max) if min == max => min.to_string(),
+                (min, usize::MAX) => format!("at least {}", min),
+                (min, max) => format!("{} to {}", min, max),
+            };
+            return self.error(&format!(
+                "Wrong number of arguments for {}: expected {}, found {}",
+                name,
+                expected,
+                arguments.len()
+            ));
+        }
+        Ok(Expression::BuiltIn(name.to_string(), arguments))
+    }
+
+    fn aggregate(&mut self, name: &str) -> Result<Expression, SyntaxError> {
+        self.expect_symbol("(")?;
+        let distinct = self.eat_word("DISTINCT");
+        let argument = if name == "COUNT" && self.eat_symbol("*") {
+            None
+        } else {
+            Some(Box::new(self.expression()?))
+        };
+        let separator = if name == "GROUP_CONCAT" && self.eat_symbol(";") {
+            self.expect_word("SEPARATOR")?;
+            self.expect_symbol("=")?;
+            match self.peek().clone() {
+                Token::String(separator) => {
+                    self.next();
+                    Some(separator)
+                }
+                _ => return self.unexpected("a string"),
+            }
+        } else {
+            None
+        };
+        self.expect_symbol(")")?;
+        Ok(Expression::Aggregate(Aggregate {
+            name: name.to_string(),
+            distinct,
+            argument,
+            separator,
+        }))
+    }
+}
+
+fn is_built_in(word: &str) -> bool {
+    ["EXISTS", "NOT"]
+        .iter()
+        .chain(AGGREGATES.iter())
+        .chain(BUILT_INS.iter().map(|(name, _, _)| name))
+        .any(|name| name.eq_ignore_ascii_case(word))
+}
+
+/// Checks that variables bound with AS in a projection are not already in scope.
+fn check_aliases(
+    projection: &Projection,
+    pattern: &GroupPattern,
+    aliases: Vec<Positioned>,
+) -> Result<(), SyntaxError> {
+    let mut in_scope = pattern.variables();
+    let mut aliases = aliases.into_iter();
+    for item in &projection.items {
+        match item {
+            SelectItem::Variable(name) => in_scope.push(name.to_string()),
+            SelectItem::Expression(_, name) => {
+                let (_, line, column) = aliases.next().unwrap();
+                if in_scope.contains(name) {
+                    return Err(SyntaxError {
+                        line,
+                        column,
+                        message: format!("Variable ?{} is already in scope", name),
+                    });
+                }
+                in_scope.push(name.to_string());
+            }
+        }
+    }
+    Ok(())
+}
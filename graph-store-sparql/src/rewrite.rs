@@ -0,0 +1,347 @@
+use crate::ast::*;
+use std::fmt;
+
+/// A variable that could not be renamed, because the new name is already in use.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct VariableInUse(pub String);
+
+impl fmt::Display for VariableInUse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Variable ?{} is already in use", self.0)
+    }
+}
+
+impl std::error::Error for VariableInUse {}
+
+impl Query {
+    /// Limits the number of results, keeping an existing limit if it is lower.
+    pub fn with_limit(mut self, limit: u64) -> Query {
+        self.modifiers.limit = Some(self.modifiers.limit.map_or(limit, |l| l.min(limit)));
+        self
+    }
+
+    /// Skips a number of results, replacing an existing offset.
+    pub fn with_offset(mut self, offset: u64) -> Query {
+        self.modifiers.offset = Some(offset);
+        self
+    }
+
+    /// Orders the results, after existing order conditions.
+    pub fn with_order_by(mut self, condition: OrderCondition) -> Query {
+        self.modifiers.order_by.push(condition);
+        self
+    }
+
+    /// Removes duplicate results of a SELECT query.
+    pub fn with_distinct(mut self) -> Query {
+        if let QueryForm::Select(projection) = &mut self.form {
+            projection.modifier = Some(SelectModifier::Distinct);
+        }
+        self
+    }
+
+    /// Adds a FILTER to the WHERE clause.
+    pub fn with_filter(mut self, expression: Expression) -> Query {
+        self.materialize_template();
+        let pattern = std::mem::replace(&mut self.pattern, GroupPattern::Elements(vec![]));
+        let mut elements = match pattern {
+            GroupPattern::Elements(elements) => elements,
+            select => vec![Element::Group(select)],
+        };
+        elements.push(Element::Filter(expression));
+        self.pattern = GroupPattern::Elements(elements);
+        self
+    }
+
+    /// Matches the WHERE clause in a named graph, or in any named graph bound to a variable.
+    pub fn in_graph(mut self, graph: VarOrIri) -> Query {
+        self.materialize_template();
+        let pattern = std::mem::replace(&mut self.pattern, GroupPattern::Elements(vec![]));
+        self.pattern = GroupPattern::Elements(vec![Element::Graph(graph, pattern)]);
+        self
+    }
+
+    /// Renames a variable everywhere in the query, unless the new name is already in use.
+    pub fn with_renamed_variable(mut self, from: &str, to: &str) -> Result<Query, VariableInUse> {
+        if from == to {
+            return Ok(self);
+        }
+        let mut in_use = false;
+        visit_query(&mut self, &mut |name: &mut String| in_use |= name == to);
+        if in_use {
+            return Err(VariableInUse(to.to_string()));
+        }
+        visit_query(&mut self, &mut |name: &mut String| {
+            if name == from {
+                *name = to.to_string();
+            }
+        });
+        Ok(self)
+    }
+
+    /// Writes out the template of a `CONSTRUCT WHERE` query, so that its pattern can change.
+    fn materialize_template(&mut self) {
+        if let (QueryForm::Construct(None), GroupPattern::Elements(elements)) =
+            (&self.form, &self.pattern)
+        {
+            let template = elements
+                .iter()
+                .flat_map(|element| match element {
+                    Element::Triples(triples) => triples.clone(),
+                    _ => vec![],
+                })
+                .collect();
+            self.form = QueryForm::Construct(Some(template));
+        }
+    }
+}
+
+type Visitor<'a> = dyn FnMut(&mut String) + 'a;
+
+fn visit_query(query: &mut Query, f: &mut Visitor) {
+    match &mut query.form {
+        QueryForm::Select(projection) => visit_projection(projection, f),
+        QueryForm::Construct(Some(template)) => {
+            for triple in template {
+                visit_triple(triple, f);
+            }
+        }
+        QueryForm::Construct(None) | QueryForm::Ask => (),
+        QueryForm::Describe(resources) => {
+            for resource in resources {
+                visit_var_or_iri(resource, f);
+            }
+        }
+    }
+    visit_pattern(&mut query.pattern, f);
+    visit_modifiers(&mut query.modifiers, f);
+    if let Some(values) = &mut query.values {
+        visit_values(values, f);
+    }
+}
+
+fn visit_projection(projection: &mut Projection, f: &mut Visitor) {
+    for item in &mut projection.items {
+        match item {
+            SelectItem::Variable(name) => f(name),
+            SelectItem::Expression(expression, name) => {
+                visit_expression(expression, f);
+                f(name);
+            }
+        }
+    }
+}
+
+fn visit_modifiers(modifiers: &mut Modifiers, f: &mut Visitor) {
+    for condition in &mut modifiers.group_by {
+        visit_expression(&mut condition.expression, f);
+        if let Some(name) = &mut condition.variable {
+            f(name);
+        }
+    }
+    for expression in &mut modifiers.having {
+        visit_expression(expression, f);
+    }
+    for condition in &mut modifiers.order_by {
+        visit_expression(&mut condition.expression, f);
+    }
+}
+
+fn visit_pattern(pattern: &mut GroupPattern, f: &mut Visitor) {
+    match pattern {
+        GroupPattern::Elements(elements) => {
+            for element in elements {
+                visit_element(element, f);
+            }
+        }
+        GroupPattern::SubSelect(select) => {
+            visit_projection(&mut select.projection, f);
+            visit_pattern(&mut select.pattern, f);
+            visit_modifiers(&mut select.modifiers, f);
+            if let Some(values) = &mut select.values {
+                visit_values(values, f);
+            }
+        }
+    }
+}
+
+fn visit_element(element: &mut Element, f: &mut Visitor) {
+    match element {
+        Element::Triples(triples) => {
+            for triple in triples {
+                visit_triple(triple, f);
+            }
+        }
+        Element::Group(pattern) | Element::Optional(pattern) | Element::Minus(pattern) => {
+            visit_pattern(pattern, f)
+        }
+        Element::Union(patterns) => {
+            for pattern in patterns {
+                visit_pattern(pattern, f);
+            }
+        }
+        Element::Graph(name, pattern) | Element::Service { name, pattern, .. } => {
+            visit_var_or_iri(name, f);
+            visit_pattern(pattern, f);
+        }
+        Element::Filter(expression) => visit_expression(expression, f),
+        Element::Bind(expression, name) => {
+            visit_expression(expression, f);
+            f(name);
+        }
+        Element::Values(values) => visit_values(values, f),
+    }
+}
+
+fn visit_triple(triple: &mut TriplePattern, f: &mut Visitor) {
+    visit_node(&mut triple.subject, f);
+    visit_properties(&mut triple.properties, f);
+}
+
+fn visit_properties(properties: &mut [Property], f: &mut Visitor) {
+    for property in properties {
+        if let Verb::Variable(name) = &mut property.verb {
+            f(name);
+        }
+        for object in &mut property.objects {
+            visit_node(object, f);
+        }
+    }
+}
+
+fn visit_node(node: &mut GraphNode, f: &mut Visitor) {
+    match node {
+        GraphNode::Term(term) => visit_term(term, f),
+        GraphNode::Collection(nodes) => {
+            for node in nodes {
+                visit_node(node, f);
+            }
+        }
+        GraphNode::Properties(properties) => visit_properties(properties, f),
+    }
+}
+
+fn visit_term(term: &mut Term, f: &mut Visitor) {
+    if let Term::Variable(name) = term {
+        f(name);
+    }
+}
+
+fn visit_var_or_iri(value: &mut VarOrIri, f: &mut Visitor) {
+    if let VarOrIri::Variable(name) = value {
+        f(name);
+    }
+}
+
+fn visit_values(values: &mut Values, f: &mut Visitor) {
+    for name in &mut values.variables {
+        f(name);
+    }
+}
+
+fn visit_expression(expression: &mut Expression, f: &mut Visitor) {
+    use Expression::*;
+    match expression {
+        Term(term) => visit_term(term, f),
+        Or(a, b)
+        | And(a, b)
+        | Equal(a, b)
+        | NotEqual(a, b)
+        | Less(a, b)
+        | Greater(a, b)
+        | LessOrEqual(a, b)
+        | GreaterOrEqual(a, b)
+        | Add(a, b)
+        | Subtract(a, b)
+        | Multiply(a, b)
+        | Divide(a, b) => {
+            visit_expression(a, f);
+            visit_expression(b, f);
+        }
+        In(a, list) | NotIn(a, list) => {
+            visit_expression(a, f);
+            for expression in list {
+                visit_expression(expression, f);
+            }
+        }
+        Plus(a) | Minus(a) | Not(a) => visit_expression(a, f),
+        BuiltIn(_, arguments) | Function { arguments, .. } => {
+            for argument in arguments {
+                visit_expression(argument, f);
+            }
+        }
+        Exists(pattern) | NotExists(pattern) => visit_pattern(pattern, f),
+        Aggregate(aggregate) => {
+            if let Some(argument) = &mut aggregate.argument {
+                visit_expression(argument, f);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn rewrites_queries() {
+        let query = parse_query(
+            "SELECT ?s (COUNT(?o) AS ?n) WHERE { ?s ?p ?o FILTER(?o != ?s) } GROUP BY ?s LIMIT 5",
+        )
+        .unwrap()
+        .with_limit(10)
+        .with_offset(20)
+        .with_renamed_variable("s", "subject")
+        .unwrap()
+        .with_order_by(OrderCondition {
+            expression: Expression::Term(Term::Variable("n".to_string())),
+            descending: true,
+        })
+        .in_graph(VarOrIri::Variable("g".to_string()));
+
+        assert_eq!(
+            query.to_string(),
+            "SELECT ?subject (COUNT(?o) AS ?n)
+WHERE {
+  GRAPH ?g {
+    ?subject ?p ?o .
+    FILTER(?o != ?subject)
+  }
+}
+GROUP BY ?subject
+ORDER BY DESC(?n)
+LIMIT 5
+OFFSET 20"
+        );
+    }
+
+    #[test]
+    fn keeps_variables_in_use() {
+        let query = parse_query("SELECT ?s WHERE { ?s ?p ?o }").unwrap();
+
+        assert_eq!(
+            query.clone().with_renamed_variable("s", "o"),
+            Err(VariableInUse("o".to_string()))
+        );
+        assert_eq!(query.clone().with_renamed_variable("s", "s"), Ok(query));
+    }
+
+    #[test]
+    fn keeps_construct_templates() {
+        let query = parse_query("CONSTRUCT WHERE { ?s a ?o }")
+            .unwrap()
+            .in_graph(VarOrIri::Iri(Iri::Full("http://example.org/g".to_string())));
+
+        assert_eq!(
+            query.to_string(),
+            "CONSTRUCT {
+  ?s a ?o .
+}
+WHERE {
+  GRAPH <http://example.org/g> {
+    ?s a ?o .
+  }
+}"
+        );
+    }
+}
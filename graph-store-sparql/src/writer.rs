@@ -0,0 +1,708 @@
+use crate::ast::*;
+use std::fmt;
+
+/// Writes queries as text, with nested groups indented.
+struct Writer {
+    output: String,
+    indent: usize,
+}
+
+impl Writer {
+    fn new() -> Writer {
+        Writer {
+            output: String::new(),
+            indent: 0,
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+
+    fn newline(&mut self) {
+        self.output.push('\n');
+        self.output.push_str(&"  ".repeat(self.indent));
+    }
+
+    fn query(&mut self, query: &Query) {
+        for declaration in &query.prologue {
+            match declaration {
+                Declaration::Base(iri) => self.write(&format!("BASE <{}>", iri)),
+                Declaration::Prefix(prefix, iri) => {
+                    self.write(&format!("PREFIX {}: <{}>", prefix, iri))
+                }
+            }
+            self.newline();
+        }
+        match &query.form {
+            QueryForm::Select(projection) => self.projection(projection),
+            QueryForm::Construct(Some(template)) => {
+                self.write("CONSTRUCT ");
+                self.triples_block(template);
+            }
+            QueryForm::Construct(None) => self.write("CONSTRUCT"),
+            QueryForm::Describe(resources) => {
+                self.write("DESCRIBE");
+                if resources.is_empty() {
+                    self.write(" *");
+                }
+                for resource in resources {
+                    self.write(" ");
+                    self.var_or_iri(resource);
+                }
+            }
+            QueryForm::Ask => self.write("ASK"),
+        }
+        for clause in &query.dataset {
+            self.newline();
+            let iri = match clause {
+                DatasetClause::From(iri) => {
+                    self.write("FROM ");
+                    iri
+                }
+                DatasetClause::FromNamed(iri) => {
+                    self.write("FROM NAMED ");
+                    iri
+                }
+            };
+            self.iri(iri);
+        }
+        match &query.form {
+            QueryForm::Construct(None) => {
+                self.newline();
+                self.write("WHERE ");
+                let triples = match &query.pattern {
+                    GroupPattern::Elements(elements) => elements
+                        .iter()
+                        .flat_map(|element| match element {
+                            Element::Triples(triples) => triples.clone(),
+                            _ => vec![],
+                        })
+                        .collect(),
+                    GroupPattern::SubSelect(_) => vec![],
+                };
+                self.triples_block(&triples);
+            }
+            QueryForm::Describe(_) if query.pattern == GroupPattern::Elements(vec![]) => (),
+            _ => {
+                self.newline();
+                self.write("WHERE ");
+                self.group_pattern(&query.pattern);
+            }
+        }
+        self.modifiers(&query.modifiers);
+        if let Some(values) = &query.values {
+            self.newline();
+            self.values(values);
+        }
+    }
+
+    fn projection(&mut self, projection: &Projection) {
+        self.write("SELECT");
+        match projection.modifier {
+            Some(SelectModifier::Distinct) => self.write(" DISTINCT"),
+            Some(SelectModifier::Reduced) => self.write(" REDUCED"),
+            None => (),
+        }
+        if projection.items.is_empty() {
+            self.write(" *");
+        }
+        for item in &projection.items {
+            self.write(" ");
+            match item {
+                SelectItem::Variable(name) => self.variable(name),
+                SelectItem::Expression(expression, name) => {
+                    self.write("(");
+                    self.expression(expression, 0);
+                    self.write(" AS ");
+                    self.variable(name);
+                    self.write(")");
+                }
+            }
+        }
+    }
+
+    fn modifiers(&mut self, modifiers: &Modifiers) {
+        if !modifiers.group_by.is_empty() {
+            self.newline();
+            self.write("GROUP BY");
+            for condition in &modifiers.group_by {
+                self.write(" ");
+                match condition {
+                    GroupCondition {
+                        expression: Expression::Term(Term::Variable(name)),
+                        variable: None,
+                    } => self.variable(name),
+                    GroupCondition {
+                        expression,
+                        variable,
+                    } => {
+                        self.write("(");
+                        self.expression(expression, 0);
+                        if let Some(variable) = variable {
+                            self.write(" AS ");
+                            self.variable(variable);
+                        }
+                        self.write(")");
+                    }
+                }
+            }
+        }
+        if !modifiers.having.is_empty() {
+            self.newline();
+            self.write("HAVING");
+            for expression in &modifiers.having {
+                self.write(" ");
+                self.bracketed(expression);
+            }
+        }
+        if !modifiers.order_by.is_empty() {
+            self.newline();
+            self.write("ORDER BY");
+            for condition in &modifiers.order_by {
+                self.write(" ");
+                match (&condition.expression, condition.descending) {
+                    (Expression::Term(Term::Variable(name)), false) => self.variable(name),
+                    (expression, true) => {
+                        self.write("DESC");
+                        self.bracketed(expression);
+                    }
+                    (expression, false) => self.bracketed(expression),
+                }
+            }
+        }
+        if let Some(limit) = modifiers.limit {
+            self.newline();
+            self.write(&format!("LIMIT {}", limit));
+        }
+        if let Some(offset) = modifiers.offset {
+            self.newline();
+            self.write(&format!("OFFSET {}", offset));
+        }
+    }
+
+    fn group_pattern(&mut self, pattern: &GroupPattern) {
+        match pattern {
+            GroupPattern::Elements(elements) if elements.is_empty() => self.write("{}"),
+            GroupPattern::Elements(elements) => {
+                self.write("{");
+                self.indent += 1;
+                for element in elements {
+                    self.element(element);
+                }
+                self.indent -= 1;
+                self.newline();
+                self.write("}");
+            }
+            GroupPattern::SubSelect(select) => {
+                self.write("{");
+                self.indent += 1;
+                self.newline();
+                self.projection(&select.projection);
+                self.newline();
+                self.write("WHERE ");
+                self.group_pattern(&select.pattern);
+                self.modifiers(&select.modifiers);
+                if let Some(values) = &select.values {
+                    self.newline();
+                    self.values(values);
+                }
+                self.indent -= 1;
+                self.newline();
+                self.write("}");
+            }
+        }
+    }
+
+    fn element(&mut self, element: &Element) {
+        if let Element::Triples(triples) = element {
+            for triple in triples {
+                self.newline();
+                self.triple_pattern(triple);
+                self.write(" .");
+            }
+            return;
+        }
+        self.newline();
+        match element {
+            Element::Triples(_) => (),
+            Element::Group(pattern) => self.group_pattern(pattern),
+            Element::Union(patterns) => {
+                for (i, pattern) in patterns.iter().enumerate() {
+                    if i > 0 {
+                        self.write(" UNION ");
+                    }
+                    self.group_pattern(pattern);
+                }
+            }
+            Element::Optional(pattern) => {
+                self.write("OPTIONAL ");
+                self.group_pattern(pattern);
+            }
+            Element::Minus(pattern) => {
+                self.write("MINUS ");
+                self.group_pattern(pattern);
+            }
+            Element::Graph(name, pattern) => {
+                self.write("GRAPH ");
+                self.var_or_iri(name);
+                self.write(" ");
+                self.group_pattern(pattern);
+            }
+            Element::Service {
+                silent,
+                name,
+                pattern,
+            } => {
+                self.write(if *silent {
+                    "SERVICE SILENT "
+                } else {
+                    "SERVICE "
+                });
+                self.var_or_iri(name);
+                self.write(" ");
+                self.group_pattern(pattern);
+            }
+            Element::Filter(expression) => {
+                self.write("FILTER");
+                self.bracketed(expression);
+            }
+            Element::Bind(expression, name) => {
+                self.write("BIND(");
+                self.expression(expression, 0);
+                self.write(" AS ");
+                self.variable(name);
+                self.write(")");
+            }
+            Element::Values(values) => self.values(values),
+        }
+    }
+
+    /// Writes triples between braces, as in a CONSTRUCT template.
+    fn triples_block(&mut self, triples: &[TriplePattern]) {
+        self.group_pattern(&GroupPattern::Elements(match triples.is_empty() {
+            true => vec![],
+            false => vec![Element::Triples(triples.to_vec())],
+        }));
+    }
+
+    fn triple_pattern(&mut self, triple: &TriplePattern) {
+        self.graph_node(&triple.subject);
+        if !triple.properties.is_empty() {
+            self.write(" ");
+            self.properties(&triple.properties);
+        }
+    }
+
+    fn properties(&mut self, properties: &[Property]) {
+        for (i, property) in properties.iter().enumerate() {
+            if i > 0 {
+                self.write(" ; ");
+            }
+            match &property.verb {
+                Verb::Variable(name) => self.variable(name),
+                Verb::Path(path) => self.path(path, 0),
+            }
+            for (j, object) in property.objects.iter().enumerate() {
+                self.write(if j > 0 { ", " } else { " " });
+                self.graph_node(object);
+            }
+        }
+    }
+
+    fn graph_node(&mut self, node: &GraphNode) {
+        match node {
+            GraphNode::Term(term) => self.term(term),
+            GraphNode::Collection(nodes) => {
+                self.write("(");
+                for node in nodes {
+                    self.write(" ");
+                    self.graph_node(node);
+                }
+                self.write(" )");
+            }
+            GraphNode::Properties(properties) => {
+                self.write("[ ");
+                self.properties(properties);
+                self.write(" ]");
+            }
+        }
+    }
+
+    /// Writes a path, in parentheses if it binds less tightly than the context requires.
+    fn path(&mut self, path: &Path, precedence: usize) {
+        let own = match path {
+            Path::Alternative(_) => 0,
+            Path::Sequence(_) => 1,
+            Path::Inverse(_) => 2,
+            Path::ZeroOrMore(_) | Path::OneOrMore(_) | Path::ZeroOrOne(_) => 3,
+            Path::Iri(_) | Path::A | Path::Negated(_) => 4,
+        };
+        if own < precedence {
+            self.write("(");
+        }
+        match path {
+            Path::Iri(iri) => self.iri(iri),
+            Path::A => self.write("a"),
+            Path::Alternative(paths) => self.paths(paths, "|", 1),
+            Path::Sequence(paths) => self.paths(paths, "/", 2),
+            Path::Inverse(path) => {
+                self.write("^");
+                self.path(path, 3);
+            }
+            Path::ZeroOrMore(path) => {
+                self.path(path, 4);
+                self.write("*");
+            }
+            Path::OneOrMore(path) => {
+                self.path(path, 4);
+                self.write("+");
+            }
+            Path::ZeroOrOne(path) => {
+                self.path(path, 4);
+                self.write("?");
+            }
+            Path::Negated(paths) => {
+                self.write("!");
+                match paths.as_slice() {
+                    [path @ Path::Iri(_)] | [path @ Path::A] => self.path(path, 0),
+                    paths => {
+                        self.write("(");
+                        self.paths(paths, "|", 2);
+                        self.write(")");
+                    }
+                }
+            }
+        }
+        if own < precedence {
+            self.write(")");
+        }
+    }
+
+    fn paths(&mut self, paths: &[Path], separator: &str, precedence: usize) {
+        for (i, path) in paths.iter().enumerate() {
+            if i > 0 {
+                self.write(separator);
+            }
+            self.path(path, precedence);
+        }
+    }
+
+    fn values(&mut self, values: &Values) {
+        self.write("VALUES ");
+        if let [variable] = values.variables.as_slice() {
+            self.variable(variable);
+            self.write(" {");
+            for row in &values.rows {
+                self.write(" ");
+                self.data_value(&row[0]);
+            }
+            self.write(" }");
+            return;
+        }
+        self.write("(");
+        for (i, variable) in values.variables.iter().enumerate() {
+            if i > 0 {
+                self.write(" ");
+            }
+            self.variable(variable);
+        }
+        self.write(") {");
+        self.indent += 1;
+        for row in &values.rows {
+            self.newline();
+            self.write("(");
+            for (i, value) in row.iter().enumerate() {
+                if i > 0 {
+                    self.write(" ");
+                }
+                self.data_value(value);
+            }
+            self.write(")");
+        }
+        self.indent -= 1;
+        self.newline();
+        self.write("}");
+    }
+
+    fn data_value(&mut self, value: &Option<Term>) {
+        match value {
+            Some(term) => self.term(term),
+            None => self.write("UNDEF"),
+        }
+    }
+
+    fn bracketed(&mut self, expression: &Expression) {
+        self.write("(");
+        self.expression(expression, 0);
+        self.write(")");
+    }
+
+    /// Writes an expression, in parentheses if it binds less tightly than the context requires.
+    fn expression(&mut self, expression: &Expression, precedence: usize) {
+        use Expression::*;
+        let own = match expression {
+            Or(_, _) => 0,
+            And(_, _) => 1,
+            Equal(_, _)
+            | NotEqual(_, _)
+            | Less(_, _)
+            | Greater(_, _)
+            | LessOrEqual(_, _)
+            | GreaterOrEqual(_, _)
+            | In(_, _)
+            | NotIn(_, _) => 2,
+            Add(_, _) | Subtract(_, _) => 3,
+            Multiply(_, _) | Divide(_, _) => 4,
+            Plus(_) | Minus(_) | Not(_) => 5,
+            _ => 6,
+        };
+        if own < precedence {
+            self.write("(");
+        }
+        match expression {
+            Term(term) => self.term(term),
+            Or(a, b) => self.binary(a, " || ", b, (0, 1)),
+            And(a, b) => self.binary(a, " && ", b, (1, 2)),
+            Equal(a, b) => self.binary(a, " = ", b, (3, 3)),
+            NotEqual(a, b) => self.binary(a, " != ", b, (3, 3)),
+            Less(a, b) => self.binary(a, " < ", b, (3, 3)),
+            Greater(a, b) => self.binary(a, " > ", b, (3, 3)),
+            LessOrEqual(a, b) => self.binary(a, " <= ", b, (3, 3)),
+            GreaterOrEqual(a, b) => self.binary(a, " >= ", b, (3, 3)),
+            In(a, list) | NotIn(a, list) => {
+                self.expression(a, 3);
+                self.write(match expression {
+                    In(_, _) => " IN ",
+                    _ => " NOT IN ",
+                });
+                self.arguments(list);
+            }
+            Add(a, b) => self.binary(a, " + ", b, (3, 4)),
+            Subtract(a, b) => self.binary(a, " - ", b, (3, 4)),
+            Multiply(a, b) => self.binary(a, " * ", b, (4, 5)),
+            Divide(a, b) => self.binary(a, " / ", b, (4, 5)),
+            Plus(a) => self.unary("+", a),
+            Minus(a) => self.unary("-", a),
+            Not(a) => self.unary("!", a),
+            BuiltIn(name, arguments) => {
+                self.write(name);
+                self.arguments(arguments);
+            }
+            Function {
+                iri,
+                distinct,
+                arguments,
+            } => {
+                self.iri(iri);
+                if *distinct {
+                    self.write("(DISTINCT ");
+                    self.expressions(arguments);
+                    self.write(")");
+                } else {
+                    self.arguments(arguments);
+                }
+            }
+            Exists(pattern) => {
+                self.write("EXISTS ");
+                self.group_pattern(pattern);
+            }
+            NotExists(pattern) => {
+                self.write("NOT EXISTS ");
+                self.group_pattern(pattern);
+            }
+            Aggregate(aggregate) => {
+                self.write(&aggregate.name);
+                self.write("(");
+                if aggregate.distinct {
+                    self.write("DISTINCT ");
+                }
+                match &aggregate.argument {
+                    Some(argument) => self.expression(argument, 0),
+                    None => self.write("*"),
+                }
+                if let Some(separator) = &aggregate.separator {
+                    self.write("; SEPARATOR=");
+                    self.string(separator);
+                }
+                self.write(")");
+            }
+        }
+        if own < precedence {
+            self.write(")");
+        }
+    }
+
+    /// Writes an operation, with the precedences its operands need.
+    fn binary(
+        &mut self,
+        a: &Expression,
+        operator: &str,
+        b: &Expression,
+        (left, right): (usize, usize),
+    ) {
+        self.expression(a, left);
+        self.write(operator);
+        self.expression(b, right);
+    }
+
+    fn unary(&mut self, operator: &str, a: &Expression) {
+        self.write(operator);
+        self.expression(a, 6);
+    }
+
+    fn arguments(&mut self, arguments: &[Expression]) {
+        self.write("(");
+        self.expressions(arguments);
+        self.write(")");
+    }
+
+    fn expressions(&mut self, expressions: &[Expression]) {
+        for (i, expression) in expressions.iter().enumerate() {
+            if i > 0 {
+                self.write(", ");
+            }
+            self.expression(expression, 0);
+        }
+    }
+
+    fn term(&mut self, term: &Term) {
+        match term {
+            Term::Variable(name) => self.variable(name),
+            Term::Iri(iri) => self.iri(iri),
+            Term::Literal(literal) => self.literal(literal),
+            Term::BlankNode(label) => self.write(&format!("_:{}", label)),
+            Term::Anonymous => self.write("[]"),
+            Term::Nil => self.write("()"),
+        }
+    }
+
+    fn variable(&mut self, name: &str) {
+        self.write(&format!("?{}", name));
+    }
+
+    fn var_or_iri(&mut self, value: &VarOrIri) {
+        match value {
+            VarOrIri::Variable(name) => self.variable(name),
+            VarOrIri::Iri(iri) => self.iri(iri),
+        }
+    }
+
+    fn iri(&mut self, iri: &Iri) {
+        match iri {
+            Iri::Full(iri) => self.write(&format!("<{}>", iri)),
+            Iri::Prefixed { prefix, local } => self.write(&format!("{}:{}", prefix, local)),
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal) {
+        match literal {
+            Literal::String {
+                value,
+                language,
+                datatype,
+            } => {
+                self.string(value);
+                if let Some(language) = language {
+                    self.write(&format!("@{}", language));
+                }
+                if let Some(datatype) = datatype {
+                    self.write("^^");
+                    self.iri(datatype);
+                }
+            }
+            Literal::Integer(n) | Literal::Decimal(n) | Literal::Double(n) => self.write(n),
+            Literal::Boolean(b) => self.write(&b.to_string()),
+        }
+    }
+
+    fn string(&mut self, value: &str) {
+        self.write("\"");
+        for c in value.chars() {
+            match c {
+                '"' => self.write("\\\""),
+                '\\' => self.write("\\\\"),
+                '\n' => self.write("\\n"),
+                '\r' => self.write("\\r"),
+                c => self.output.push(c),
+            }
+        }
+        self.write("\"");
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut writer = Writer::new();
+        writer.query(self);
+        f.write_str(&writer.output)
+    }
+}
+
+impl fmt::Display for GroupPattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut writer = Writer::new();
+        writer.group_pattern(self);
+        f.write_str(&writer.output)
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut writer = Writer::new();
+        writer.expression(self, 0);
+        f.write_str(&writer.output)
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut writer = Writer::new();
+        writer.term(self);
+        f.write_str(&writer.output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn writes_parentheses_where_needed() {
+        let expression = |query: &str| match parse_query(query).unwrap().form {
+            QueryForm::Select(projection) => match &projection.items[0] {
+                SelectItem::Expression(expression, _) => expression.to_string(),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+
+        assert_eq!(
+            expression("SELECT (((?a + ?b)) * ?c AS ?x) {}"),
+            "(?a + ?b) * ?c"
+        );
+        assert_eq!(
+            expression("SELECT ((?a - (?b - ?c)) - ?d AS ?x) {}"),
+            "?a - (?b - ?c) - ?d"
+        );
+        assert_eq!(
+            expression("SELECT (!(?a && (?b || ?c)) AS ?x) {}"),
+            "!(?a && (?b || ?c))"
+        );
+    }
+
+    #[test]
+    fn writes_paths_and_literals() {
+        let query =
+            parse_query("SELECT * { ?s ^(<p>/<q>)*|!(a|^<r>) [ <p> ( 'a\"b'@en-GB -1.5 ) ] }")
+                .unwrap();
+
+        assert_eq!(
+            query.to_string(),
+            "SELECT *
+WHERE {
+  ?s ^(<p>/<q>)*|!(a|^<r>) [ <p> ( \"a\\\"b\"@en-GB -1.5 ) ] .
+}"
+        );
+    }
+}
@@ -0,0 +1,354 @@
+//! A synchronous interface for programs without a Tokio runtime, such as build scripts. Each
+//! dataset runs its requests on an internal runtime, which clones share.
+//!
+//! The methods panic when they are called from an asynchronous context.
+
+use crate::error::Error;
+use crate::http::{self, DatasetBuilder, DatasetType, Endpoints};
+use crate::interceptor::Interceptor;
+use crate::iri::IriError;
+use crate::prefix::PrefixMap;
+use crate::retry::RetryPolicy;
+use crate::table::Table;
+use crate::void::{Statistics, VoidStatistics};
+use crate::{
+    AskQuery, ConstructQuery, DataFile, DescribeQuery, Graph, QuadFormat, Resource, Selection,
+};
+use rdf::node::Node;
+use std::future::Future;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::AsyncWrite;
+use tokio::runtime::Runtime;
+
+/// The synchronous counterpart of `crate::GraphStore`.
+pub trait GraphStore {
+    /// Imports a file into a dataset.
+    fn import(&self, graph: Graph, file: DataFile) -> Result<(), Error>;
+
+    /// Performs a SPARQL query.
+    fn select(&self, query: Selection) -> Result<Table<Node>, Error>;
+
+    fn ask(&self, query: AskQuery) -> Result<bool, Error>;
+
+    fn describe(&self, query: DescribeQuery) -> Result<rdf::graph::Graph, Error>;
+
+    fn construct(&self, query: ConstructQuery) -> Result<rdf::graph::Graph, Error>;
+
+    /// Retrieves the content of a graph.
+    fn get(&self, graph: Graph) -> Result<rdf::graph::Graph, Error>;
+
+    /// Describes resources as JSON-LD, compacted if a context is given.
+    fn describe_json_ld(
+        &self,
+        query: DescribeQuery,
+        context: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, Error>;
+
+    /// Constructs a graph as JSON-LD, compacted if a context is given.
+    fn construct_json_ld(
+        &self,
+        query: ConstructQuery,
+        context: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, Error>;
+
+    /// Retrieves the content of a graph as JSON-LD, compacted if a context is given.
+    fn get_json_ld(
+        &self,
+        graph: Graph,
+        context: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, Error>;
+
+    /// Describes the resources in all named graphs.
+    fn describe_everything(&self) -> Result<rdf::graph::Graph, Error>;
+}
+
+/// A dataset whose methods block until the server responds. See `http::Dataset`.
+#[derive(Clone)]
+pub struct Dataset {
+    inner: http::Dataset,
+    runtime: Arc<Runtime>,
+}
+
+impl Dataset {
+    /// Returns a named dataset in a Fuseki server, which is first created with a type if the
+    /// dataset does not exist. Like the other shortcuts, it sends no credentials; use
+    /// `from_builder` for a protected server.
+    pub fn get_or_create(
+        base: url::Url,
+        name: &str,
+        dataset_type: DatasetType,
+    ) -> Result<Dataset, Error> {
+        Dataset::from_builder(
+            DatasetBuilder::new(name)
+                .fuseki(&base)
+                .dataset_type(dataset_type),
+        )
+    }
+
+    /// Returns a named dataset in a Fuseki server, or `Error::NotFound` if it does not exist.
+    pub fn open(base: url::Url, name: &str) -> Result<Dataset, Error> {
+        Dataset::start(DatasetBuilder::new(name).fuseki(&base).open())
+    }
+
+    /// Creates a named dataset with a type in a Fuseki server, or returns
+    /// `Error::AlreadyExists`.
+    pub fn create(base: url::Url, name: &str, dataset_type: DatasetType) -> Result<Dataset, Error> {
+        Dataset::start(
+            DatasetBuilder::new(name)
+                .fuseki(&base)
+                .dataset_type(dataset_type)
+                .create(),
+        )
+    }
+
+    /// Returns a dataset from a builder, which is first created if there is an admin endpoint.
+    pub fn from_builder(builder: DatasetBuilder) -> Result<Dataset, Error> {
+        Dataset::start(builder.get_or_create())
+    }
+
+    /// Runs the future that returns the dataset on a new runtime, which the dataset keeps.
+    fn start<F, E>(dataset: F) -> Result<Dataset, E>
+    where
+        F: Future<Output = Result<http::Dataset, E>>,
+    {
+        let runtime = Arc::new(new_runtime());
+        let inner = runtime.handle().block_on(dataset)?;
+        Ok(Dataset { inner, runtime })
+    }
+
+    /// The asynchronous dataset, which can be used on another runtime.
+    pub fn into_async(self) -> http::Dataset {
+        self.inner
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.handle().block_on(future)
+    }
+
+    pub fn endpoints(&self) -> &Endpoints {
+        self.inner.endpoints()
+    }
+
+    /// Replaces the prefixes that are declared in queries and added to graphs.
+    pub fn with_prefixes(self, prefixes: PrefixMap) -> Self {
+        self.map(|inner| inner.with_prefixes(prefixes))
+    }
+
+    pub fn prefixes(&self) -> &PrefixMap {
+        self.inner.prefixes()
+    }
+
+    /// Sets the base against which `resource` resolves relative references.
+    pub fn with_resource_base(self, base: Resource) -> Self {
+        self.map(|inner| inner.with_resource_base(base))
+    }
+
+    /// Creates a resource from a prefixed name, a relative reference or an absolute IRI.
+    pub fn resource(&self, reference: &str) -> Result<Resource, IriError> {
+        self.inner.resource(reference)
+    }
+
+    /// Limits the time that queries may take, unless they have their own timeout.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.map(|inner| inner.with_timeout(timeout))
+    }
+
+    /// Retries requests after transient failures. By default, each request is sent once.
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        self.map(|inner| inner.with_retry_policy(policy))
+    }
+
+    /// Adds an interceptor after those that the dataset already has.
+    pub fn with_interceptor<I: Interceptor + 'static>(self, interceptor: I) -> Self {
+        self.map(|inner| inner.with_interceptor(interceptor))
+    }
+
+    fn map<F: FnOnce(http::Dataset) -> http::Dataset>(self, f: F) -> Self {
+        Dataset {
+            inner: f(self.inner),
+            runtime: self.runtime,
+        }
+    }
+
+    /// Writes the default graph and all named graphs, streaming them from the server.
+    pub fn dump<W: Write>(&self, format: QuadFormat, writer: &mut W) -> Result<(), Error> {
+        self.block_on(self.inner.dump(format, &mut SyncWriter(writer)))
+    }
+
+    /// Writes the dataset to a file in the format indicated by its extension.
+    pub fn dump_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.block_on(self.inner.dump_to_file(path))
+    }
+
+    /// Recreates a dump in this dataset, which must be empty. The dump is read into memory
+    /// first; use `restore_from_file` to stream it from a file.
+    pub fn restore<R: Read>(&self, format: QuadFormat, mut reader: R) -> Result<(), Error> {
+        let mut content = vec![];
+        reader.read_to_end(&mut content)?;
+        self.block_on(self.inner.restore(format, std::io::Cursor::new(content)))
+    }
+
+    /// Recreates a dump from a file in the format indicated by its extension.
+    pub fn restore_from_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.block_on(self.inner.restore_from_file(path))
+    }
+
+    /// Deletes a dataset through the admin endpoint.
+    pub fn delete(self) -> Result<(), Error> {
+        self.runtime.handle().block_on(self.inner.delete())
+    }
+
+    /// Performs a SPARQL update, which is retried only if the retry policy allows it.
+    pub fn update(&self, update: &str) -> Result<(), Error> {
+        self.block_on(self.inner.update(update))
+    }
+
+    /// Adds the triples of a data file to a graph, where `import` replaces the graph.
+    pub fn merge(&self, graph: Graph, file: DataFile) -> Result<(), Error> {
+        self.block_on(self.inner.merge(graph, file))
+    }
+
+    /// Computes statistics about the graphs in the dataset.
+    pub fn statistics(&self) -> Result<Statistics, Error> {
+        self.block_on(self.inner.statistics())
+    }
+}
+
+impl GraphStore for Dataset {
+    fn import(&self, graph: Graph, file: DataFile) -> Result<(), Error> {
+        self.block_on(crate::GraphStore::import(&self.inner, graph, file))
+    }
+
+    fn select(&self, query: Selection) -> Result<Table<Node>, Error> {
+        self.block_on(crate::GraphStore::select(&self.inner, query))
+    }
+
+    fn ask(&self, query: AskQuery) -> Result<bool, Error> {
+        self.block_on(crate::GraphStore::ask(&self.inner, query))
+    }
+
+    fn describe(&self, query: DescribeQuery) -> Result<rdf::graph::Graph, Error> {
+        self.block_on(crate::GraphStore::describe(&self.inner, query))
+    }
+
+    fn construct(&self, query: ConstructQuery) -> Result<rdf::graph::Graph, Error> {
+        self.block_on(crate::GraphStore::construct(&self.inner, query))
+    }
+
+    fn get(&self, graph: Graph) -> Result<rdf::graph::Graph, Error> {
+        self.block_on(crate::GraphStore::get(&self.inner, graph))
+    }
+
+    fn describe_json_ld(
+        &self,
+        query: DescribeQuery,
+        context: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, Error> {
+        self.block_on(crate::GraphStore::describe_json_ld(
+            &self.inner,
+            query,
+            context,
+        ))
+    }
+
+    fn construct_json_ld(
+        &self,
+        query: ConstructQuery,
+        context: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, Error> {
+        self.block_on(crate::GraphStore::construct_json_ld(
+            &self.inner,
+            query,
+            context,
+        ))
+    }
+
+    fn get_json_ld(
+        &self,
+        graph: Graph,
+        context: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, Error> {
+        self.block_on(crate::GraphStore::get_json_ld(&self.inner, graph, context))
+    }
+
+    fn describe_everything(&self) -> Result<rdf::graph::Graph, Error> {
+        self.block_on(crate::GraphStore::describe_everything(&self.inner))
+    }
+}
+
+/// Writes the resources with labels as HTML pages. See `doc::export_to_html`.
+pub fn export_to_html(dataset: &Dataset) -> Result<(), Error> {
+    dataset.block_on(crate::doc::export_to_html(&dataset.inner))
+}
+
+/// A runtime with a single worker, which drives the connections while the calling thread
+/// blocks on a request.
+fn new_runtime() -> Runtime {
+    tokio::runtime::Builder::new()
+        .threaded_scheduler()
+        .core_threads(1)
+        .enable_all()
+        .build()
+        .expect("Could not start runtime")
+}
+
+/// Writes to a synchronous writer on the blocked thread.
+struct SyncWriter<'a, W>(&'a mut W);
+
+impl<W: Write> AsyncWrite for SyncWriter<'_, W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _: &mut Context,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Poll::Ready(self.get_mut().0.write(buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<std::io::Result<()>> {
+        Poll::Ready(self.get_mut().0.flush())
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<std::io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blocking::{Dataset, GraphStore};
+    use crate::http::DatasetType;
+    use crate::{AskQuery, QuadFormat};
+    use mockito::mock;
+
+    #[test]
+    fn blocks_on_requests() {
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "blocking";
+        let _create_mock = mock("POST", "/$/datasets").with_status(200).create();
+        let ask_mock = mock("POST", format!("/{}", name).as_ref())
+            .with_status(200)
+            .with_body(r#"{ "head": {}, "boolean": true }"#)
+            .create();
+        let quads =
+            "<http://example.org/a> <http://example.org/b> \"c\" <http://example.org/g> .\n";
+        let dump_mock = mock("GET", format!("/{}/data", name).as_ref())
+            .with_status(200)
+            .with_body(quads)
+            .create();
+
+        let dataset = Dataset::get_or_create(base, name, DatasetType::Mem).unwrap();
+        let result = dataset.ask(AskQuery::unsafe_from("ASK {}")).unwrap();
+        let mut output: Vec<u8> = vec![];
+        dataset.dump(QuadFormat::NQuads, &mut output).unwrap();
+
+        ask_mock.assert();
+        dump_mock.assert();
+        assert!(result);
+        assert_eq!(String::from_utf8(output).unwrap(), quads);
+    }
+}
@@ -0,0 +1,1430 @@
+use crate::query::Value;
+use crate::sparql_ast as ast;
+use crate::syntax::{is_pn_chars, is_pn_chars_u};
+use crate::{AskQuery, ConstructQuery, Resource, Selection};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Not, Sub};
+
+/// A query that was rejected by a builder before being sent.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct QueryError {
+    pub message: String,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid query: {}", self.message)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+fn error<T>(message: String) -> Result<T, QueryError> {
+    Err(QueryError { message })
+}
+
+/// A variable or a fixed value in a pattern.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Term {
+    Variable(String),
+    Value(Value),
+}
+
+impl Term {
+    pub fn var(name: &str) -> Term {
+        Term::Variable(name.to_string())
+    }
+}
+
+impl From<Value> for Term {
+    fn from(value: Value) -> Self {
+        Term::Value(value)
+    }
+}
+
+impl From<Resource> for Term {
+    fn from(resource: Resource) -> Self {
+        Term::Value(Value::Resource(resource))
+    }
+}
+
+impl From<&Resource> for Term {
+    fn from(resource: &Resource) -> Self {
+        Term::Value(Value::from(resource))
+    }
+}
+
+/// The predicate of a triple pattern. A variable may only be used as the whole predicate, not
+/// within a path.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Path {
+    Variable(String),
+    Resource(Resource),
+    Inverse(Box<Path>),
+    Sequence(Vec<Path>),
+    Alternative(Vec<Path>),
+    ZeroOrMore(Box<Path>),
+    OneOrMore(Box<Path>),
+    ZeroOrOne(Box<Path>),
+    Negated(Vec<Resource>),
+}
+
+impl Path {
+    pub fn var(name: &str) -> Path {
+        Path::Variable(name.to_string())
+    }
+
+    pub fn inverse(self) -> Path {
+        Path::Inverse(Box::new(self))
+    }
+
+    pub fn then(self, next: Path) -> Path {
+        match self {
+            Path::Sequence(mut paths) => {
+                paths.push(next);
+                Path::Sequence(paths)
+            }
+            path => Path::Sequence(vec![path, next]),
+        }
+    }
+
+    pub fn or(self, alternative: Path) -> Path {
+        match self {
+            Path::Alternative(mut paths) => {
+                paths.push(alternative);
+                Path::Alternative(paths)
+            }
+            path => Path::Alternative(vec![path, alternative]),
+        }
+    }
+
+    pub fn zero_or_more(self) -> Path {
+        Path::ZeroOrMore(Box::new(self))
+    }
+
+    pub fn one_or_more(self) -> Path {
+        Path::OneOrMore(Box::new(self))
+    }
+
+    pub fn zero_or_one(self) -> Path {
+        Path::ZeroOrOne(Box::new(self))
+    }
+}
+
+impl From<Resource> for Path {
+    fn from(resource: Resource) -> Self {
+        Path::Resource(resource)
+    }
+}
+
+impl From<&Resource> for Path {
+    fn from(resource: &Resource) -> Self {
+        Path::Resource(resource.clone())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Aggregate {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Avg,
+    Sample,
+    GroupConcat(Option<String>),
+}
+
+/// An expression in a filter, a binding, a projection or a solution modifier.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expression {
+    Variable(String),
+    Value(Value),
+    Or(Box<Expression>, Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Equal(Box<Expression>, Box<Expression>),
+    NotEqual(Box<Expression>, Box<Expression>),
+    Less(Box<Expression>, Box<Expression>),
+    LessOrEqual(Box<Expression>, Box<Expression>),
+    Greater(Box<Expression>, Box<Expression>),
+    GreaterOrEqual(Box<Expression>, Box<Expression>),
+    In(Box<Expression>, Vec<Expression>),
+    NotIn(Box<Expression>, Vec<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Subtract(Box<Expression>, Box<Expression>),
+    Multiply(Box<Expression>, Box<Expression>),
+    Divide(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    /// A built-in function such as `isURI` or `REGEX`, by name.
+    Call(String, Vec<Expression>),
+    /// An extension function, identified by an IRI.
+    Function(Resource, Vec<Expression>),
+    Exists(Vec<Pattern>),
+    NotExists(Vec<Pattern>),
+    Aggregate {
+        function: Aggregate,
+        distinct: bool,
+        argument: Option<Box<Expression>>,
+    },
+}
+
+impl Expression {
+    pub fn var(name: &str) -> Expression {
+        Expression::Variable(name.to_string())
+    }
+
+    pub fn value<V: Into<Value>>(value: V) -> Expression {
+        Expression::Value(value.into())
+    }
+
+    pub fn call(name: &str, arguments: Vec<Expression>) -> Expression {
+        Expression::Call(name.to_string(), arguments)
+    }
+
+    pub fn exists(patterns: Vec<Pattern>) -> Expression {
+        Expression::Exists(patterns)
+    }
+
+    pub fn not_exists(patterns: Vec<Pattern>) -> Expression {
+        Expression::NotExists(patterns)
+    }
+
+    /// An aggregate over a group. Only `COUNT` may omit the argument, to count solutions.
+    pub fn aggregate(function: Aggregate, argument: Option<Expression>) -> Expression {
+        Expression::Aggregate {
+            function,
+            distinct: false,
+            argument: argument.map(Box::new),
+        }
+    }
+
+    pub fn aggregate_distinct(function: Aggregate, argument: Option<Expression>) -> Expression {
+        Expression::Aggregate {
+            function,
+            distinct: true,
+            argument: argument.map(Box::new),
+        }
+    }
+
+    pub fn or(self, other: Expression) -> Expression {
+        Expression::Or(Box::new(self), Box::new(other))
+    }
+
+    pub fn and(self, other: Expression) -> Expression {
+        Expression::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn equal(self, other: Expression) -> Expression {
+        Expression::Equal(Box::new(self), Box::new(other))
+    }
+
+    pub fn not_equal(self, other: Expression) -> Expression {
+        Expression::NotEqual(Box::new(self), Box::new(other))
+    }
+
+    pub fn less_than(self, other: Expression) -> Expression {
+        Expression::Less(Box::new(self), Box::new(other))
+    }
+
+    pub fn less_or_equal(self, other: Expression) -> Expression {
+        Expression::LessOrEqual(Box::new(self), Box::new(other))
+    }
+
+    pub fn greater_than(self, other: Expression) -> Expression {
+        Expression::Greater(Box::new(self), Box::new(other))
+    }
+
+    pub fn greater_or_equal(self, other: Expression) -> Expression {
+        Expression::GreaterOrEqual(Box::new(self), Box::new(other))
+    }
+
+    pub fn is_in(self, list: Vec<Expression>) -> Expression {
+        Expression::In(Box::new(self), list)
+    }
+
+    pub fn not_in(self, list: Vec<Expression>) -> Expression {
+        Expression::NotIn(Box::new(self), list)
+    }
+}
+
+impl From<Value> for Expression {
+    fn from(value: Value) -> Self {
+        Expression::Value(value)
+    }
+}
+
+impl From<Term> for Expression {
+    fn from(term: Term) -> Self {
+        match term {
+            Term::Variable(name) => Expression::Variable(name),
+            Term::Value(value) => Expression::Value(value),
+        }
+    }
+}
+
+impl Add for Expression {
+    type Output = Expression;
+
+    fn add(self, other: Expression) -> Expression {
+        Expression::Add(Box::new(self), Box::new(other))
+    }
+}
+
+impl Sub for Expression {
+    type Output = Expression;
+
+    fn sub(self, other: Expression) -> Expression {
+        Expression::Subtract(Box::new(self), Box::new(other))
+    }
+}
+
+impl Mul for Expression {
+    type Output = Expression;
+
+    fn mul(self, other: Expression) -> Expression {
+        Expression::Multiply(Box::new(self), Box::new(other))
+    }
+}
+
+impl Div for Expression {
+    type Output = Expression;
+
+    fn div(self, other: Expression) -> Expression {
+        Expression::Divide(Box::new(self), Box::new(other))
+    }
+}
+
+impl Not for Expression {
+    type Output = Expression;
+
+    fn not(self) -> Expression {
+        Expression::Not(Box::new(self))
+    }
+}
+
+/// An element of a group graph pattern.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pattern {
+    Triple {
+        subject: Term,
+        path: Path,
+        object: Term,
+    },
+    Group(Vec<Pattern>),
+    Optional(Vec<Pattern>),
+    Union(Vec<Vec<Pattern>>),
+    Filter(Expression),
+    Graph {
+        name: Term,
+        patterns: Vec<Pattern>,
+    },
+    Bind {
+        expression: Expression,
+        variable: String,
+    },
+    /// Inline data, with `None` for an undefined value.
+    Values {
+        variables: Vec<String>,
+        rows: Vec<Vec<Option<Value>>>,
+    },
+}
+
+impl Pattern {
+    pub fn triple<S, P, O>(subject: S, path: P, object: O) -> Pattern
+    where
+        S: Into<Term>,
+        P: Into<Path>,
+        O: Into<Term>,
+    {
+        Pattern::Triple {
+            subject: subject.into(),
+            path: path.into(),
+            object: object.into(),
+        }
+    }
+
+    pub fn optional(patterns: Vec<Pattern>) -> Pattern {
+        Pattern::Optional(patterns)
+    }
+
+    pub fn union(alternatives: Vec<Vec<Pattern>>) -> Pattern {
+        Pattern::Union(alternatives)
+    }
+
+    pub fn filter(expression: Expression) -> Pattern {
+        Pattern::Filter(expression)
+    }
+
+    pub fn graph<N: Into<Term>>(name: N, patterns: Vec<Pattern>) -> Pattern {
+        Pattern::Graph {
+            name: name.into(),
+            patterns,
+        }
+    }
+
+    pub fn bind(expression: Expression, variable: &str) -> Pattern {
+        Pattern::Bind {
+            expression,
+            variable: variable.to_string(),
+        }
+    }
+
+    pub fn values(variables: &[&str], rows: Vec<Vec<Option<Value>>>) -> Pattern {
+        Pattern::Values {
+            variables: variables.iter().map(|v| v.to_string()).collect(),
+            rows,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Projection {
+    Variable(String),
+    Expression(Expression, String),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Order {
+    Ascending(Expression),
+    Descending(Expression),
+}
+
+/// Grouping, ordering and slicing of solutions.
+#[derive(Debug, Default, PartialEq, Clone)]
+struct Modifiers {
+    group_by: Vec<String>,
+    having: Vec<Expression>,
+    order_by: Vec<Order>,
+    limit: Option<u64>,
+    offset: Option<u64>,
+}
+
+/// Builds a SELECT query. Without projected variables, all variables are selected.
+#[derive(Debug, Default, Clone)]
+pub struct SelectBuilder {
+    distinct: bool,
+    projection: Vec<Projection>,
+    patterns: Vec<Pattern>,
+    modifiers: Modifiers,
+}
+
+impl SelectBuilder {
+    pub fn new() -> SelectBuilder {
+        SelectBuilder::default()
+    }
+
+    pub fn distinct(mut self) -> SelectBuilder {
+        self.distinct = true;
+        self
+    }
+
+    pub fn variable(mut self, name: &str) -> SelectBuilder {
+        self.projection.push(Projection::Variable(name.to_string()));
+        self
+    }
+
+    /// Selects the value of an expression, such as an aggregate, as a new variable.
+    pub fn expression(mut self, expression: Expression, alias: &str) -> SelectBuilder {
+        self.projection
+            .push(Projection::Expression(expression, alias.to_string()));
+        self
+    }
+
+    pub fn pattern(mut self, pattern: Pattern) -> SelectBuilder {
+        self.patterns.push(pattern);
+        self
+    }
+
+    pub fn group_by(mut self, variable: &str) -> SelectBuilder {
+        self.modifiers.group_by.push(variable.to_string());
+        self
+    }
+
+    pub fn having(mut self, expression: Expression) -> SelectBuilder {
+        self.modifiers.having.push(expression);
+        self
+    }
+
+    pub fn order_by(mut self, expression: Expression) -> SelectBuilder {
+        self.modifiers.order_by.push(Order::Ascending(expression));
+        self
+    }
+
+    pub fn order_by_descending(mut self, expression: Expression) -> SelectBuilder {
+        self.modifiers.order_by.push(Order::Descending(expression));
+        self
+    }
+
+    pub fn limit(mut self, limit: u64) -> SelectBuilder {
+        self.modifiers.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: u64) -> SelectBuilder {
+        self.modifiers.offset = Some(offset);
+        self
+    }
+
+    pub fn build(&self) -> Result<Selection, QueryError> {
+        check_group(&self.patterns)?;
+        let mut in_scope = BTreeSet::new();
+        bound_variables(&self.patterns, &mut in_scope);
+
+        let aggregated = !self.modifiers.group_by.is_empty()
+            || !self.modifiers.having.is_empty()
+            || self.projection.iter().any(|p| match p {
+                Projection::Expression(e, _) => has_aggregate(e),
+                _ => false,
+            })
+            || self.modifiers.order_by.iter().any(|o| match o {
+                Order::Ascending(e) | Order::Descending(e) => has_aggregate(e),
+            });
+        if aggregated && self.projection.is_empty() {
+            return error("SELECT * cannot be used with grouping".to_string());
+        }
+        for variable in &self.modifiers.group_by {
+            check_variable(variable)?;
+        }
+        let mut projected = BTreeSet::new();
+        for projection in &self.projection {
+            let name = match projection {
+                Projection::Variable(name) => {
+                    check_variable(name)?;
+                    if aggregated && !self.modifiers.group_by.contains(name) {
+                        return error(format!("?{} is selected but not grouped", name));
+                    }
+                    name
+                }
+                Projection::Expression(expression, alias) => {
+                    check_variable(alias)?;
+                    check_expression(expression, aggregated)?;
+                    if aggregated {
+                        check_grouped(expression, &self.modifiers.group_by)?;
+                    }
+                    if in_scope.contains(alias) {
+                        return error(format!("?{} is already bound in the pattern", alias));
+                    }
+                    alias
+                }
+            };
+            if !projected.insert(name.to_string()) {
+                return error(format!("?{} is selected more than once", name));
+            }
+        }
+        check_modifiers(&self.modifiers, aggregated)?;
+
+        let projection = ast::Projection {
+            modifier: if self.distinct {
+                Some(ast::SelectModifier::Distinct)
+            } else {
+                None
+            },
+            items: self
+                .projection
+                .iter()
+                .map(|p| match p {
+                    Projection::Variable(name) => ast::SelectItem::Variable(name.to_string()),
+                    Projection::Expression(e, alias) => {
+                        ast::SelectItem::Expression(expression(e), alias.to_string())
+                    }
+                })
+                .collect(),
+        };
+        let query = query(
+            ast::QueryForm::Select(projection),
+            &self.patterns,
+            &self.modifiers,
+        );
+        Ok(Selection::unsafe_from(&query.to_string()))
+    }
+}
+
+/// Builds an ASK query, which tests whether a pattern has a solution.
+#[derive(Debug, Default, Clone)]
+pub struct AskBuilder {
+    patterns: Vec<Pattern>,
+}
+
+impl AskBuilder {
+    pub fn new() -> AskBuilder {
+        AskBuilder::default()
+    }
+
+    pub fn pattern(mut self, pattern: Pattern) -> AskBuilder {
+        self.patterns.push(pattern);
+        self
+    }
+
+    pub fn build(&self) -> Result<AskQuery, QueryError> {
+        check_group(&self.patterns)?;
+        let query = query(ast::QueryForm::Ask, &self.patterns, &Modifiers::default());
+        Ok(AskQuery::unsafe_from(&query.to_string()))
+    }
+}
+
+/// Builds a CONSTRUCT query from a template of triples, whose predicates must be variables or
+/// resources.
+#[derive(Debug, Default, Clone)]
+pub struct ConstructBuilder {
+    template: Vec<(Term, Path, Term)>,
+    patterns: Vec<Pattern>,
+    modifiers: Modifiers,
+}
+
+impl ConstructBuilder {
+    pub fn new() -> ConstructBuilder {
+        ConstructBuilder::default()
+    }
+
+    pub fn template<S, P, O>(mut self, subject: S, predicate: P, object: O) -> ConstructBuilder
+    where
+        S: Into<Term>,
+        P: Into<Path>,
+        O: Into<Term>,
+    {
+        self.template
+            .push((subject.into(), predicate.into(), object.into()));
+        self
+    }
+
+    pub fn pattern(mut self, pattern: Pattern) -> ConstructBuilder {
+        self.patterns.push(pattern);
+        self
+    }
+
+    pub fn order_by(mut self, expression: Expression) -> ConstructBuilder {
+        self.modifiers.order_by.push(Order::Ascending(expression));
+        self
+    }
+
+    pub fn order_by_descending(mut self, expression: Expression) -> ConstructBuilder {
+        self.modifiers.order_by.push(Order::Descending(expression));
+        self
+    }
+
+    pub fn limit(mut self, limit: u64) -> ConstructBuilder {
+        self.modifiers.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: u64) -> ConstructBuilder {
+        self.modifiers.offset = Some(offset);
+        self
+    }
+
+    pub fn build(&self) -> Result<ConstructQuery, QueryError> {
+        for (subject, predicate, object) in &self.template {
+            check_term(subject)?;
+            check_term(object)?;
+            match predicate {
+                Path::Variable(name) => check_variable(name)?,
+                Path::Resource(resource) => check_resource(resource)?,
+                _ => return error("Paths cannot be used in a template".to_string()),
+            }
+        }
+        check_group(&self.patterns)?;
+        check_modifiers(&self.modifiers, false)?;
+
+        let template = self
+            .template
+            .iter()
+            .map(|(subject, predicate, object)| triple(subject, predicate, object))
+            .collect();
+        let query = query(
+            ast::QueryForm::Construct(Some(template)),
+            &self.patterns,
+            &self.modifiers,
+        );
+        Ok(ConstructQuery::unsafe_from(&query.to_string()))
+    }
+}
+
+/// Built-in functions with their minimum and maximum number of arguments.
+const FUNCTIONS: &[(&str, usize, Option<usize>)] = &[
+    ("STR", 1, Some(1)),
+    ("LANG", 1, Some(1)),
+    ("LANGMATCHES", 2, Some(2)),
+    ("DATATYPE", 1, Some(1)),
+    ("BOUND", 1, Some(1)),
+    ("IRI", 1, Some(1)),
+    ("URI", 1, Some(1)),
+    ("BNODE", 0, Some(1)),
+    ("RAND", 0, Some(0)),
+    ("ABS", 1, Some(1)),
+    ("CEIL", 1, Some(1)),
+    ("FLOOR", 1, Some(1)),
+    ("ROUND", 1, Some(1)),
+    ("CONCAT", 0, None),
+    ("STRLEN", 1, Some(1)),
+    ("UCASE", 1, Some(1)),
+    ("LCASE", 1, Some(1)),
+    ("ENCODE_FOR_URI", 1, Some(1)),
+    ("CONTAINS", 2, Some(2)),
+    ("STRSTARTS", 2, Some(2)),
+    ("STRENDS", 2, Some(2)),
+    ("STRBEFORE", 2, Some(2)),
+    ("STRAFTER", 2, Some(2)),
+    ("YEAR", 1, Some(1)),
+    ("MONTH", 1, Some(1)),
+    ("DAY", 1, Some(1)),
+    ("HOURS", 1, Some(1)),
+    ("MINUTES", 1, Some(1)),
+    ("SECONDS", 1, Some(1)),
+    ("TIMEZONE", 1, Some(1)),
+    ("TZ", 1, Some(1)),
+    ("NOW", 0, Some(0)),
+    ("UUID", 0, Some(0)),
+    ("STRUUID", 0, Some(0)),
+    ("MD5", 1, Some(1)),
+    ("SHA1", 1, Some(1)),
+    ("SHA256", 1, Some(1)),
+    ("SHA384", 1, Some(1)),
+    ("SHA512", 1, Some(1)),
+    ("COALESCE", 0, None),
+    ("IF", 3, Some(3)),
+    ("STRLANG", 2, Some(2)),
+    ("STRDT", 2, Some(2)),
+    ("sameTerm", 2, Some(2)),
+    ("isIRI", 1, Some(1)),
+    ("isURI", 1, Some(1)),
+    ("isBlank", 1, Some(1)),
+    ("isLiteral", 1, Some(1)),
+    ("isNumeric", 1, Some(1)),
+    ("REGEX", 2, Some(3)),
+    ("SUBSTR", 2, Some(3)),
+    ("REPLACE", 3, Some(4)),
+];
+
+fn check_variable(name: &str) -> Result<(), QueryError> {
+    let mut chars = name.chars();
+    let valid = match chars.next() {
+        Some(c) if is_pn_chars_u(c) || c.is_ascii_digit() => {
+            chars.all(|c| is_pn_chars(c) && c != '-')
+        }
+        _ => false,
+    };
+    if valid {
+        Ok(())
+    } else {
+        error(format!("\"{}\" is not a valid variable name", name))
+    }
+}
+
+fn check_value(value: &Value) -> Result<(), QueryError> {
+    value.check().map_err(|e| QueryError {
+        message: e.to_string(),
+    })
+}
+
+fn check_resource(resource: &Resource) -> Result<(), QueryError> {
+    check_value(&Value::from(resource))
+}
+
+fn check_term(term: &Term) -> Result<(), QueryError> {
+    match term {
+        Term::Variable(name) => check_variable(name),
+        Term::Value(value) => check_value(value),
+    }
+}
+
+fn check_path(path: &Path, nested: bool) -> Result<(), QueryError> {
+    match path {
+        Path::Variable(name) if !nested => check_variable(name),
+        Path::Variable(name) => error(format!("?{} cannot be used within a path", name)),
+        Path::Resource(resource) => check_resource(resource),
+        Path::Inverse(path)
+        | Path::ZeroOrMore(path)
+        | Path::OneOrMore(path)
+        | Path::ZeroOrOne(path) => check_path(path, true),
+        Path::Sequence(paths) | Path::Alternative(paths) if paths.is_empty() => {
+            error("Empty path".to_string())
+        }
+        Path::Sequence(paths) | Path::Alternative(paths) => {
+            paths.iter().try_for_each(|p| check_path(p, true))
+        }
+        Path::Negated(resources) if resources.is_empty() => {
+            error("Empty negated property set".to_string())
+        }
+        Path::Negated(resources) => resources.iter().try_for_each(check_resource),
+    }
+}
+
+fn has_aggregate(expression: &Expression) -> bool {
+    match expression {
+        Expression::Aggregate { .. } => true,
+        Expression::Variable(_)
+        | Expression::Value(_)
+        | Expression::Exists(_)
+        | Expression::NotExists(_) => false,
+        Expression::Not(e) => has_aggregate(e),
+        Expression::In(e, list) | Expression::NotIn(e, list) => {
+            has_aggregate(e) || list.iter().any(has_aggregate)
+        }
+        Expression::Call(_, arguments) | Expression::Function(_, arguments) => {
+            arguments.iter().any(has_aggregate)
+        }
+        Expression::Or(a, b)
+        | Expression::And(a, b)
+        | Expression::Equal(a, b)
+        | Expression::NotEqual(a, b)
+        | Expression::Less(a, b)
+        | Expression::LessOrEqual(a, b)
+        | Expression::Greater(a, b)
+        | Expression::GreaterOrEqual(a, b)
+        | Expression::Add(a, b)
+        | Expression::Subtract(a, b)
+        | Expression::Multiply(a, b)
+        | Expression::Divide(a, b) => has_aggregate(a) || has_aggregate(b),
+    }
+}
+
+/// Checks an expression, allowing aggregates only where solutions are grouped.
+fn check_expression(expression: &Expression, aggregates: bool) -> Result<(), QueryError> {
+    match expression {
+        Expression::Variable(name) => check_variable(name),
+        Expression::Value(value) => check_value(value),
+        Expression::Not(e) => check_expression(e, aggregates),
+        Expression::In(e, list) | Expression::NotIn(e, list) => {
+            check_expression(e, aggregates)?;
+            list.iter()
+                .try_for_each(|e| check_expression(e, aggregates))
+        }
+        Expression::Call(name, arguments) => {
+            match FUNCTIONS
+                .iter()
+                .find(|(f, _, _)| f.eq_ignore_ascii_case(name))
+            {
+                Some((_, min, max))
+                    if arguments.len() < *min || matches!(max, Some(m) if arguments.len() > *m) =>
+                {
+                    return error(format!(
+                        "{} does not take {} arguments",
+                        name,
+                        arguments.len()
+                    ))
+                }
+                Some(_) => (),
+                None => return error(format!("Unknown function {}", name)),
+            }
+            if name.eq_ignore_ascii_case("BOUND") {
+                if let Some(Expression::Variable(_)) = arguments.first() {
+                } else {
+                    return error("BOUND takes a variable".to_string());
+                }
+            }
+            arguments
+                .iter()
+                .try_for_each(|e| check_expression(e, aggregates))
+        }
+        Expression::Function(resource, arguments) => {
+            check_resource(resource)?;
+            arguments
+                .iter()
+                .try_for_each(|e| check_expression(e, aggregates))
+        }
+        Expression::Exists(patterns) | Expression::NotExists(patterns) => check_group(patterns),
+        Expression::Aggregate { .. } if !aggregates => {
+            error("Aggregates can only be used in a grouped query".to_string())
+        }
+        Expression::Aggregate {
+            function, argument, ..
+        } => match argument {
+            Some(argument) if has_aggregate(argument) => {
+                error("Aggregates cannot be nested".to_string())
+            }
+            Some(argument) => check_expression(argument, false),
+            None if *function == Aggregate::Count => Ok(()),
+            None => error(format!("{:?} needs an argument", function)),
+        },
+        Expression::Or(a, b)
+        | Expression::And(a, b)
+        | Expression::Equal(a, b)
+        | Expression::NotEqual(a, b)
+        | Expression::Less(a, b)
+        | Expression::LessOrEqual(a, b)
+        | Expression::Greater(a, b)
+        | Expression::GreaterOrEqual(a, b)
+        | Expression::Add(a, b)
+        | Expression::Subtract(a, b)
+        | Expression::Multiply(a, b)
+        | Expression::Divide(a, b) => {
+            check_expression(a, aggregates)?;
+            check_expression(b, aggregates)
+        }
+    }
+}
+
+/// Checks that the variables of an expression outside aggregates are grouped.
+fn check_grouped(expression: &Expression, group_by: &[String]) -> Result<(), QueryError> {
+    match expression {
+        Expression::Variable(name) if !group_by.contains(name) => {
+            error(format!("?{} is used but not grouped", name))
+        }
+        Expression::Variable(_)
+        | Expression::Value(_)
+        | Expression::Aggregate { .. }
+        | Expression::Exists(_)
+        | Expression::NotExists(_) => Ok(()),
+        Expression::Not(e) => check_grouped(e, group_by),
+        Expression::In(e, list) | Expression::NotIn(e, list) => {
+            check_grouped(e, group_by)?;
+            list.iter().try_for_each(|e| check_grouped(e, group_by))
+        }
+        Expression::Call(_, arguments) | Expression::Function(_, arguments) => arguments
+            .iter()
+            .try_for_each(|e| check_grouped(e, group_by)),
+        Expression::Or(a, b)
+        | Expression::And(a, b)
+        | Expression::Equal(a, b)
+        | Expression::NotEqual(a, b)
+        | Expression::Less(a, b)
+        | Expression::LessOrEqual(a, b)
+        | Expression::Greater(a, b)
+        | Expression::GreaterOrEqual(a, b)
+        | Expression::Add(a, b)
+        | Expression::Subtract(a, b)
+        | Expression::Multiply(a, b)
+        | Expression::Divide(a, b) => {
+            check_grouped(a, group_by)?;
+            check_grouped(b, group_by)
+        }
+    }
+}
+
+fn check_modifiers(modifiers: &Modifiers, aggregated: bool) -> Result<(), QueryError> {
+    for expression in &modifiers.having {
+        check_expression(expression, true)?;
+        check_grouped(expression, &modifiers.group_by)?;
+    }
+    for order in &modifiers.order_by {
+        match order {
+            Order::Ascending(e) | Order::Descending(e) => check_expression(e, aggregated)?,
+        }
+    }
+    Ok(())
+}
+
+/// Adds the variables bound by a group of patterns.
+fn bound_variables(patterns: &[Pattern], variables: &mut BTreeSet<String>) {
+    for pattern in patterns {
+        match pattern {
+            Pattern::Triple {
+                subject,
+                path,
+                object,
+            } => {
+                for term in &[subject, object] {
+                    if let Term::Variable(name) = term {
+                        variables.insert(name.to_string());
+                    }
+                }
+                if let Path::Variable(name) = path {
+                    variables.insert(name.to_string());
+                }
+            }
+            Pattern::Group(patterns) | Pattern::Optional(patterns) => {
+                bound_variables(patterns, variables)
+            }
+            Pattern::Union(alternatives) => {
+                for patterns in alternatives {
+                    bound_variables(patterns, variables);
+                }
+            }
+            Pattern::Graph { name, patterns } => {
+                if let Term::Variable(name) = name {
+                    variables.insert(name.to_string());
+                }
+                bound_variables(patterns, variables);
+            }
+            Pattern::Bind { variable, .. } => {
+                variables.insert(variable.to_string());
+            }
+            Pattern::Values {
+                variables: names, ..
+            } => variables.extend(names.iter().cloned()),
+            Pattern::Filter(_) => (),
+        }
+    }
+}
+
+fn check_group(patterns: &[Pattern]) -> Result<(), QueryError> {
+    for (i, pattern) in patterns.iter().enumerate() {
+        match pattern {
+            Pattern::Triple {
+                subject,
+                path,
+                object,
+            } => {
+                check_term(subject)?;
+                check_path(path, false)?;
+                check_term(object)?;
+            }
+            Pattern::Group(patterns) | Pattern::Optional(patterns) => check_group(patterns)?,
+            Pattern::Union(alternatives) if alternatives.len() < 2 => {
+                return error("A union needs at least two alternatives".to_string())
+            }
+            Pattern::Union(alternatives) => alternatives.iter().try_for_each(|p| check_group(p))?,
+            Pattern::Filter(expression) => check_expression(expression, false)?,
+            Pattern::Graph { name, patterns } => {
+                match name {
+                    Term::Variable(name) => check_variable(name)?,
+                    Term::Value(Value::Resource(resource)) => check_resource(resource)?,
+                    Term::Value(value) => {
+                        return error(format!("{} cannot name a graph", value.write()))
+                    }
+                }
+                check_group(patterns)?;
+            }
+            Pattern::Bind {
+                expression,
+                variable,
+            } => {
+                check_variable(variable)?;
+                check_expression(expression, false)?;
+                let mut in_scope = BTreeSet::new();
+                bound_variables(&patterns[..i], &mut in_scope);
+                if in_scope.contains(variable) {
+                    return error(format!("?{} is already bound before BIND", variable));
+                }
+            }
+            Pattern::Values { variables, rows } => {
+                variables.iter().try_for_each(|v| check_variable(v))?;
+                rows.iter().flatten().flatten().try_for_each(check_value)?;
+                if let Some(row) = rows.iter().find(|r| r.len() != variables.len()) {
+                    return error(format!(
+                        "VALUES row has {} values for {} variables",
+                        row.len(),
+                        variables.len()
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn iri(resource: &Resource) -> ast::Iri {
+    ast::Iri::Full(resource.0.clone())
+}
+
+fn term(term: &Term) -> ast::Term {
+    match term {
+        Term::Variable(name) => ast::Term::Variable(name.to_string()),
+        Term::Value(value) => value.term(),
+    }
+}
+
+fn path(path: &Path) -> ast::Path {
+    match path {
+        Path::Variable(name) => unreachable!("?{} was checked not to be within a path", name),
+        Path::Resource(resource) => ast::Path::Iri(iri(resource)),
+        Path::Inverse(p) => ast::Path::Inverse(Box::new(self::path(p))),
+        Path::Sequence(paths) => ast::Path::Sequence(paths.iter().map(self::path).collect()),
+        Path::Alternative(paths) => ast::Path::Alternative(paths.iter().map(self::path).collect()),
+        Path::ZeroOrMore(p) => ast::Path::ZeroOrMore(Box::new(self::path(p))),
+        Path::OneOrMore(p) => ast::Path::OneOrMore(Box::new(self::path(p))),
+        Path::ZeroOrOne(p) => ast::Path::ZeroOrOne(Box::new(self::path(p))),
+        Path::Negated(resources) => {
+            ast::Path::Negated(resources.iter().map(|r| ast::Path::Iri(iri(r))).collect())
+        }
+    }
+}
+
+fn verb(predicate: &Path) -> ast::Verb {
+    match predicate {
+        Path::Variable(name) => ast::Verb::Variable(name.to_string()),
+        predicate => ast::Verb::Path(path(predicate)),
+    }
+}
+
+fn triple(subject: &Term, predicate: &Path, object: &Term) -> ast::TriplePattern {
+    ast::TriplePattern {
+        subject: ast::GraphNode::Term(term(subject)),
+        properties: vec![ast::Property {
+            verb: verb(predicate),
+            objects: vec![ast::GraphNode::Term(term(object))],
+        }],
+    }
+}
+
+fn expression(expression: &Expression) -> ast::Expression {
+    let binary = |a: &Expression, b: &Expression| {
+        (Box::new(self::expression(a)), Box::new(self::expression(b)))
+    };
+    let list = |expressions: &[Expression]| expressions.iter().map(self::expression).collect();
+    match expression {
+        Expression::Variable(name) => ast::Expression::Term(ast::Term::Variable(name.to_string())),
+        Expression::Value(value) => ast::Expression::Term(value.term()),
+        Expression::Or(a, b) => {
+            let (a, b) = binary(a, b);
+            ast::Expression::Or(a, b)
+        }
+        Expression::And(a, b) => {
+            let (a, b) = binary(a, b);
+            ast::Expression::And(a, b)
+        }
+        Expression::Equal(a, b) => {
+            let (a, b) = binary(a, b);
+            ast::Expression::Equal(a, b)
+        }
+        Expression::NotEqual(a, b) => {
+            let (a, b) = binary(a, b);
+            ast::Expression::NotEqual(a, b)
+        }
+        Expression::Less(a, b) => {
+            let (a, b) = binary(a, b);
+            ast::Expression::Less(a, b)
+        }
+        Expression::LessOrEqual(a, b) => {
+            let (a, b) = binary(a, b);
+            ast::Expression::LessOrEqual(a, b)
+        }
+        Expression::Greater(a, b) => {
+            let (a, b) = binary(a, b);
+            ast::Expression::Greater(a, b)
+        }
+        Expression::GreaterOrEqual(a, b) => {
+            let (a, b) = binary(a, b);
+            ast::Expression::GreaterOrEqual(a, b)
+        }
+        Expression::Add(a, b) => {
+            let (a, b) = binary(a, b);
+            ast::Expression::Add(a, b)
+        }
+        Expression::Subtract(a, b) => {
+            let (a, b) = binary(a, b);
+            ast::Expression::Subtract(a, b)
+        }
+        Expression::Multiply(a, b) => {
+            let (a, b) = binary(a, b);
+            ast::Expression::Multiply(a, b)
+        }
+        Expression::Divide(a, b) => {
+            let (a, b) = binary(a, b);
+            ast::Expression::Divide(a, b)
+        }
+        Expression::In(e, expressions) => {
+            ast::Expression::In(Box::new(self::expression(e)), list(expressions))
+        }
+        Expression::NotIn(e, expressions) => {
+            ast::Expression::NotIn(Box::new(self::expression(e)), list(expressions))
+        }
+        Expression::Not(e) => ast::Expression::Not(Box::new(self::expression(e))),
+        Expression::Call(name, arguments) => {
+            let name = FUNCTIONS
+                .iter()
+                .find(|(f, _, _)| f.eq_ignore_ascii_case(name))
+                .map_or(name.as_str(), |(f, _, _)| f);
+            ast::Expression::BuiltIn(name.to_string(), list(arguments))
+        }
+        Expression::Function(resource, arguments) => ast::Expression::Function {
+            iri: iri(resource),
+            distinct: false,
+            arguments: list(arguments),
+        },
+        Expression::Exists(patterns) => ast::Expression::Exists(Box::new(group(patterns))),
+        Expression::NotExists(patterns) => ast::Expression::NotExists(Box::new(group(patterns))),
+        Expression::Aggregate {
+            function,
+            distinct,
+            argument,
+        } => {
+            let name = match function {
+                Aggregate::Count => "COUNT",
+                Aggregate::Sum => "SUM",
+                Aggregate::Min => "MIN",
+                Aggregate::Max => "MAX",
+                Aggregate::Avg => "AVG",
+                Aggregate::Sample => "SAMPLE",
+                Aggregate::GroupConcat(_) => "GROUP_CONCAT",
+            };
+            let separator = match function {
+                Aggregate::GroupConcat(separator) => separator.clone(),
+                _ => None,
+            };
+            ast::Expression::Aggregate(ast::Aggregate {
+                name: name.to_string(),
+                distinct: *distinct,
+                argument: argument.as_ref().map(|a| Box::new(self::expression(a))),
+                separator,
+            })
+        }
+    }
+}
+
+/// Converts a group of patterns, with consecutive triples in one block as the parser reads them.
+fn group(patterns: &[Pattern]) -> ast::GroupPattern {
+    let mut elements: Vec<ast::Element> = vec![];
+    for pattern in patterns {
+        let element = match pattern {
+            Pattern::Triple {
+                subject,
+                path,
+                object,
+            } => {
+                let triple = self::triple(subject, path, object);
+                if let Some(ast::Element::Triples(triples)) = elements.last_mut() {
+                    triples.push(triple);
+                    continue;
+                }
+                ast::Element::Triples(vec![triple])
+            }
+            Pattern::Group(patterns) => ast::Element::Group(group(patterns)),
+            Pattern::Optional(patterns) => ast::Element::Optional(group(patterns)),
+            Pattern::Union(alternatives) => {
+                ast::Element::Union(alternatives.iter().map(|p| group(p)).collect())
+            }
+            Pattern::Filter(e) => ast::Element::Filter(expression(e)),
+            Pattern::Graph { name, patterns } => {
+                let name = match term(name) {
+                    ast::Term::Variable(name) => ast::VarOrIri::Variable(name),
+                    ast::Term::Iri(iri) => ast::VarOrIri::Iri(iri),
+                    name => unreachable!("{} was checked to name a graph", name),
+                };
+                ast::Element::Graph(name, group(patterns))
+            }
+            Pattern::Bind {
+                expression: e,
+                variable,
+            } => ast::Element::Bind(expression(e), variable.to_string()),
+            Pattern::Values { variables, rows } => ast::Element::Values(ast::Values {
+                variables: variables.clone(),
+                rows: rows
+                    .iter()
+                    .map(|row| row.iter().map(|v| v.as_ref().map(Value::term)).collect())
+                    .collect(),
+            }),
+        };
+        elements.push(element);
+    }
+    ast::GroupPattern::Elements(elements)
+}
+
+fn modifiers(modifiers: &Modifiers) -> ast::Modifiers {
+    ast::Modifiers {
+        group_by: modifiers
+            .group_by
+            .iter()
+            .map(|v| ast::GroupCondition {
+                expression: ast::Expression::Term(ast::Term::Variable(v.to_string())),
+                variable: None,
+            })
+            .collect(),
+        having: modifiers.having.iter().map(expression).collect(),
+        order_by: modifiers
+            .order_by
+            .iter()
+            .map(|o| match o {
+                Order::Ascending(e) => ast::OrderCondition {
+                    expression: expression(e),
+                    descending: false,
+                },
+                Order::Descending(e) => ast::OrderCondition {
+                    expression: expression(e),
+                    descending: true,
+                },
+            })
+            .collect(),
+        limit: modifiers.limit,
+        offset: modifiers.offset,
+    }
+}
+
+/// A query without prologue, dataset or trailing VALUES, as the builders produce it.
+fn query(form: ast::QueryForm, patterns: &[Pattern], modifiers: &Modifiers) -> ast::Query {
+    ast::Query {
+        prologue: vec![],
+        form,
+        dataset: vec![],
+        pattern: group(patterns),
+        modifiers: self::modifiers(modifiers),
+        values: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::{
+        Aggregate, AskBuilder, ConstructBuilder, Expression, Path, Pattern, SelectBuilder, Term,
+    };
+    use crate::query::Value;
+    use crate::{sparql_ast, Resource};
+
+    /// Parses a built query and writes it again, which gives the same text.
+    fn reparsed(query: &str) -> String {
+        sparql_ast::parse_query(query).unwrap().to_string()
+    }
+
+    fn label() -> Resource {
+        Resource::parse("http://www.w3.org/2000/01/rdf-schema#label").unwrap()
+    }
+
+    #[test]
+    fn builds_grouped_selection() {
+        let knows = Resource::parse("http://xmlns.com/foaf/0.1/knows").unwrap();
+        let selection = SelectBuilder::new()
+            .variable("person")
+            .expression(
+                Expression::aggregate_distinct(Aggregate::Count, Some(Expression::var("friend"))),
+                "friends",
+            )
+            .pattern(Pattern::graph(
+                Term::var("g"),
+                vec![Pattern::triple(
+                    Term::var("person"),
+                    Path::from(&knows).one_or_more(),
+                    Term::var("friend"),
+                )],
+            ))
+            .pattern(Pattern::optional(vec![Pattern::triple(
+                Term::var("person"),
+                label(),
+                Term::var("name"),
+            )]))
+            .pattern(Pattern::filter(Expression::call(
+                "isuri",
+                vec![Expression::var("person")],
+            )))
+            .group_by("person")
+            .having(
+                Expression::aggregate(Aggregate::Count, None).greater_than(Expression::value(1i64)),
+            )
+            .order_by_descending(Expression::var("friends"))
+            .limit(10)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            selection.sparql_value,
+            "SELECT ?person (COUNT(DISTINCT ?friend) AS ?friends)
+WHERE {
+  GRAPH ?g {
+    ?person <http://xmlns.com/foaf/0.1/knows>+ ?friend .
+  }
+  OPTIONAL {
+    ?person <http://www.w3.org/2000/01/rdf-schema#label> ?name .
+  }
+  FILTER(isURI(?person))
+}
+GROUP BY ?person
+HAVING (COUNT(*) > \"1\"^^<http://www.w3.org/2001/XMLSchema#integer>)
+ORDER BY DESC(?friends)
+LIMIT 10"
+        );
+        assert_eq!(reparsed(&selection.sparql_value), selection.sparql_value);
+    }
+
+    #[test]
+    fn builds_ask_and_construct_queries() {
+        let ask = AskBuilder::new()
+            .pattern(Pattern::union(vec![
+                vec![Pattern::triple(Term::var("s"), label(), Term::var("o"))],
+                vec![Pattern::values(
+                    &["s", "o"],
+                    vec![vec![None, Some(Value::string("x"))]],
+                )],
+            ]))
+            .build()
+            .unwrap();
+        assert_eq!(
+            ask.sparql_value,
+            "ASK
+WHERE {
+  {
+    ?s <http://www.w3.org/2000/01/rdf-schema#label> ?o .
+  } UNION {
+    VALUES (?s ?o) {
+      (UNDEF \"x\")
+    }
+  }
+}"
+        );
+        assert_eq!(reparsed(&ask.sparql_value), ask.sparql_value);
+
+        let construct = ConstructBuilder::new()
+            .template(Term::var("o"), label(), Term::var("s"))
+            .pattern(Pattern::triple(
+                Term::var("s"),
+                Path::from(label()).inverse(),
+                Term::var("o"),
+            ))
+            .pattern(Pattern::bind(
+                Expression::var("o") + Expression::value(1i64),
+                "n",
+            ))
+            .build()
+            .unwrap();
+        assert_eq!(
+            construct.sparql_value,
+            "CONSTRUCT {
+  ?o <http://www.w3.org/2000/01/rdf-schema#label> ?s .
+}
+WHERE {
+  ?s ^<http://www.w3.org/2000/01/rdf-schema#label> ?o .
+  BIND(?o + \"1\"^^<http://www.w3.org/2001/XMLSchema#integer> AS ?n)
+}"
+        );
+        assert_eq!(reparsed(&construct.sparql_value), construct.sparql_value);
+    }
+
+    #[test]
+    fn rejects_malformed_queries() {
+        let triple = || Pattern::triple(Term::var("s"), label(), Term::var("o"));
+
+        let ungrouped = SelectBuilder::new()
+            .variable("s")
+            .expression(Expression::aggregate(Aggregate::Count, None), "n")
+            .pattern(triple())
+            .build();
+        let rebound = SelectBuilder::new()
+            .pattern(triple())
+            .pattern(Pattern::bind(Expression::value(1i64), "o"))
+            .build();
+        let aggregate_in_filter = AskBuilder::new()
+            .pattern(triple())
+            .pattern(Pattern::filter(Expression::aggregate(
+                Aggregate::Sum,
+                Some(Expression::var("o")),
+            )))
+            .build();
+        let variable_in_path = AskBuilder::new()
+            .pattern(Pattern::triple(
+                Term::var("s"),
+                Path::var("p").zero_or_more(),
+                Term::var("o"),
+            ))
+            .build();
+        let wrong_arity = AskBuilder::new()
+            .pattern(triple())
+            .pattern(Pattern::filter(Expression::call("regex", vec![])))
+            .build();
+        let invalid_name = SelectBuilder::new().variable("a b").build();
+        let path_in_template = ConstructBuilder::new()
+            .template(
+                Term::var("s"),
+                Path::from(label()).zero_or_one(),
+                Term::var("o"),
+            )
+            .build();
+        let short_row = AskBuilder::new()
+            .pattern(Pattern::values(&["a", "b"], vec![vec![None]]))
+            .build();
+        let invalid_iri = AskBuilder::new()
+            .pattern(Pattern::triple(
+                Term::var("s"),
+                Resource::unsafe_from("http://example.org/> ?p ?o"),
+                Term::var("o"),
+            ))
+            .build();
+
+        assert!(ungrouped.is_err());
+        assert!(rebound.is_err());
+        assert!(aggregate_in_filter.is_err());
+        assert!(variable_in_path.is_err());
+        assert!(wrong_arity.is_err());
+        assert!(invalid_name.is_err());
+        assert!(path_in_template.is_err());
+        assert!(short_row.is_err());
+        assert!(invalid_iri.is_err());
+    }
+}
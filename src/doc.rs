@@ -1,4 +1,6 @@
+use crate::error::Error;
 use crate::http::Dataset;
+use crate::query::ParameterError;
 use crate::table::Variable;
 use crate::{GraphStore, Resource, Selection};
 use rdf::node::Node;
@@ -8,43 +10,43 @@ use std::fs;
 use typed_html::dom::DOMTree;
 use typed_html::{html, text};
 
-pub async fn export_to_html<'a>(dataset: &'a Dataset<'a>) {
+/// Writes a page for each resource with its relations, and an index of the pages, to the
+/// working directory.
+pub async fn export_to_html(dataset: &Dataset) -> Result<(), Error> {
     #[derive(Debug)]
     struct ResourceProperties {
         label: String,
         file_name: String,
     }
 
-    let map: HashMap<Resource, ResourceProperties> = dataset
+    let mut map: HashMap<Resource, ResourceProperties> = HashMap::new();
+    let resources = dataset
         .select(Selection::of_resources_with_labels())
-        .await
-        .bindings
-        .into_iter()
-        .map(|r| {
-            let resource = match r.get(&Variable::from("resource")) {
-                Some(rdf::node::Node::UriNode { uri: id }) => id.to_string().clone(),
-                _ => panic!("Unexpected resource"),
-            };
-            (
-                Resource::from(resource.as_ref()),
-                ResourceProperties {
-                    label: match r.get(&Variable::from("label")) {
-                        Some(rdf::node::Node::LiteralNode {
-                            literal: s,
-                            data_type: _,
-                            language: _,
-                        }) => s.to_string(),
-                        _ => resource.to_string(),
-                    },
-                    file_name: {
-                        let mut hasher = Sha256::new();
-                        hasher.input(&resource.to_string());
-                        format!("{}.html", hex::encode(hasher.result()))
-                    },
+        .await?;
+    for r in resources.bindings {
+        let resource = match r.get(&Variable::from("resource")) {
+            Some(rdf::node::Node::UriNode { uri: id }) => id.to_string().clone(),
+            r => return Err(Error::Response(format!("Unexpected resource {:?}", r))),
+        };
+        map.insert(
+            Resource::unsafe_from(&resource),
+            ResourceProperties {
+                label: match r.get(&Variable::from("label")) {
+                    Some(rdf::node::Node::LiteralNode {
+                        literal: s,
+                        data_type: _,
+                        language: _,
+                    }) => s.to_string(),
+                    _ => resource.to_string(),
                 },
-            )
-        })
-        .collect();
+                file_name: {
+                    let mut hasher = Sha256::new();
+                    hasher.input(&resource);
+                    format!("{}.html", hex::encode(hasher.result()))
+                },
+            },
+        );
+    }
 
     fn resource_component(
         map: &HashMap<Resource, ResourceProperties>,
@@ -53,7 +55,7 @@ pub async fn export_to_html<'a>(dataset: &'a Dataset<'a>) {
     ) -> Box<typed_html::elements::a<String>> {
         match hash_map.get(selector) {
             Some(rdf::node::Node::UriNode { uri: u }) => {
-                let resource = Resource::from(u.to_string().as_str());
+                let resource = Resource::unsafe_from(u.to_string());
                 match map.get(&resource) {
                     Some(props) => {
                         let href = &props.file_name;
@@ -68,13 +70,18 @@ pub async fn export_to_html<'a>(dataset: &'a Dataset<'a>) {
                 data_type: _,
             }) => html!(<a>{ text!("{}", s) }</a>),
             Some(rdf::node::Node::BlankNode { id }) => html!(<a>{ text!("Blank node {}", id) }</a>),
-            r => panic!("Unexpected resource {:?}", r),
+            None => html!(<a>{ text!("Unbound") }</a>),
         }
     }
 
     for (r, props) in map.iter() {
-        let links_from = dataset.select(Selection::of_relations_from(r)).await;
-        let links_to = dataset.select(Selection::of_relations_to(r)).await;
+        let invalid = |e: ParameterError| Error::Response(e.to_string());
+        let links_from = dataset
+            .select(Selection::of_relations_from(r).map_err(invalid)?)
+            .await?;
+        let links_to = dataset
+            .select(Selection::of_relations_to(r).map_err(invalid)?)
+            .await?;
 
         let links_from_html = links_from.bindings.iter().map(|link| {
             html!(
@@ -113,14 +120,10 @@ pub async fn export_to_html<'a>(dataset: &'a Dataset<'a>) {
             </html>
         );
 
-        fs::write(
-            props.file_name.to_string(),
-            format!("<!doctype html>{}", doc.to_string()),
-        )
-        .expect("Could not write file");
+        fs::write(&props.file_name, format!("<!doctype html>{}", doc))?;
     }
 
-    let links = map.iter().map(|(_, props)| {
+    let links = map.values().map(|props| {
         let href = &props.file_name;
         let label = &props.label;
         html!(
@@ -145,6 +148,6 @@ pub async fn export_to_html<'a>(dataset: &'a Dataset<'a>) {
         </html>
     );
 
-    fs::write("index.html", format!("<!doctype html>{}", doc.to_string()))
-        .expect("Could not write index");
+    fs::write("index.html", format!("<!doctype html>{}", doc))?;
+    Ok(())
 }
@@ -0,0 +1,110 @@
+use crate::http::BuildError;
+use std::fmt;
+
+/// An error from a graph store.
+#[derive(Debug)]
+pub enum Error {
+    /// The request exceeded its timeout, on the client or on the server.
+    Timeout,
+    /// The request could not be sent or its response could not be read.
+    Http(reqwest::Error),
+    /// The server responded with an unexpected status.
+    Status {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    /// The server responded with content that could not be parsed.
+    Response(String),
+    /// The dataset with this name does not exist.
+    NotFound(String),
+    /// The dataset with this name cannot be created because it already exists.
+    AlreadyExists(String),
+    /// The dataset with this name cannot be restored because it has data.
+    NotEmpty(String),
+    /// A file or writer could not be read or written.
+    Io(std::io::Error),
+    /// The dataset is not configured for the operation.
+    Build(BuildError),
+    /// The last error of a request that was retried.
+    Retried { retries: u32, error: Box<Error> },
+}
+
+impl Error {
+    /// A short name of the variant, for logs and metrics. Retried errors have the kind of the
+    /// last error.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Timeout => "timeout",
+            Error::Http(_) => "http",
+            Error::Status { .. } => "status",
+            Error::Response(_) => "response",
+            Error::NotFound(_) => "not_found",
+            Error::AlreadyExists(_) => "already_exists",
+            Error::NotEmpty(_) => "not_empty",
+            Error::Io(_) => "io",
+            Error::Build(_) => "build",
+            Error::Retried { error, .. } => error.kind(),
+        }
+    }
+
+    /// The number of times that the failed request was retried.
+    pub fn retries(&self) -> u32 {
+        match self {
+            Error::Retried { retries, .. } => *retries,
+            _ => 0,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Timeout => write!(f, "Request timed out"),
+            Error::Http(e) => write!(f, "HTTP error: {}", e),
+            Error::Status { status, message } => {
+                write!(f, "Unexpected status {} with message {}", status, message)
+            }
+            Error::Response(message) => write!(f, "Unexpected response: {}", message),
+            Error::NotFound(name) => write!(f, "Dataset {} does not exist", name),
+            Error::AlreadyExists(name) => write!(f, "Dataset {} already exists", name),
+            Error::NotEmpty(name) => write!(f, "Dataset {} is not empty", name),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Build(e) => write!(f, "{}", e),
+            Error::Retried { retries, error } => write!(f, "{} after {} retries", error, retries),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::Build(e) => Some(e),
+            Error::Retried { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Error::Timeout
+        } else {
+            Error::Http(e)
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<BuildError> for Error {
+    fn from(e: BuildError) -> Self {
+        Error::Build(e)
+    }
+}
@@ -1,74 +1,1043 @@
-use crate::{DataFile, DescribeQuery, Graph, GraphStore, Resource, Selection};
+use crate::{
+    AskQuery, ConstructQuery, Content, DataFile, DescribeQuery, Format, Graph, GraphStore,
+    QuadFormat, QueryDataset, Resource, Selection, Upload,
+};
 
+use crate::auth::Credentials;
+use crate::error::Error;
+use crate::interceptor::{self, Interceptor, RequestKind};
+use crate::iri::IriError;
+use crate::json_ld;
+use crate::prefix::PrefixMap;
+use crate::retry::RetryPolicy;
+use crate::syntax;
 use crate::table::Table;
+use crate::telemetry::{observe, Kind};
 use async_trait::async_trait;
+use flate2::write::GzEncoder;
+use futures::Stream;
 use rdf::node::Node;
 use rdf::reader::rdf_parser::RdfParser;
 use rdf::reader::turtle_parser::TurtleParser;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
 /// Implementation of https://www.w3.org/TR/sparql11-http-rdf-update/
-pub struct Dataset<'a> {
-    client: &'a reqwest::Client,
-    base: url::Url,
+///
+/// Clones share the connection pool of the client, so a dataset can be cloned into tasks or
+/// shared as an `Arc<dyn GraphStore>`.
+#[derive(Clone)]
+pub struct Dataset {
+    client: reqwest::Client,
     name: String,
+    endpoints: Endpoints,
+    credentials: Option<Credentials>,
+    admin_credentials: Option<Credentials>,
+    prefixes: PrefixMap,
+    resource_base: Option<Resource>,
+    timeout: Option<Duration>,
+    retry_policy: RetryPolicy,
+    interceptors: Vec<Arc<dyn Interceptor>>,
 }
 
-impl DataFile {
-    fn multipart(self) -> reqwest::multipart::Part {
+/// The endpoints that a request is sent to, which may need different credentials.
+#[derive(Clone, Copy)]
+enum Access {
+    Data,
+    Admin,
+}
+
+/// Whether a request can be sent again after a transient failure.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Retry {
+    Idempotent,
+    /// Retried only if the retry policy allows it, since the request may have had effect.
+    NotIdempotent,
+}
+
+impl Retry {
+    /// Whether to retry after the result of an attempt. A query that Fuseki cancelled after its
+    /// timeout results in `Error::Timeout`, which is not retried.
+    fn allows<T>(self, policy: &RetryPolicy, retries: u32, result: &Result<T, Error>) -> bool {
+        match result {
+            Ok(_) => false,
+            Err(error) => {
+                policy.allows(retries, self == Retry::Idempotent) && policy.is_retriable(error)
+            }
+        }
+    }
+}
+
+/// The URLs of the services of a dataset.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Endpoints {
+    pub query: url::Url,
+    pub update: url::Url,
+    /// The Graph Store Protocol endpoint, which imports and exports graphs.
+    pub graph_store: url::Url,
+    /// The endpoint that creates and deletes datasets, if the server has one.
+    pub admin: Option<url::Url>,
+}
+
+impl Endpoints {
+    /// The layout of Fuseki, with datasets below the base URL, which may include a context path
+    /// such as `https://example.org/fuseki/`.
+    pub fn fuseki(base: &url::Url, name: &str) -> Endpoints {
+        Endpoints {
+            query: with_segments(base, &[name]),
+            update: with_segments(base, &[name, "update"]),
+            graph_store: with_segments(base, &[name, "data"]),
+            admin: Some(with_segments(base, &["$", "datasets"])),
+        }
+    }
+}
+
+/// Appends percent-encoded segments to the path of a URL, with or without a trailing slash.
+fn with_segments(base: &url::Url, segments: &[&str]) -> url::Url {
+    let mut url = base.clone();
+    url.path_segments_mut()
+        .expect("Base URL cannot have a path")
+        .pop_if_empty()
+        .extend(segments);
+    url
+}
+
+/// How Fuseki stores a dataset that it creates.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DatasetType {
+    /// In memory, so that the dataset is lost when the server restarts.
+    Mem,
+    Tdb,
+    Tdb2,
+}
+
+impl DatasetType {
+    fn as_str(self) -> &'static str {
         match self {
-            DataFile::Turtle { content: contents } => reqwest::multipart::Part::bytes(contents)
-                .file_name("file.ttl")
-                .mime_str("text/turtle")
-                .unwrap(),
-            DataFile::RdfXml { content: contents } => reqwest::multipart::Part::bytes(contents)
-                .file_name("file.xml")
-                .mime_str("text/xml")
-                .unwrap(),
+            DatasetType::Mem => "mem",
+            DatasetType::Tdb => "tdb",
+            DatasetType::Tdb2 => "tdb2",
+        }
+    }
+}
+
+/// What the admin endpoint receives to create a dataset.
+#[derive(Clone)]
+enum Creation {
+    Type(DatasetType),
+    Assembler(Assembler),
+}
+
+/// A Fuseki assembler configuration in Turtle, or the file that holds it.
+#[derive(Clone)]
+enum Assembler {
+    Config(String),
+    File(PathBuf),
+}
+
+/// The predicate that names a service in a Fuseki configuration.
+const FUSEKI_NAME: &str = "http://jena.apache.org/fuseki#name";
+
+impl Assembler {
+    /// Reads the configuration, and checks that it names one service like the dataset.
+    fn config(&self, name: &str) -> Result<String, BuildError> {
+        let config = match self {
+            Assembler::Config(config) => config.clone(),
+            Assembler::File(path) => std::fs::read_to_string(path).map_err(|e| BuildError {
+                message: format!("Cannot read {}: {}", path.display(), e),
+            })?,
+        };
+        let names = syntax::top_level_values(&config, FUSEKI_NAME).map_err(|e| BuildError {
+            message: format!("Invalid assembler configuration: {}", e),
+        })?;
+        match names.as_slice() {
+            [service] if service.trim_start_matches('/') == name => Ok(config),
+            _ => Err(BuildError {
+                message: format!(
+                    "The assembler configuration names {:?} instead of one service {:?}",
+                    names, name
+                ),
+            }),
+        }
+    }
+}
+
+/// A dataset that was rejected by its builder.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BuildError {
+    pub message: String,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid dataset: {}", self.message)
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// Builds a dataset from the endpoints of its services, such as those of a server with another
+/// layout than Fuseki or those exposed through a gateway.
+pub struct DatasetBuilder {
+    client: Option<reqwest::Client>,
+    name: String,
+    query: Option<url::Url>,
+    update: Option<url::Url>,
+    graph_store: Option<url::Url>,
+    admin: Option<url::Url>,
+    credentials: Option<Credentials>,
+    admin_credentials: Option<Credentials>,
+    retry_policy: RetryPolicy,
+    interceptors: Vec<Arc<dyn Interceptor>>,
+    creation: Creation,
+}
+
+impl DatasetBuilder {
+    pub fn new(name: &str) -> DatasetBuilder {
+        DatasetBuilder {
+            client: None,
+            name: name.to_string(),
+            query: None,
+            update: None,
+            graph_store: None,
+            admin: None,
+            credentials: None,
+            admin_credentials: None,
+            retry_policy: RetryPolicy::none(),
+            interceptors: vec![],
+            creation: Creation::Type(DatasetType::Mem),
+        }
+    }
+
+    /// Shares the connection pool of a client, instead of creating one.
+    pub fn client(mut self, client: reqwest::Client) -> DatasetBuilder {
+        self.client = Some(client);
+        self
+    }
+
+    /// Sets all endpoints to those of Fuseki, which the other methods can then replace.
+    pub fn fuseki(self, base: &url::Url) -> DatasetBuilder {
+        let endpoints = Endpoints::fuseki(base, &self.name);
+        DatasetBuilder {
+            query: Some(endpoints.query),
+            update: Some(endpoints.update),
+            graph_store: Some(endpoints.graph_store),
+            admin: endpoints.admin,
+            ..self
+        }
+    }
+
+    pub fn query_endpoint(mut self, url: url::Url) -> DatasetBuilder {
+        self.query = Some(url);
+        self
+    }
+
+    pub fn update_endpoint(mut self, url: url::Url) -> DatasetBuilder {
+        self.update = Some(url);
+        self
+    }
+
+    pub fn graph_store_endpoint(mut self, url: url::Url) -> DatasetBuilder {
+        self.graph_store = Some(url);
+        self
+    }
+
+    /// Sets the endpoint to which the name of the dataset is posted to create it, and below
+    /// which it is deleted.
+    pub fn admin_endpoint(mut self, url: url::Url) -> DatasetBuilder {
+        self.admin = Some(url);
+        self
+    }
+
+    /// Sets the credentials for all endpoints, unless the admin endpoint has its own.
+    pub fn credentials(mut self, credentials: Credentials) -> DatasetBuilder {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    pub fn admin_credentials(mut self, credentials: Credentials) -> DatasetBuilder {
+        self.admin_credentials = Some(credentials);
+        self
+    }
+
+    /// Sets how the dataset is stored if it is created, in memory by default.
+    pub fn dataset_type(mut self, dataset_type: DatasetType) -> DatasetBuilder {
+        self.creation = Creation::Type(dataset_type);
+        self
+    }
+
+    /// Creates the dataset from a Fuseki assembler configuration in Turtle instead of from its
+    /// name and type, for example to add a text index or inference. Creating the dataset fails
+    /// with `Error::Build` unless `fuseki:name` names one service like the dataset.
+    pub fn assembler(mut self, config: &str) -> DatasetBuilder {
+        self.creation = Creation::Assembler(Assembler::Config(config.to_string()));
+        self
+    }
+
+    /// Creates the dataset from a Fuseki assembler configuration in a Turtle file, which is
+    /// read and checked like the configuration of `assembler` when the dataset is created.
+    pub fn assembler_file<P: AsRef<Path>>(mut self, path: P) -> DatasetBuilder {
+        self.creation = Creation::Assembler(Assembler::File(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Retries requests after transient failures, including the request that creates the
+    /// dataset.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> DatasetBuilder {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Adds an interceptor, which also runs around the request that creates the dataset.
+    pub fn interceptor<I: Interceptor + 'static>(mut self, interceptor: I) -> DatasetBuilder {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    /// Returns a dataset that is expected to exist, without contacting the server.
+    pub fn build(self) -> Result<Dataset, BuildError> {
+        let missing = |endpoint: &str| BuildError {
+            message: format!("No {} endpoint", endpoint),
+        };
+        let admin_credentials = match self.admin_credentials {
+            Some(credentials) => Some(credentials),
+            None => self.credentials.clone(),
+        };
+        Ok(Dataset {
+            client: self.client.unwrap_or_default(),
+            endpoints: Endpoints {
+                query: self.query.ok_or_else(|| missing("query"))?,
+                update: self.update.ok_or_else(|| missing("update"))?,
+                graph_store: self.graph_store.ok_or_else(|| missing("graph store"))?,
+                admin: self.admin,
+            },
+            admin_credentials,
+            credentials: self.credentials,
+            name: self.name,
+            prefixes: PrefixMap::default(),
+            resource_base: None,
+            timeout: None,
+            retry_policy: self.retry_policy,
+            interceptors: self.interceptors,
+        })
+    }
+
+    /// Returns a dataset, which is first created if there is an admin endpoint. A dataset that
+    /// already exists is kept as it is.
+    pub async fn get_or_create(self) -> Result<Dataset, Error> {
+        let creation = self.creation.clone();
+        let dataset = self.build()?;
+        if dataset.endpoints.admin.is_some() {
+            let created = |response: reqwest::Response| async move {
+                match response.status() {
+                    reqwest::StatusCode::CONFLICT | reqwest::StatusCode::OK => Ok(()),
+                    status => Err(Error::Status {
+                        status,
+                        message: response.text().await?,
+                    }),
+                }
+            };
+            dataset
+                .send_create(&creation, Retry::Idempotent, created)
+                .await?;
+        }
+        Ok(dataset)
+    }
+
+    /// Returns a dataset after checking with a query that it exists, which does not need
+    /// access to the admin endpoint.
+    pub async fn open(self) -> Result<Dataset, Error> {
+        let dataset = self.build()?;
+        match dataset.ask(AskQuery::unsafe_from("ASK {}")).await {
+            Ok(_) => Ok(dataset),
+            Err(Error::Status { status, .. }) if status == reqwest::StatusCode::NOT_FOUND => {
+                Err(Error::NotFound(dataset.name))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Creates a dataset through the admin endpoint, and fails if it already exists. The
+    /// request is retried only if the retry policy allows non-idempotent requests.
+    pub async fn create(self) -> Result<Dataset, Error> {
+        let creation = self.creation.clone();
+        let dataset = self.build()?;
+        let name = &dataset.name;
+        let created = |response: reqwest::Response| async move {
+            match response.status() {
+                reqwest::StatusCode::OK => Ok(()),
+                reqwest::StatusCode::CONFLICT => Err(Error::AlreadyExists(name.to_string())),
+                status => Err(Error::Status {
+                    status,
+                    message: response.text().await?,
+                }),
+            }
+        };
+        dataset
+            .send_create(&creation, Retry::NotIdempotent, created)
+            .await?;
+        Ok(dataset)
+    }
+}
+
+const UPLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+const JSON_LD: &str = "application/ld+json";
+
+/// The name of the default graph of a dataset in Fuseki.
+const DEFAULT_GRAPH: &str = "urn:x-arq:DefaultGraph";
+
+impl Upload {
+    /// Copies content that is held in memory, so that it can be sent again.
+    fn try_clone(&self) -> Option<Upload> {
+        match &self.content {
+            Content::Bytes(bytes) => Some(Upload {
+                format: self.format,
+                content: Content::Bytes(bytes.clone()),
+                gzip: self.gzip,
+                progress: self.progress.clone(),
+            }),
+            Content::Reader(_) => None,
         }
     }
+
+    fn multipart(self) -> reqwest::multipart::Part {
+        let (file_name, mime) = match self.format {
+            Format::Turtle => ("file.ttl", "text/turtle"),
+            Format::NTriples => ("file.nt", "application/n-triples"),
+            Format::RdfXml => ("file.xml", "text/xml"),
+            Format::JsonLd => ("file.jsonld", "application/ld+json"),
+        };
+        let part = match (self.content, self.gzip, self.progress) {
+            (Content::Bytes(content), false, None) => reqwest::multipart::Part::bytes(content),
+            (content, gzip, progress) => reqwest::multipart::Part::stream(
+                reqwest::Body::wrap_stream(upload_stream(content, gzip, progress)),
+            ),
+        };
+        let file_name = if self.gzip {
+            format!("{}.gz", file_name)
+        } else {
+            file_name.to_string()
+        };
+        part.file_name(file_name).mime_str(mime).unwrap()
+    }
+}
+
+/// Reads content in chunks, optionally compressing it, so that it is sent with chunked transfer
+/// encoding instead of being held in memory.
+fn upload_stream(
+    content: Content,
+    gzip: bool,
+    progress: Option<Arc<dyn Fn(u64) + Send + Sync>>,
+) -> impl Stream<Item = std::io::Result<Vec<u8>>> + Send + Sync + 'static {
+    let reader: Box<dyn AsyncRead + Send + Sync + Unpin> = match content {
+        Content::Bytes(bytes) => Box::new(std::io::Cursor::new(bytes)),
+        Content::Reader(reader) => reader,
+    };
+    let encoder = if gzip {
+        Some(GzEncoder::new(Vec::new(), flate2::Compression::default()))
+    } else {
+        None
+    };
+    futures::stream::unfold(Some((reader, encoder, 0)), move |state| {
+        let progress = progress.clone();
+        async move {
+            let (mut reader, mut encoder, mut total) = state?;
+            let mut buffer = vec![0; UPLOAD_CHUNK_SIZE];
+            loop {
+                let read = match reader.read(&mut buffer).await {
+                    Ok(read) => read,
+                    Err(e) => return Some((Err(e), None)),
+                };
+                if read == 0 {
+                    return encoder.map(|encoder| (encoder.finish(), None));
+                }
+                total += read as u64;
+                if let Some(progress) = &progress {
+                    progress(total);
+                }
+                let chunk = match &mut encoder {
+                    Some(encoder) => match encoder.write_all(&buffer[..read]) {
+                        Ok(()) => std::mem::take(encoder.get_mut()),
+                        Err(e) => return Some((Err(e), None)),
+                    },
+                    None => buffer[..read].to_vec(),
+                };
+                if !chunk.is_empty() {
+                    return Some((Ok(chunk), Some((reader, encoder, total))));
+                }
+            }
+        }
+    })
 }
 
-impl Dataset<'_> {
-    /// Returns a named dataset in the graph store.
-    pub async fn get_or_create<'a>(
-        client: &'a reqwest::Client,
+impl Dataset {
+    /// Returns a named dataset in a Fuseki server, which is first created with a type if the
+    /// dataset does not exist. The dataset keeps a handle to the client.
+    ///
+    /// This and the other shortcuts send no credentials. Use `DatasetBuilder` with `credentials`
+    /// for a protected server.
+    pub async fn get_or_create(
+        client: &reqwest::Client,
         base: url::Url,
         name: &str,
-    ) -> Dataset<'a> {
-        match client
-            .post(base.join("/$/datasets").unwrap())
-            .form(&[("dbName", name), ("dbType", &"mem".to_string())])
-            .send()
+        dataset_type: DatasetType,
+    ) -> Result<Dataset, Error> {
+        DatasetBuilder::new(name)
+            .client(client.clone())
+            .fuseki(&base)
+            .dataset_type(dataset_type)
+            .get_or_create()
             .await
-            .unwrap()
-            .status()
-        {
-            reqwest::StatusCode::CONFLICT | reqwest::StatusCode::OK => Dataset {
-                client,
-                base,
-                name: name.to_string(),
-            },
-            _ => panic!("Error creating dataset {}.", name),
+    }
+
+    /// Returns a named dataset in a Fuseki server, or `Error::NotFound` if it does not exist.
+    pub async fn open(
+        client: &reqwest::Client,
+        base: url::Url,
+        name: &str,
+    ) -> Result<Dataset, Error> {
+        DatasetBuilder::new(name)
+            .client(client.clone())
+            .fuseki(&base)
+            .open()
+            .await
+    }
+
+    /// Creates a named dataset with a type in a Fuseki server, or returns
+    /// `Error::AlreadyExists`.
+    pub async fn create(
+        client: &reqwest::Client,
+        base: url::Url,
+        name: &str,
+        dataset_type: DatasetType,
+    ) -> Result<Dataset, Error> {
+        DatasetBuilder::new(name)
+            .client(client.clone())
+            .fuseki(&base)
+            .dataset_type(dataset_type)
+            .create()
+            .await
+    }
+
+    /// Posts the name and type or the configuration of the dataset to the admin endpoint.
+    async fn send_create<T, M, F>(
+        &self,
+        creation: &Creation,
+        retry: Retry,
+        map: M,
+    ) -> Result<T, Error>
+    where
+        M: Fn(reqwest::Response) -> F,
+        F: Future<Output = Result<T, Error>>,
+    {
+        let admin = self.endpoints.admin.as_ref().ok_or_else(|| BuildError {
+            message: "No admin endpoint".to_string(),
+        })?;
+        let request = self.client.post(admin.clone());
+        let request = match creation {
+            Creation::Type(dataset_type) => request.form(&[
+                ("dbName", self.name.as_str()),
+                ("dbType", dataset_type.as_str()),
+            ]),
+            Creation::Assembler(assembler) => request
+                .header(reqwest::header::CONTENT_TYPE, "text/turtle")
+                .body(assembler.config(&self.name)?),
+        };
+        self.send(request, Access::Admin, retry, map).await
+    }
+
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    /// Replaces the prefixes that are declared in queries and added to graphs.
+    pub fn with_prefixes(mut self, prefixes: PrefixMap) -> Self {
+        self.prefixes = prefixes;
+        self
+    }
+
+    pub fn prefixes(&self) -> &PrefixMap {
+        &self.prefixes
+    }
+
+    /// Sets the base against which `resource` resolves relative references.
+    pub fn with_resource_base(mut self, base: Resource) -> Self {
+        self.resource_base = Some(base);
+        self
+    }
+
+    /// Creates a resource from a prefixed name with a known prefix, from a reference relative
+    /// to the resource base, or from an absolute IRI.
+    pub fn resource(&self, reference: &str) -> Result<Resource, IriError> {
+        if let Some(resource) = self.prefixes.expand(reference) {
+            return Ok(resource);
+        }
+        match &self.resource_base {
+            Some(base) => Resource::resolve(reference, base),
+            None => Resource::parse(reference),
         }
     }
 
-    /// Deletes a dataset. Moves the variable so that it cannot be used again.
-    pub async fn delete(self) {
-        let path = self
-            .base
-            .join("/$/datasets/")
-            .unwrap()
-            .join(&self.name)
-            .unwrap();
-        match self.client.delete(path).send().await.unwrap().status() {
-            reqwest::StatusCode::OK => (),
-            code => panic!("Unexpected status {}.", code),
+    /// Limits the time that queries may take, unless they have their own timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Retries requests after transient failures. By default, each request is sent once.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Adds an interceptor after those that the dataset already has.
+    pub fn with_interceptor<I: Interceptor + 'static>(mut self, interceptor: I) -> Self {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    /// Encodes a query with its dataset as protocol parameters.
+    fn query_form(&self, sparql_value: &str, dataset: &QueryDataset) -> Vec<(&str, String)> {
+        let mut form = vec![("query", self.prefixes.declare(sparql_value))];
+        for graph in &dataset.default_graphs {
+            let uri = match graph {
+                Graph::Default => DEFAULT_GRAPH.to_string(),
+                Graph::Named(Resource(id)) => id.to_string(),
+            };
+            form.push(("default-graph-uri", uri));
+        }
+        for Resource(id) in &dataset.named_graphs {
+            form.push(("named-graph-uri", id.to_string()));
+        }
+        form
+    }
+
+    /// Sends a request with the credentials for its endpoint, retrying it as the retry policy
+    /// allows. A rejected OAuth 2.0 access token is refreshed once. Requests for tokens bypass
+    /// the retry policy but not the interceptors, as described in `OAuth2`.
+    ///
+    /// Each response is mapped to a result, and an error with a retriable status is retried, so
+    /// that the body can tell errors with the same status apart. The error of the last attempt
+    /// gets the number of retries.
+    async fn send<T, M, F>(
+        &self,
+        request: reqwest::RequestBuilder,
+        access: Access,
+        retry: Retry,
+        map: M,
+    ) -> Result<T, Error>
+    where
+        M: Fn(reqwest::Response) -> F,
+        F: Future<Output = Result<T, Error>>,
+    {
+        self.send_attempts(
+            request,
+            reqwest::RequestBuilder::try_clone,
+            |r| r,
+            access,
+            retry,
+            map,
+        )
+        .await
+    }
+
+    /// Builds a request for each attempt from a copy of its source, such as a data file.
+    /// Sources that cannot be copied, such as streams, are sent once.
+    async fn send_attempts<S, C, B, T, M, F>(
+        &self,
+        mut source: S,
+        try_clone: C,
+        build: B,
+        access: Access,
+        retry: Retry,
+        map: M,
+    ) -> Result<T, Error>
+    where
+        C: Fn(&S) -> Option<S>,
+        B: Fn(S) -> reqwest::RequestBuilder,
+        M: Fn(reqwest::Response) -> F,
+        F: Future<Output = Result<T, Error>>,
+    {
+        let credentials = match access {
+            Access::Data => &self.credentials,
+            Access::Admin => &self.admin_credentials,
+        };
+        let mut retries = 0;
+        let mut refreshed = false;
+        loop {
+            let next = try_clone(&source);
+            let mut request = build(source);
+            if let Some(credentials) = credentials {
+                request = credentials
+                    .authorize(&self.client, &self.interceptors, request)
+                    .await?;
+            }
+            let result = self.execute(request).await;
+            source = match (credentials, next) {
+                (Some(Credentials::OAuth2(provider)), Some(next))
+                    if !refreshed && is_unauthorized(&result) =>
+                {
+                    provider.invalidate().await;
+                    refreshed = true;
+                    next
+                }
+                (_, next) => {
+                    let result = match result {
+                        Ok(response) => map(response).await,
+                        Err(error) => Err(error),
+                    };
+                    match next {
+                        Some(next) if retry.allows(&self.retry_policy, retries, &result) => {
+                            tokio::time::delay_for(self.retry_policy.backoff(retries)).await;
+                            retries += 1;
+                            next
+                        }
+                        _ => return with_retries(result, retries),
+                    }
+                }
+            };
+        }
+    }
+
+    /// Sends a single attempt through the interceptors.
+    async fn execute(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, Error> {
+        interceptor::execute(
+            &self.client,
+            &self.interceptors,
+            request,
+            RequestKind::Endpoint,
+        )
+        .await
+    }
+
+    /// Sends a query with a timeout, both as a deadline for the response and as the timeout
+    /// parameter that tells Fuseki to abort the query. Dropping the future aborts the request.
+    async fn send_query(
+        &self,
+        mut form: Vec<(&str, String)>,
+        accept: &str,
+        timeout: Option<Duration>,
+    ) -> Result<reqwest::Response, Error> {
+        let timeout = timeout.or(self.timeout);
+        let mut request = self
+            .client
+            .post(self.endpoints.query.clone())
+            .header(reqwest::header::ACCEPT, accept);
+        if let Some(timeout) = timeout {
+            form.push(("timeout", timeout.as_secs_f64().to_string()));
+            request = request.timeout(timeout);
+        }
+        let answered = |response: reqwest::Response| async move {
+            let status = response.status();
+            if status == reqwest::StatusCode::OK {
+                return Ok(response);
+            }
+            let message = response.text().await?;
+            if status == reqwest::StatusCode::SERVICE_UNAVAILABLE
+                && timeout.is_some()
+                && is_cancelled(&message)
+            {
+                Err(Error::Timeout)
+            } else {
+                Err(Error::Status { status, message })
+            }
+        };
+        self.send(
+            request.form(&form),
+            Access::Data,
+            Retry::Idempotent,
+            answered,
+        )
+        .await
+    }
+
+    /// Performs a query that results in a graph.
+    async fn query_graph(
+        &self,
+        sparql_value: &str,
+        dataset: &QueryDataset,
+        timeout: Option<Duration>,
+    ) -> Result<rdf::graph::Graph, Error> {
+        let form = self.query_form(sparql_value, dataset);
+        let response = self.send_query(form, "text/turtle", timeout).await?;
+        let mut graph = parse_turtle(response.text().await?)?;
+        self.prefixes.add_to(&mut graph);
+        record_triples(&graph);
+        Ok(graph)
+    }
+
+    /// Performs a query that results in a graph, as a JSON-LD document.
+    async fn query_json_ld(
+        &self,
+        sparql_value: &str,
+        dataset: &QueryDataset,
+        timeout: Option<Duration>,
+        context: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, Error> {
+        let form = self.query_form(sparql_value, dataset);
+        let response = self.send_query(form, JSON_LD, timeout).await?;
+        json_ld::from_document(read_json(response).await?, context)
+            .map_err(|e| Error::Response(e.to_string()))
+    }
+
+    /// Retrieves the content of a graph in a format.
+    async fn get_content(&self, graph: &Graph, accept: &str) -> Result<String, Error> {
+        let path = self.endpoints.graph_store.clone();
+        let query = match graph {
+            Graph::Named(Resource(id)) => vec![("graph", id.as_str())],
+            Graph::Default => vec![("default", "")],
+        };
+        let request = self
+            .client
+            .get(path)
+            .query(&query)
+            .header(reqwest::header::ACCEPT, accept);
+        let read = |response: reqwest::Response| async move {
+            let status = response.status();
+            let body = response.text().await?;
+            if status != reqwest::StatusCode::OK {
+                return Err(Error::Status {
+                    status,
+                    message: body,
+                });
+            }
+            Ok(body)
+        };
+        let body = self
+            .send(request, Access::Data, Retry::Idempotent, read)
+            .await?;
+        tracing::Span::current().record("bytes", body.len() as u64);
+        Ok(body)
+    }
+
+    /// Writes the default graph and all named graphs, streaming them from the server.
+    pub async fn dump<W: AsyncWrite + Unpin>(
+        &self,
+        format: QuadFormat,
+        writer: &mut W,
+    ) -> Result<(), Error> {
+        let path = self.endpoints.graph_store.clone();
+        let request = self
+            .client
+            .get(path)
+            .header(reqwest::header::ACCEPT, format.mime());
+        let mut response = self
+            .send(request, Access::Data, Retry::Idempotent, expect_ok)
+            .await?;
+        while let Some(chunk) = response.chunk().await? {
+            writer.write_all(&chunk).await?;
+        }
+        writer.flush().await?;
+        Ok(())
+    }
+
+    /// Writes the dataset to a file in the format indicated by its extension.
+    pub async fn dump_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let format = quad_format(path.as_ref())?;
+        let mut file = tokio::fs::File::create(path).await?;
+        self.dump(format, &mut file).await
+    }
+
+    /// Recreates a dump in this dataset, which must be empty, or returns `Error::NotEmpty`.
+    ///
+    /// The dataset is checked with a query before the dump is uploaded, which replaces the
+    /// content of the dataset. Data that another client writes in between is therefore lost.
+    pub async fn restore<R>(&self, format: QuadFormat, reader: R) -> Result<(), Error>
+    where
+        R: AsyncRead + Send + Sync + Unpin + 'static,
+    {
+        let existing = self
+            .select(Selection::unsafe_from(
+                "SELECT * WHERE { { ?s ?p ?o } UNION { GRAPH ?g { ?s ?p ?o } } } LIMIT 1",
+            ))
+            .await?;
+        if !existing.bindings.is_empty() {
+            return Err(Error::NotEmpty(self.name.to_string()));
+        }
+        let path = self.endpoints.graph_store.clone();
+        let body = reqwest::Body::wrap_stream(upload_stream(
+            Content::Reader(Box::new(reader)),
+            false,
+            None,
+        ));
+        let request = self
+            .client
+            .put(path)
+            .header(reqwest::header::CONTENT_TYPE, format.mime())
+            .body(body);
+        self.send(request, Access::Data, Retry::Idempotent, expect_stored)
+            .await
+    }
+
+    /// Recreates a dump from a file in the format indicated by its extension.
+    pub async fn restore_from_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let format = quad_format(path.as_ref())?;
+        let file = tokio::fs::File::open(path).await?;
+        self.restore(format, file).await
+    }
+
+    /// Deletes a dataset through the admin endpoint. Moves the variable so that it cannot be
+    /// used again.
+    pub async fn delete(self) -> Result<(), Error> {
+        let path = match &self.endpoints.admin {
+            Some(admin) => with_segments(admin, &[&self.name]),
+            None => {
+                return Err(Error::Build(BuildError {
+                    message: format!("No admin endpoint to delete dataset {}", self.name),
+                }))
+            }
+        };
+        let request = self.client.delete(path);
+        let name = &self.name;
+        let deleted = |response: reqwest::Response| async move {
+            match response.status() {
+                reqwest::StatusCode::OK => Ok(()),
+                reqwest::StatusCode::NOT_FOUND => Err(Error::NotFound(name.to_string())),
+                status => Err(Error::Status {
+                    status,
+                    message: response.text().await?,
+                }),
+            }
+        };
+        self.send(request, Access::Admin, Retry::Idempotent, deleted)
+            .await
+    }
+
+    /// Performs a SPARQL update, which is retried only if the retry policy allows it.
+    pub async fn update(&self, update: &str) -> Result<(), Error> {
+        observe(Kind::Update, &self.name, Some(update), None, async {
+            let request = self
+                .client
+                .post(self.endpoints.update.clone())
+                .form(&[("update", self.prefixes.declare(update))]);
+            let updated = |response: reqwest::Response| async move {
+                match response.status() {
+                    reqwest::StatusCode::OK | reqwest::StatusCode::NO_CONTENT => Ok(()),
+                    status => Err(Error::Status {
+                        status,
+                        message: response.text().await?,
+                    }),
+                }
+            };
+            self.send(request, Access::Data, Retry::NotIdempotent, updated)
+                .await
+        })
+        .await
+    }
+
+    /// Adds the triples of a data file to a graph, where `import` replaces the graph. Retried
+    /// only if the retry policy allows it.
+    pub async fn merge(&self, graph: Graph, file: DataFile) -> Result<(), Error> {
+        observe(Kind::Merge, &self.name, None, Some(&graph), async {
+            self.upload(reqwest::Method::POST, &graph, file, Retry::NotIdempotent)
+                .await
+        })
+        .await
+    }
+
+    async fn upload(
+        &self,
+        method: reqwest::Method,
+        graph: &Graph,
+        file: DataFile,
+        retry: Retry,
+    ) -> Result<(), Error> {
+        let path = self.endpoints.graph_store.clone();
+        let query = match graph {
+            Graph::Named(Resource(id)) => vec![("graph", id.as_str())],
+            Graph::Default => vec![],
+        };
+        let file = file.into_upload();
+        if let Content::Bytes(bytes) = &file.content {
+            tracing::Span::current().record("bytes", bytes.len() as u64);
         }
+        let build = |file: Upload| {
+            let form = reqwest::multipart::Form::new().part("files[]", file.multipart());
+            self.client
+                .request(method.clone(), path.clone())
+                .query(&query)
+                .multipart(form)
+        };
+        self.send_attempts(
+            file,
+            Upload::try_clone,
+            build,
+            Access::Data,
+            retry,
+            expect_stored,
+        )
+        .await
+    }
+}
+
+/// Whether the body of Service Unavailable is the message with which Fuseki reports a query
+/// that it cancelled after its timeout: `Query timed out`, or `The query timed out (restricted
+/// to ... ms)` before Fuseki 4, optionally after the `Error 503: ` of its error handler. Other
+/// reasons, such as an overloaded server or a proxy error page, are not timeouts.
+fn is_cancelled(message: &str) -> bool {
+    let message = message.trim_start().to_lowercase();
+    let message = message.strip_prefix("error 503: ").unwrap_or(&message);
+    message.starts_with("query timed out") || message.starts_with("the query timed out")
+}
+
+/// Accepts a response with status OK.
+async fn expect_ok(response: reqwest::Response) -> Result<reqwest::Response, Error> {
+    match response.status() {
+        reqwest::StatusCode::OK => Ok(response),
+        status => Err(Error::Status {
+            status,
+            message: response.text().await?,
+        }),
+    }
+}
+
+/// Accepts a response to stored data, whatever its status of success.
+async fn expect_stored(response: reqwest::Response) -> Result<(), Error> {
+    let status = response.status();
+    let body = response.text().await?;
+    match status {
+        reqwest::StatusCode::CREATED
+        | reqwest::StatusCode::OK
+        | reqwest::StatusCode::NO_CONTENT => Ok(()),
+        status => Err(Error::Status {
+            status,
+            message: body,
+        }),
     }
 }
 
+/// Reports the retries with the error of the last attempt.
+fn with_retries<T>(result: Result<T, Error>, retries: u32) -> Result<T, Error> {
+    match result {
+        Err(error) if retries > 0 => Err(Error::Retried {
+            retries,
+            error: Box::new(error),
+        }),
+        result => result,
+    }
+}
+
+fn is_unauthorized(result: &Result<reqwest::Response, Error>) -> bool {
+    matches!(result, Ok(response) if response.status() == reqwest::StatusCode::UNAUTHORIZED)
+}
+
+fn quad_format(path: &Path) -> std::io::Result<QuadFormat> {
+    QuadFormat::from_path(path).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Unknown dump file extension: {}", path.display()),
+        )
+    })
+}
+
 #[derive(Deserialize, Debug)]
 struct QueryResponseHead {
     vars: Vec<String>,
@@ -78,9 +1047,19 @@ struct QueryResponseHead {
 #[serde(tag = "type")]
 #[allow(non_camel_case_types)]
 enum QueryResponseValue {
-    uri { value: String },
-    literal { value: String },
-    bnode { value: String },
+    uri {
+        value: String,
+    },
+    #[serde(alias = "typed-literal")]
+    literal {
+        value: String,
+        datatype: Option<String>,
+        #[serde(rename = "xml:lang")]
+        language: Option<String>,
+    },
+    bnode {
+        value: String,
+    },
 }
 
 impl QueryResponseValue {
@@ -89,10 +1068,16 @@ impl QueryResponseValue {
             QueryResponseValue::uri { value } => rdf::node::Node::UriNode {
                 uri: rdf::uri::Uri::new(value.to_string()),
             },
-            QueryResponseValue::literal { value } => rdf::node::Node::LiteralNode {
+            QueryResponseValue::literal {
+                value,
+                datatype,
+                language,
+            } => rdf::node::Node::LiteralNode {
                 literal: value.to_string(),
-                data_type: None,
-                language: None,
+                data_type: datatype
+                    .as_ref()
+                    .map(|datatype| rdf::uri::Uri::new(datatype.to_string())),
+                language: language.clone(),
             },
             QueryResponseValue::bnode { value } => rdf::node::Node::BlankNode {
                 id: value.to_string(),
@@ -112,90 +1097,389 @@ struct QueryResponse {
     results: QueryResponseResults,
 }
 
+#[derive(Deserialize, Debug)]
+struct AskResponse {
+    boolean: bool,
+}
+
 #[async_trait]
-impl GraphStore for Dataset<'_> {
-    async fn import(&self, graph: Graph, file: DataFile) {
-        let form = reqwest::multipart::Form::new().part("files[]", file.multipart());
-        let path = self.base.join(&format!("/{}/data", &self.name)).unwrap();
-        let query = match graph {
-            Graph::Named(Resource(id)) => vec![("graph", id)],
-            Graph::Default => Vec::<(&str, String)>::new(),
-        };
-        let response = self
-            .client
-            .put(path)
-            .query(&query)
-            .multipart(form)
-            .send()
-            .await
-            .unwrap();
-        let status = response.status();
-        let body = response.text().await.unwrap();
-        match status {
-            reqwest::StatusCode::CREATED | reqwest::StatusCode::OK => (),
-            code => panic!("Unexpected status {} with message {}.", code, body),
-        };
+impl GraphStore for Dataset {
+    async fn import(&self, graph: Graph, file: DataFile) -> Result<(), Error> {
+        observe(Kind::Import, &self.name, None, Some(&graph), async {
+            self.upload(reqwest::Method::PUT, &graph, file, Retry::Idempotent)
+                .await
+        })
+        .await
     }
 
-    async fn select(&self, selection: Selection) -> Table<Node> {
-        let form = [("query", selection.sparql_value)];
-        let path = self.base.join(&self.name).unwrap();
-        let response = self.client.post(path).form(&form).send().await.unwrap();
-        let status = response.status();
-        let response: QueryResponse = response
-            .json::<QueryResponse>()
-            .await
-            .expect("Unexpected response");
-        match status {
-            reqwest::StatusCode::OK => {
-                Table::from(response.head.vars, response.results.bindings, |b| {
-                    b.to_node()
-                })
-            }
-            code => panic!("Unexpected status {}.", code),
-        }
+    async fn select(&self, selection: Selection) -> Result<Table<Node>, Error> {
+        let query = &selection.sparql_value;
+        observe(Kind::Select, &self.name, Some(query), None, async {
+            let form = self.query_form(query, &selection.dataset);
+            let response = self
+                .send_query(form, "application/sparql-results+json", selection.timeout)
+                .await?;
+            let response: QueryResponse = read_json(response).await?;
+            let span = tracing::Span::current();
+            span.record("rows", response.results.bindings.len() as u64);
+            Ok(Table::from(
+                response.head.vars,
+                response.results.bindings,
+                |b| b.to_node(),
+            ))
+        })
+        .await
     }
 
-    async fn describe(&self, query: DescribeQuery) -> rdf::graph::Graph {
-        let form = [("query", query.sparql_value)];
-        let path = self.base.join(&self.name).unwrap();
-        let response = self.client.post(path).form(&form).send().await.unwrap();
-        let status = response.status();
-        let response: String = response.text().await.unwrap();
-        match status {
-            reqwest::StatusCode::OK => {
-                let mut reader = TurtleParser::from_string(response);
-
-                match reader.decode() {
-                    Ok(graph) => graph,
-                    Err(e) => panic!("Could not parse: {:?}", e),
-                }
-            }
-            code => panic!("Unexpected status {}.", code),
-        }
+    async fn ask(&self, query: AskQuery) -> Result<bool, Error> {
+        let sparql_value = &query.sparql_value;
+        observe(Kind::Ask, &self.name, Some(sparql_value), None, async {
+            let form = self.query_form(sparql_value, &query.dataset);
+            let response = self
+                .send_query(form, "application/sparql-results+json", query.timeout)
+                .await?;
+            let response: AskResponse = read_json(response).await?;
+            Ok(response.boolean)
+        })
+        .await
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use crate::http::Dataset;
-    use mockito::{mock, Mock};
 
-    fn create_endpoint_mock() -> Mock {
-        mock("POST", "/$/datasets").with_status(200).create()
+    async fn describe(&self, query: DescribeQuery) -> Result<rdf::graph::Graph, Error> {
+        let sparql_value = &query.sparql_value;
+        observe(
+            Kind::Describe,
+            &self.name,
+            Some(sparql_value),
+            None,
+            async {
+                self.query_graph(sparql_value, &query.dataset, query.timeout)
+                    .await
+            },
+        )
+        .await
     }
 
-    #[tokio::test]
-    async fn correct_create_or_get_request() {
-        let client = reqwest::Client::new();
-        let base = url::Url::parse(&mockito::server_url()).unwrap();
-        let create_mock = create_endpoint_mock();
-        let name = "test";
+    async fn construct(&self, query: ConstructQuery) -> Result<rdf::graph::Graph, Error> {
+        let sparql_value = &query.sparql_value;
+        observe(
+            Kind::Construct,
+            &self.name,
+            Some(sparql_value),
+            None,
+            async {
+                self.query_graph(sparql_value, &query.dataset, query.timeout)
+                    .await
+            },
+        )
+        .await
+    }
 
-        let dataset = Dataset::get_or_create(&client, base, &name).await;
+    async fn get(&self, graph: Graph) -> Result<rdf::graph::Graph, Error> {
+        observe(Kind::Get, &self.name, None, Some(&graph), async {
+            let body = self.get_content(&graph, "text/turtle").await?;
+            let mut graph = parse_turtle(body)?;
+            self.prefixes.add_to(&mut graph);
+            record_triples(&graph);
+            Ok(graph)
+        })
+        .await
+    }
 
-        create_mock.assert();
-        assert_eq!(dataset.name, name);
+    /// Requests JSON-LD from the server, which is returned as it is without a context and
+    /// otherwise compacted as described in `json_ld`.
+    async fn describe_json_ld(
+        &self,
+        query: DescribeQuery,
+        context: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, Error> {
+        let sparql_value = &query.sparql_value;
+        observe(
+            Kind::Describe,
+            &self.name,
+            Some(sparql_value),
+            None,
+            async {
+                self.query_json_ld(sparql_value, &query.dataset, query.timeout, context)
+                    .await
+            },
+        )
+        .await
+    }
+
+    /// Requests JSON-LD from the server, which is returned as it is without a context and
+    /// otherwise compacted as described in `json_ld`.
+    async fn construct_json_ld(
+        &self,
+        query: ConstructQuery,
+        context: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, Error> {
+        let sparql_value = &query.sparql_value;
+        observe(
+            Kind::Construct,
+            &self.name,
+            Some(sparql_value),
+            None,
+            async {
+                self.query_json_ld(sparql_value, &query.dataset, query.timeout, context)
+                    .await
+            },
+        )
+        .await
+    }
+
+    /// Requests JSON-LD from the server, which is returned as it is without a context and
+    /// otherwise compacted as described in `json_ld`.
+    async fn get_json_ld(
+        &self,
+        graph: Graph,
+        context: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, Error> {
+        observe(Kind::Get, &self.name, None, Some(&graph), async {
+            let body = self.get_content(&graph, JSON_LD).await?;
+            let document =
+                serde_json::from_str(&body).map_err(|e| Error::Response(e.to_string()))?;
+            json_ld::from_document(document, context).map_err(|e| Error::Response(e.to_string()))
+        })
+        .await
+    }
+}
+
+/// Reads a JSON response, recording its size in the current span.
+async fn read_json<T: serde::de::DeserializeOwned>(
+    response: reqwest::Response,
+) -> Result<T, Error> {
+    let body = response.bytes().await?;
+    tracing::Span::current().record("bytes", body.len() as u64);
+    serde_json::from_slice(&body).map_err(|e| Error::Response(e.to_string()))
+}
+
+/// Records the number of triples of a graph as the rows of the current span.
+fn record_triples(graph: &rdf::graph::Graph) {
+    tracing::Span::current().record("rows", graph.count() as u64);
+}
+
+fn parse_turtle(turtle: String) -> Result<rdf::graph::Graph, Error> {
+    TurtleParser::from_string(turtle)
+        .decode()
+        .map_err(|e| Error::Response(format!("Could not parse: {:?}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{Credentials, OAuth2};
+    use crate::error::Error;
+    use crate::http::{
+        is_cancelled, upload_stream, Dataset, DatasetBuilder, DatasetType, Endpoints,
+    };
+    use crate::interceptor::{Interceptor, RequestKind, SetHeader};
+    use crate::prefix::PrefixMap;
+    use crate::retry::RetryPolicy;
+    use crate::table::Variable;
+    use crate::{AskQuery, Content, DataFile, Graph, GraphStore, QuadFormat, Resource, Selection};
+    use futures::StreamExt;
+    use mockito::{mock, Matcher, Mock};
+    use reqwest::header::{HeaderValue, USER_AGENT};
+    use serde_json::json;
+    use std::io::Read;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    fn create_endpoint_mock() -> Mock {
+        mock("POST", "/$/datasets").with_status(200).create()
+    }
+
+    #[tokio::test]
+    async fn correct_create_or_get_request() {
+        let client = reqwest::Client::new();
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let create_mock = create_endpoint_mock();
+        let name = "test";
+
+        let dataset = Dataset::get_or_create(&client, base, name, DatasetType::Mem)
+            .await
+            .unwrap();
+
+        create_mock.assert();
+        assert_eq!(dataset.name, name);
+    }
+
+    #[tokio::test]
+    async fn requests_below_context_path() {
+        let client = reqwest::Client::new();
+        let base = url::Url::parse(&format!("{}/fuseki/", mockito::server_url())).unwrap();
+        let name = "my data/set";
+        let create_mock = mock("POST", "/fuseki/$/datasets")
+            .match_body(Matcher::UrlEncoded("dbName".to_string(), name.to_string()))
+            .with_status(200)
+            .create();
+        let ask_mock = mock("POST", "/fuseki/my%20data%2Fset")
+            .with_status(200)
+            .with_body(r#"{ "head": {}, "boolean": false }"#)
+            .create();
+        let import_mock = mock("PUT", "/fuseki/my%20data%2Fset/data")
+            .with_status(200)
+            .create();
+        let delete_mock = mock("DELETE", "/fuseki/$/datasets/my%20data%2Fset")
+            .with_status(200)
+            .create();
+
+        let dataset = Dataset::get_or_create(&client, base, name, DatasetType::Mem)
+            .await
+            .unwrap();
+        let result = dataset.ask(AskQuery::unsafe_from("ASK {}")).await.unwrap();
+        dataset
+            .import(
+                Graph::Default,
+                DataFile::unsafe_from_turtle("<http://example.org/a> <http://example.org/b> 1 ."),
+            )
+            .await
+            .unwrap();
+        dataset.delete().await.unwrap();
+
+        create_mock.assert();
+        ask_mock.assert();
+        import_mock.assert();
+        delete_mock.assert();
+        assert!(!result);
+    }
+
+    #[tokio::test]
+    async fn opens_existing_datasets() {
+        let client = reqwest::Client::new();
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let create_mock = mock("POST", "/$/datasets")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("dbName".to_string(), "new".to_string()),
+                Matcher::UrlEncoded("dbType".to_string(), "tdb2".to_string()),
+            ]))
+            .with_status(200)
+            .create();
+        let conflict_mock = mock("POST", "/$/datasets")
+            .match_body(Matcher::UrlEncoded(
+                "dbName".to_string(),
+                "existing".to_string(),
+            ))
+            .with_status(409)
+            .create();
+        let existing_mock = mock("POST", "/existing")
+            .with_status(200)
+            .with_body(r#"{ "head": {}, "boolean": false }"#)
+            .create();
+        let missing_mock = mock("POST", "/typo").with_status(404).create();
+
+        let created = Dataset::create(&client, base.clone(), "new", DatasetType::Tdb2).await;
+        let conflict = Dataset::create(&client, base.clone(), "existing", DatasetType::Mem).await;
+        let existing = Dataset::open(&client, base.clone(), "existing").await;
+        let missing = Dataset::open(&client, base.clone(), "typo").await;
+
+        create_mock.assert();
+        conflict_mock.assert();
+        existing_mock.assert();
+        missing_mock.assert();
+        assert_eq!(created.unwrap().name, "new");
+        assert!(matches!(conflict, Err(Error::AlreadyExists(name)) if name == "existing"));
+        assert_eq!(existing.unwrap().name, "existing");
+        assert!(matches!(missing, Err(Error::NotFound(name)) if name == "typo"));
+        assert!(matches!(
+            DatasetBuilder::new("test")
+                .query_endpoint(base.join("/sparql").unwrap())
+                .update_endpoint(base.join("/update").unwrap())
+                .graph_store_endpoint(base.join("/data").unwrap())
+                .create()
+                .await,
+            Err(Error::Build(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn creates_persistent_datasets() {
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let tdb2_mock = mock("POST", "/$/datasets")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("dbName".to_string(), "persistent".to_string()),
+                Matcher::UrlEncoded("dbType".to_string(), "tdb2".to_string()),
+            ]))
+            .with_status(200)
+            .create();
+        let config = r#"
+            @prefix fuseki: <http://jena.apache.org/fuseki#> .
+            @prefix tdb2: <http://jena.apache.org/2016/tdb#> .
+
+            <#service> a fuseki:Service ;
+                fuseki:name "indexed" ;
+                fuseki:endpoint [ fuseki:operation fuseki:query ] ;
+                fuseki:dataset <#dataset> .
+
+            <#dataset> a tdb2:DatasetTDB2 ;
+                tdb2:location "/fuseki/databases/indexed" .
+        "#;
+        let assembler_mock = mock("POST", "/$/datasets")
+            .match_header("content-type", "text/turtle")
+            .match_body(config)
+            .with_status(200)
+            .expect(2)
+            .create();
+
+        DatasetBuilder::new("persistent")
+            .fuseki(&base)
+            .dataset_type(DatasetType::Tdb2)
+            .get_or_create()
+            .await
+            .unwrap();
+        DatasetBuilder::new("indexed")
+            .fuseki(&base)
+            .assembler(config)
+            .create()
+            .await
+            .unwrap();
+        let path = std::env::temp_dir().join(format!("{}.ttl", uuid::Uuid::new_v4()));
+        std::fs::write(&path, config).unwrap();
+        let from_file = DatasetBuilder::new("indexed")
+            .fuseki(&base)
+            .assembler_file(&path)
+            .get_or_create()
+            .await;
+        std::fs::remove_file(&path).unwrap();
+        let misnamed = DatasetBuilder::new("other")
+            .fuseki(&base)
+            .assembler(config)
+            .create()
+            .await;
+        let invalid = DatasetBuilder::new("indexed")
+            .fuseki(&base)
+            .assembler("<#service> fuseki:name \"indexed\" .")
+            .create()
+            .await;
+        let missing = DatasetBuilder::new("indexed")
+            .fuseki(&base)
+            .assembler_file(&path)
+            .create()
+            .await;
+
+        from_file.unwrap();
+        tdb2_mock.assert();
+        assembler_mock.assert();
+        assert!(matches!(misnamed, Err(Error::Build(_))));
+        assert!(matches!(invalid, Err(Error::Build(_))));
+        assert!(matches!(missing, Err(Error::Build(_))));
+    }
+
+    #[test]
+    fn keeps_context_path_without_trailing_slash() {
+        let base = url::Url::parse("https://example.org/fuseki").unwrap();
+
+        let endpoints = Endpoints::fuseki(&base, "ds");
+
+        assert_eq!(endpoints.query.as_str(), "https://example.org/fuseki/ds");
+        assert_eq!(
+            endpoints.graph_store.as_str(),
+            "https://example.org/fuseki/ds/data"
+        );
+        assert_eq!(
+            endpoints.admin.unwrap().as_str(),
+            "https://example.org/fuseki/$/datasets"
+        );
     }
 
     #[tokio::test]
@@ -207,10 +1491,768 @@ mod tests {
         let delete_mock = mock("DELETE", format!("/$/datasets/{}", name).as_ref())
             .with_status(200)
             .create();
-        let dataset = Dataset::get_or_create(&client, base, &name).await;
+        let dataset = Dataset::get_or_create(&client, base, name, DatasetType::Mem)
+            .await
+            .unwrap();
 
-        dataset.delete().await;
+        dataset.delete().await.unwrap();
 
         delete_mock.assert();
     }
+
+    #[tokio::test]
+    async fn builds_datasets_with_other_endpoints() {
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let ask_mock = mock("POST", "/gateway/sparql")
+            .with_status(200)
+            .with_body(r#"{ "head": {}, "boolean": true }"#)
+            .create();
+        let get_mock = mock("GET", "/gateway/rdf-graph-store?default=")
+            .with_status(200)
+            .with_body("<http://example.org/a> <http://example.org/b> \"c\" .")
+            .create();
+
+        let dataset = DatasetBuilder::new("test")
+            .query_endpoint(base.join("/gateway/sparql").unwrap())
+            .update_endpoint(base.join("/gateway/update").unwrap())
+            .graph_store_endpoint(base.join("/gateway/rdf-graph-store").unwrap())
+            .get_or_create()
+            .await
+            .unwrap();
+
+        assert!(dataset
+            .ask(AskQuery::unsafe_from("ASK { ?s ?p ?o }"))
+            .await
+            .unwrap());
+        assert_eq!(dataset.get(Graph::Default).await.unwrap().count(), 1);
+        ask_mock.assert();
+        get_mock.assert();
+        assert_eq!(
+            DatasetBuilder::new("test")
+                .fuseki(&base)
+                .query_endpoint(base.join("/sparql").unwrap())
+                .build()
+                .unwrap()
+                .endpoints()
+                .graph_store,
+            base.join("/test/data").unwrap()
+        );
+        assert!(DatasetBuilder::new("test").build().is_err());
+        assert!(matches!(dataset.delete().await, Err(Error::Build(_))));
+    }
+
+    #[tokio::test]
+    async fn reports_failures_to_create_datasets() {
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let create_mock = mock("POST", "/$/datasets")
+            .with_status(403)
+            .with_body("Forbidden")
+            .create();
+
+        let result = DatasetBuilder::new("forbidden")
+            .fuseki(&base)
+            .get_or_create()
+            .await;
+
+        create_mock.assert();
+        assert!(matches!(
+            result,
+            Err(Error::Status { status, .. }) if status == reqwest::StatusCode::FORBIDDEN
+        ));
+    }
+
+    #[tokio::test]
+    async fn sends_credentials() {
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "protected";
+        let create_mock = mock("POST", "/$/datasets")
+            .match_header("authorization", "Basic YWRtaW46c2VjcmV0")
+            .with_status(200)
+            .create();
+        let token_mock = mock("POST", "/token")
+            .with_status(200)
+            .with_body(r#"{ "access_token": "abc", "expires_in": 3600 }"#)
+            .create();
+        let ask_mock = mock("POST", format!("/{}", name).as_ref())
+            .match_header("authorization", "Bearer abc")
+            .with_status(200)
+            .with_body(r#"{ "head": {}, "boolean": true }"#)
+            .expect(2)
+            .create();
+        let provider = OAuth2::client_credentials(base.join("/token").unwrap(), "client", "secret");
+
+        let dataset = DatasetBuilder::new(name)
+            .fuseki(&base)
+            .credentials(Credentials::OAuth2(provider))
+            .admin_credentials(Credentials::basic("admin", "secret"))
+            .get_or_create()
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            assert!(dataset.ask(AskQuery::unsafe_from("ASK {}")).await.unwrap());
+        }
+
+        create_mock.assert();
+        token_mock.assert();
+        ask_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn retries_idempotent_requests() {
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "unavailable";
+        let _create_mock = create_endpoint_mock();
+        let select_mock = mock("POST", format!("/{}", name).as_ref())
+            .with_status(503)
+            .expect(3)
+            .create();
+        let import_mock = mock("PUT", format!("/{}/data", name).as_ref())
+            .with_status(502)
+            .expect(3)
+            .create();
+        let update_mock = mock("POST", format!("/{}/update", name).as_ref())
+            .with_status(503)
+            .expect(1)
+            .create();
+        let policy = RetryPolicy::default()
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(10));
+        let dataset = DatasetBuilder::new(name)
+            .fuseki(&base)
+            .retry_policy(policy)
+            .get_or_create()
+            .await
+            .unwrap();
+
+        let selected = dataset.select(Selection::of_triples()).await;
+        let imported = dataset
+            .import(
+                Graph::Default,
+                DataFile::unsafe_from_turtle("<http://example.org/a> <http://example.org/b> 1 ."),
+            )
+            .await;
+        let updated = dataset.update("CLEAR DEFAULT").await;
+
+        select_mock.assert();
+        import_mock.assert();
+        update_mock.assert();
+        match selected {
+            Err(Error::Retried { retries, error }) => {
+                assert_eq!(retries, 2);
+                assert!(matches!(*error, Error::Status { status, .. } if status == 503));
+            }
+            _ => panic!("Expected retries"),
+        }
+        let imported = imported.unwrap_err();
+        assert_eq!(imported.retries(), 2);
+        assert_eq!(imported.kind(), "status");
+        assert!(matches!(updated, Err(Error::Status { status, .. }) if status == 503));
+    }
+
+    #[tokio::test]
+    async fn retries_timed_queries_unless_cancelled() {
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let _create_mock = create_endpoint_mock();
+        let unavailable_mock = mock("POST", "/busy")
+            .with_status(503)
+            .with_body("Service Unavailable")
+            .expect(3)
+            .create();
+        let cancelled_mock = mock("POST", "/slow")
+            .with_status(503)
+            .with_body("Query timed out")
+            .expect(1)
+            .create();
+        let dataset = |name| {
+            DatasetBuilder::new(name)
+                .fuseki(&base)
+                .retry_policy(
+                    RetryPolicy::default()
+                        .with_backoff(Duration::from_millis(1), Duration::from_millis(10)),
+                )
+                .get_or_create()
+        };
+        let busy = dataset("busy").await.unwrap();
+        let slow = dataset("slow").await.unwrap();
+
+        let timeout = Duration::from_secs(10);
+        let unavailable = busy
+            .with_timeout(timeout)
+            .select(Selection::of_triples())
+            .await;
+        let cancelled = slow
+            .with_timeout(timeout)
+            .select(Selection::of_triples())
+            .await;
+
+        unavailable_mock.assert();
+        cancelled_mock.assert();
+        let unavailable = unavailable.unwrap_err();
+        assert_eq!(unavailable.retries(), 2);
+        assert_eq!(unavailable.kind(), "status");
+        assert!(matches!(cancelled, Err(Error::Timeout)));
+    }
+
+    struct Audit {
+        name: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Interceptor for Audit {
+        fn before(&self, request: &mut reqwest::Request, kind: RequestKind) {
+            let header = HeaderValue::from_static(self.name);
+            request.headers_mut().append("x-audit", header);
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("before {} {:?}", self.name, kind));
+        }
+
+        fn after(
+            &self,
+            request: &reqwest::Request,
+            _kind: RequestKind,
+            result: &Result<reqwest::Response, Error>,
+            _elapsed: Duration,
+        ) {
+            let status = result.as_ref().unwrap().status();
+            let headers = request.headers().get_all("x-audit").iter().count();
+            self.log.lock().unwrap().push(format!(
+                "after {} {} {} {}",
+                self.name,
+                request.method(),
+                status,
+                headers
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_interceptors_around_requests() {
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "intercepted";
+        let _create_mock = create_endpoint_mock();
+        let ask_mock = mock("POST", format!("/{}", name).as_ref())
+            .match_header("user-agent", "graph-store-test")
+            .match_header("x-audit", Matcher::Any)
+            .with_status(200)
+            .with_body(r#"{ "head": {}, "boolean": true }"#)
+            .create();
+        let log = Arc::new(Mutex::new(vec![]));
+        let dataset = DatasetBuilder::new(name)
+            .fuseki(&base)
+            .build()
+            .unwrap()
+            .with_interceptor(SetHeader::new(
+                USER_AGENT,
+                HeaderValue::from_static("graph-store-test"),
+            ))
+            .with_interceptor(Audit {
+                name: "outer",
+                log: log.clone(),
+            })
+            .with_interceptor(Audit {
+                name: "inner",
+                log: log.clone(),
+            });
+
+        assert!(dataset.ask(AskQuery::unsafe_from("ASK {}")).await.unwrap());
+
+        ask_mock.assert();
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "before outer Endpoint",
+                "before inner Endpoint",
+                "after inner POST 200 OK 2",
+                "after outer POST 200 OK 2",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn runs_interceptors_around_token_requests() {
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "intercepted-token";
+        let token_mock = mock("POST", "/intercepted-token/token")
+            .match_header("x-audit", "audit")
+            .with_status(200)
+            .with_body(r#"{ "access_token": "abc", "expires_in": 3600 }"#)
+            .create();
+        let ask_mock = mock("POST", format!("/{}", name).as_ref())
+            .match_header("authorization", "Bearer abc")
+            .with_status(200)
+            .with_body(r#"{ "head": {}, "boolean": true }"#)
+            .create();
+        let token_url = base.join("/intercepted-token/token").unwrap();
+        let provider = OAuth2::client_credentials(token_url, "client", "secret");
+        let log = Arc::new(Mutex::new(vec![]));
+        let dataset = DatasetBuilder::new(name)
+            .fuseki(&base)
+            .credentials(Credentials::OAuth2(provider))
+            .build()
+            .unwrap()
+            .with_interceptor(Audit {
+                name: "audit",
+                log: log.clone(),
+            });
+
+        assert!(dataset.ask(AskQuery::unsafe_from("ASK {}")).await.unwrap());
+
+        token_mock.assert();
+        ask_mock.assert();
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "before audit Token",
+                "after audit POST 200 OK 1",
+                "before audit Endpoint",
+                "after audit POST 200 OK 1",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn compressed_upload_with_progress() {
+        let content = "<http://example.org/a> <http://example.org/b> \"c\" .\n".repeat(5000);
+        let read = Arc::new(AtomicU64::new(0));
+        let reported = read.clone();
+
+        let chunks: Vec<Vec<u8>> = upload_stream(
+            Content::Bytes(content.as_bytes().to_vec()),
+            true,
+            Some(Arc::new(move |total| {
+                reported.store(total, Ordering::SeqCst)
+            })),
+        )
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await;
+
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(&chunks.concat()[..])
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, content);
+        assert_eq!(read.load(Ordering::SeqCst), content.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn imports_compressed_files() {
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "compressed";
+        let content = "<http://example.org/a> <http://example.org/b> \"c\" .\n";
+        let import_mock = mock("PUT", format!("/{}/data", name).as_ref())
+            .match_body(Matcher::AllOf(vec![
+                Matcher::Regex(r#"filename="file.ttl.gz""#.to_string()),
+                Matcher::Regex("Content-Type: text/turtle".to_string()),
+            ]))
+            .with_status(200)
+            .create();
+        let read = Arc::new(AtomicU64::new(0));
+        let reported = read.clone();
+        let dataset = DatasetBuilder::new(name).fuseki(&base).build().unwrap();
+
+        dataset
+            .import(
+                Graph::Default,
+                DataFile::unsafe_from_turtle(content)
+                    .gzip()
+                    .on_progress(move |total| reported.store(total, Ordering::SeqCst)),
+            )
+            .await
+            .unwrap();
+
+        import_mock.assert();
+        assert_eq!(read.load(Ordering::SeqCst), content.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn correct_get_json_ld_request() {
+        let client = reqwest::Client::new();
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "test";
+        let _create_mock = create_endpoint_mock();
+        let get_mock = mock("GET", format!("/{}/data", name).as_ref())
+            .match_query(Matcher::UrlEncoded(
+                "graph".to_string(),
+                "http://example.org/g".to_string(),
+            ))
+            .match_header("accept", "application/ld+json")
+            .with_status(200)
+            .with_body(
+                r#"{ "@id": "http://example.org/a", "b": "c",
+                     "@context": { "b": { "@id": "http://example.org/b" } } }"#,
+            )
+            .create();
+        let dataset = Dataset::get_or_create(&client, base, name, DatasetType::Mem)
+            .await
+            .unwrap();
+        let context = json!({ "ex": "http://example.org/" });
+
+        let document = dataset
+            .get_json_ld(
+                Graph::Named(Resource::parse("http://example.org/g").unwrap()),
+                Some(&context),
+            )
+            .await
+            .unwrap();
+
+        get_mock.assert();
+        assert_eq!(
+            document,
+            json!({ "@context": context, "@id": "ex:a", "ex:b": "c" })
+        );
+    }
+
+    #[tokio::test]
+    async fn select_declares_prefixes() {
+        let client = reqwest::Client::new();
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "test";
+        let _create_mock = create_endpoint_mock();
+        let select_mock = mock("POST", format!("/{}", name).as_ref())
+            .match_body(Matcher::UrlEncoded(
+                "query".to_string(),
+                "PREFIX ex: <http://example.org/>
+PREFIX rdfs: <http://www.w3.org/2000/01/rdf-schema#>
+
+SELECT ?s WHERE { ?s rdfs:label ex:a }"
+                    .to_string(),
+            ))
+            .with_status(200)
+            .with_body(
+                r#"{ "head": { "vars": ["s"] }, "results": { "bindings": [
+                    { "s": { "type": "uri", "value": "http://example.org/b" } }
+                ] } }"#,
+            )
+            .create();
+        let prefixes = PrefixMap::default()
+            .with("ex", "http://example.org/")
+            .unwrap();
+        let dataset = Dataset::get_or_create(&client, base, name, DatasetType::Mem)
+            .await
+            .unwrap()
+            .with_prefixes(prefixes);
+
+        let result = dataset
+            .select(Selection::unsafe_from(
+                "SELECT ?s WHERE { ?s rdfs:label ex:a }",
+            ))
+            .await
+            .unwrap()
+            .compact(dataset.prefixes());
+
+        select_mock.assert();
+        assert_eq!(result.bindings[0][&Variable::from("s")], "ex:b");
+    }
+
+    #[tokio::test]
+    async fn select_reads_typed_literals() {
+        let client = reqwest::Client::new();
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "typed";
+        let _create_mock = create_endpoint_mock();
+        let _select_mock = mock("POST", format!("/{}", name).as_ref())
+            .with_status(200)
+            .with_body(
+                r#"{ "head": { "vars": ["n", "label"] }, "results": { "bindings": [
+                    { "n": { "type": "literal", "value": "2.50",
+                             "datatype": "http://www.w3.org/2001/XMLSchema#decimal" },
+                      "label": { "type": "literal", "value": "chat", "xml:lang": "fr" } }
+                ] } }"#,
+            )
+            .create();
+        let dataset = Dataset::get_or_create(&client, base, name, DatasetType::Mem)
+            .await
+            .unwrap();
+
+        let result = dataset
+            .select(Selection::unsafe_from(
+                "SELECT ?n ?label WHERE { ?s ?p ?n }",
+            ))
+            .await
+            .unwrap()
+            .terms();
+
+        let row = &result.bindings[0];
+        assert_eq!(
+            row[&Variable::from("n")].to_string(),
+            "\"2.5\"^^<http://www.w3.org/2001/XMLSchema#decimal>"
+        );
+        assert_eq!(row[&Variable::from("label")].to_string(), "\"chat\"@fr");
+    }
+
+    #[tokio::test]
+    async fn creates_resources() {
+        let client = reqwest::Client::new();
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let _create_mock = create_endpoint_mock();
+        let dataset = Dataset::get_or_create(&client, base, "test", DatasetType::Mem)
+            .await
+            .unwrap()
+            .with_resource_base(Resource::parse("http://example.org/data/").unwrap());
+
+        assert_eq!(
+            dataset.resource("rdfs:label").unwrap().as_str(),
+            "http://www.w3.org/2000/01/rdf-schema#label"
+        );
+        assert_eq!(
+            dataset.resource("../g1").unwrap().as_str(),
+            "http://example.org/g1"
+        );
+        assert!(dataset.resource("a b").is_err());
+    }
+
+    #[tokio::test]
+    async fn select_sends_query_dataset() {
+        let client = reqwest::Client::new();
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "test";
+        let _create_mock = create_endpoint_mock();
+        let select_mock = mock("POST", format!("/{}", name).as_ref())
+            .match_body(Matcher::AllOf(vec![
+                Matcher::Regex("default-graph-uri=urn%3Ax-arq%3ADefaultGraph".to_string()),
+                Matcher::Regex("default-graph-uri=http%3A%2F%2Fexample.org%2Fg1".to_string()),
+                Matcher::UrlEncoded(
+                    "named-graph-uri".to_string(),
+                    "http://example.org/g2".to_string(),
+                ),
+            ]))
+            .with_status(200)
+            .with_body(r#"{ "head": { "vars": ["s"] }, "results": { "bindings": [] } }"#)
+            .create();
+        let dataset = Dataset::get_or_create(&client, base, name, DatasetType::Mem)
+            .await
+            .unwrap();
+
+        dataset
+            .select(
+                Selection::of_triples()
+                    .from_graph(Graph::Default)
+                    .from_graph(Graph::Named(
+                        Resource::parse("http://example.org/g1").unwrap(),
+                    ))
+                    .from_named_graph(Resource::parse("http://example.org/g2").unwrap()),
+            )
+            .await
+            .unwrap();
+
+        select_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn query_timeout_is_sent_to_server() {
+        let client = reqwest::Client::new();
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "test";
+        let _create_mock = create_endpoint_mock();
+        let select_mock = mock("POST", format!("/{}", name).as_ref())
+            .match_body(Matcher::UrlEncoded(
+                "timeout".to_string(),
+                "1.5".to_string(),
+            ))
+            .with_status(503)
+            .with_body("Query timed out")
+            .create();
+        let dataset = Dataset::get_or_create(&client, base, name, DatasetType::Mem)
+            .await
+            .unwrap()
+            .with_timeout(Duration::from_secs(10));
+
+        let result = dataset
+            .select(Selection::of_triples().with_timeout(Duration::from_millis(1500)))
+            .await;
+
+        select_mock.assert();
+        assert!(matches!(result, Err(Error::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn reports_unavailable_servers_with_query_timeout() {
+        let client = reqwest::Client::new();
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "overloaded";
+        let _create_mock = create_endpoint_mock();
+        let select_mock = mock("POST", format!("/{}", name).as_ref())
+            .with_status(503)
+            .with_body("<html><body>Upstream timed out, request cancelled</body></html>")
+            .create();
+        let dataset = Dataset::get_or_create(&client, base, name, DatasetType::Mem)
+            .await
+            .unwrap()
+            .with_timeout(Duration::from_secs(10));
+
+        let result = dataset.select(Selection::of_triples()).await;
+
+        select_mock.assert();
+        assert!(matches!(
+            result,
+            Err(Error::Status { status, .. }) if status == reqwest::StatusCode::SERVICE_UNAVAILABLE
+        ));
+    }
+
+    #[tokio::test]
+    async fn reports_graphs_that_are_not_iris() {
+        let client = reqwest::Client::new();
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "literal-graph";
+        let _create_mock = create_endpoint_mock();
+        let select_mock = mock("POST", format!("/{}", name).as_ref())
+            .with_status(200)
+            .with_body(
+                r#"{ "head": { "vars": ["graph"] }, "results": { "bindings": [
+                     { "graph": { "type": "literal", "value": "g" } } ] } }"#,
+            )
+            .create();
+        let dataset = Dataset::get_or_create(&client, base, name, DatasetType::Mem)
+            .await
+            .unwrap();
+
+        let result = dataset.describe_everything().await;
+
+        select_mock.assert();
+        assert!(matches!(result, Err(Error::Response(_))));
+    }
+
+    #[test]
+    fn recognizes_fuseki_cancellation_messages() {
+        assert!(is_cancelled("Error 503: Query timed out\n"));
+        assert!(is_cancelled("The query timed out (restricted to 1500 ms)"));
+        assert!(!is_cancelled(
+            "<html><body>Upstream timed out; request cancelled</body></html>"
+        ));
+        assert!(!is_cancelled("Error 503: Service Unavailable"));
+    }
+
+    #[tokio::test]
+    async fn correct_ask_request() {
+        let client = reqwest::Client::new();
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "test";
+        let _create_mock = create_endpoint_mock();
+        let query = "ASK WHERE { ?s ?p ?o }";
+        let ask_mock = mock("POST", format!("/{}", name).as_ref())
+            .match_header("accept", "application/sparql-results+json")
+            .match_body(Matcher::UrlEncoded("query".to_string(), query.to_string()))
+            .with_status(200)
+            .with_body(r#"{ "head": {}, "boolean": true }"#)
+            .create();
+        let dataset = Dataset::get_or_create(&client, base, name, DatasetType::Mem)
+            .await
+            .unwrap();
+
+        let result = dataset.ask(AskQuery::unsafe_from(query)).await.unwrap();
+
+        ask_mock.assert();
+        assert!(result);
+    }
+
+    #[tokio::test]
+    async fn shares_dataset_between_tasks() {
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "shared";
+        let _create_mock = create_endpoint_mock();
+        let ask_mock = mock("POST", format!("/{}", name).as_ref())
+            .with_status(200)
+            .with_body(r#"{ "head": {}, "boolean": true }"#)
+            .expect(2)
+            .create();
+        let dataset = Dataset::get_or_create(&reqwest::Client::new(), base, name, DatasetType::Mem)
+            .await
+            .unwrap();
+        let store: Arc<dyn GraphStore> = Arc::new(dataset.clone());
+
+        let tasks = vec![
+            tokio::spawn(async move { dataset.ask(AskQuery::unsafe_from("ASK {}")).await }),
+            tokio::spawn(async move { store.ask(AskQuery::unsafe_from("ASK {}")).await }),
+        ];
+
+        for task in tasks {
+            assert!(task.await.unwrap().unwrap());
+        }
+        ask_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn correct_dump_request() {
+        let client = reqwest::Client::new();
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "test";
+        let _create_mock = create_endpoint_mock();
+        let quads =
+            "<http://example.org/a> <http://example.org/b> \"c\" <http://example.org/g> .\n";
+        let dump_mock = mock("GET", format!("/{}/data", name).as_ref())
+            .match_header("accept", "application/n-quads")
+            .with_status(200)
+            .with_body(quads)
+            .create();
+        let dataset = Dataset::get_or_create(&client, base, name, DatasetType::Mem)
+            .await
+            .unwrap();
+        let mut output: Vec<u8> = vec![];
+
+        dataset.dump(QuadFormat::NQuads, &mut output).await.unwrap();
+
+        dump_mock.assert();
+        assert_eq!(String::from_utf8(output).unwrap(), quads);
+    }
+
+    #[tokio::test]
+    async fn correct_restore_request() {
+        let client = reqwest::Client::new();
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "test";
+        let _create_mock = create_endpoint_mock();
+        let _empty_mock = mock("POST", format!("/{}", name).as_ref())
+            .with_status(200)
+            .with_body(
+                r#"{ "head": { "vars": ["s", "p", "o", "g"] }, "results": { "bindings": [] } }"#,
+            )
+            .create();
+        let trig =
+            "<http://example.org/g> { <http://example.org/a> <http://example.org/b> \"c\" . }";
+        let restore_mock = mock("PUT", format!("/{}/data", name).as_ref())
+            .match_header("content-type", "application/trig")
+            .match_body(trig)
+            .with_status(200)
+            .create();
+        let dataset = Dataset::get_or_create(&client, base, name, DatasetType::Mem)
+            .await
+            .unwrap();
+
+        dataset
+            .restore(
+                QuadFormat::TriG,
+                std::io::Cursor::new(trig.as_bytes().to_vec()),
+            )
+            .await
+            .unwrap();
+
+        restore_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn restores_only_into_empty_datasets() {
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "filled";
+        let _filled_mock = mock("POST", format!("/{}", name).as_ref())
+            .with_status(200)
+            .with_body(
+                r#"{ "head": { "vars": ["s", "p", "o", "g"] }, "results": { "bindings": [
+                    { "s": { "type": "uri", "value": "http://example.org/a" } }
+                ] } }"#,
+            )
+            .create();
+        let restore_mock = mock("PUT", format!("/{}/data", name).as_ref())
+            .expect(0)
+            .create();
+        let dataset = DatasetBuilder::new(name).fuseki(&base).build().unwrap();
+
+        let result = dataset
+            .restore(QuadFormat::NQuads, std::io::Cursor::new(vec![]))
+            .await;
+
+        restore_mock.assert();
+        assert!(matches!(result, Err(Error::NotEmpty(n)) if n == name));
+    }
 }
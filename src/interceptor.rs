@@ -0,0 +1,75 @@
+//! Hooks around the requests that a dataset sends to its endpoints.
+
+use crate::error::Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// What a request is sent for.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RequestKind {
+    /// A request to an endpoint of the dataset.
+    Endpoint,
+    /// A request for an OAuth 2.0 access token, which carries the client secret.
+    Token,
+}
+
+/// Runs around each attempt of each request that a dataset sends, for example to add
+/// correlation IDs, sign requests or log them. Interceptors run before the request in the order
+/// in which they were added, after the credentials are added, and after it in reverse order.
+/// Requests for access tokens run through them too, with their own kind.
+pub trait Interceptor: Send + Sync {
+    /// Can change the request before it is sent.
+    fn before(&self, _request: &mut reqwest::Request, _kind: RequestKind) {}
+
+    /// Observes the response or error, with the time since the request was sent. The request is
+    /// as it was sent, but without its body.
+    fn after(
+        &self,
+        _request: &reqwest::Request,
+        _kind: RequestKind,
+        _result: &Result<reqwest::Response, Error>,
+        _elapsed: Duration,
+    ) {
+    }
+}
+
+/// Sends a single attempt through interceptors.
+pub(crate) async fn execute(
+    client: &reqwest::Client,
+    interceptors: &[Arc<dyn Interceptor>],
+    request: reqwest::RequestBuilder,
+    kind: RequestKind,
+) -> Result<reqwest::Response, Error> {
+    let mut request = request.build()?;
+    for interceptor in interceptors {
+        interceptor.before(&mut request, kind);
+    }
+    let mut sent = reqwest::Request::new(request.method().clone(), request.url().clone());
+    *sent.headers_mut() = request.headers().clone();
+    let start = Instant::now();
+    let result = client.execute(request).await.map_err(Error::from);
+    for interceptor in interceptors.iter().rev() {
+        interceptor.after(&sent, kind, &result, start.elapsed());
+    }
+    result
+}
+
+/// Sets a header on each request, such as a custom user agent.
+pub struct SetHeader {
+    name: reqwest::header::HeaderName,
+    value: reqwest::header::HeaderValue,
+}
+
+impl SetHeader {
+    pub fn new(name: reqwest::header::HeaderName, value: reqwest::header::HeaderValue) -> Self {
+        SetHeader { name, value }
+    }
+}
+
+impl Interceptor for SetHeader {
+    fn before(&self, request: &mut reqwest::Request, _kind: RequestKind) {
+        request
+            .headers_mut()
+            .insert(self.name.clone(), self.value.clone());
+    }
+}
@@ -0,0 +1,472 @@
+use std::fmt;
+
+/// An IRI that does not conform to RFC 3987.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct IriError {
+    pub iri: String,
+    pub message: String,
+}
+
+impl fmt::Display for IriError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid IRI <{}>: {}", self.iri, self.message)
+    }
+}
+
+impl std::error::Error for IriError {}
+
+/// The components of an IRI reference, split as in appendix B of RFC 3986.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Components {
+    scheme: Option<String>,
+    authority: Option<String>,
+    path: String,
+    query: Option<String>,
+    fragment: Option<String>,
+}
+
+impl Components {
+    fn split(reference: &str) -> Components {
+        let (rest, fragment) = match reference.find('#') {
+            Some(i) => (&reference[..i], Some(reference[i + 1..].to_string())),
+            None => (reference, None),
+        };
+        let (rest, query) = match rest.find('?') {
+            Some(i) => (&rest[..i], Some(rest[i + 1..].to_string())),
+            None => (rest, None),
+        };
+        let (scheme, rest) = match rest.find(&[':', '/'][..]) {
+            Some(i) if rest[i..].starts_with(':') && is_scheme(&rest[..i]) => {
+                (Some(rest[..i].to_string()), &rest[i + 1..])
+            }
+            _ => (None, rest),
+        };
+        let (authority, path) = if let Some(rest) = rest.strip_prefix("//") {
+            let end = rest.find('/').unwrap_or(rest.len());
+            (Some(rest[..end].to_string()), rest[end..].to_string())
+        } else {
+            (None, rest.to_string())
+        };
+        Components {
+            scheme,
+            authority,
+            path,
+            query,
+            fragment,
+        }
+    }
+
+    fn to_iri(&self) -> String {
+        let mut output = String::new();
+        if let Some(scheme) = &self.scheme {
+            output.push_str(scheme);
+            output.push(':');
+        }
+        if let Some(authority) = &self.authority {
+            output.push_str("//");
+            output.push_str(authority);
+        }
+        output.push_str(&self.path);
+        if let Some(query) = &self.query {
+            output.push('?');
+            output.push_str(query);
+        }
+        if let Some(fragment) = &self.fragment {
+            output.push('#');
+            output.push_str(fragment);
+        }
+        output
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if let Some(authority) = &self.authority {
+            check_authority(authority)?;
+        } else if self.path.starts_with("//") {
+            return Err("A path cannot start with // without an authority".to_string());
+        }
+        if self.scheme.is_none() && self.authority.is_none() {
+            let first_segment = self.path.split('/').next().unwrap_or("");
+            if first_segment.contains(':') {
+                return Err(format!(
+                    "The first segment {} of a relative path cannot contain a colon",
+                    first_segment
+                ));
+            }
+        }
+        check_characters(&self.path, "path", |c| is_ipchar(c) || c == '/')?;
+        if let Some(query) = &self.query {
+            check_characters(query, "query", |c| {
+                is_ipchar(c) || is_iprivate(c) || c == '/' || c == '?'
+            })?;
+        }
+        if let Some(fragment) = &self.fragment {
+            check_characters(fragment, "fragment", |c| {
+                is_ipchar(c) || c == '/' || c == '?'
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Applies the syntax-based normalizations of RFC 3987, which do not change what the IRI
+    /// identifies: lowercase scheme and host, uppercase percent-encodings, decoded unreserved
+    /// characters and no dot segments.
+    fn normalize(mut self) -> Components {
+        self.scheme = self.scheme.map(|s| s.to_ascii_lowercase());
+        self.authority = self.authority.map(|authority| {
+            let (userinfo, host_port) = match authority.rfind('@') {
+                Some(i) => (&authority[..=i], &authority[i + 1..]),
+                None => ("", authority.as_str()),
+            };
+            format!(
+                "{}{}",
+                normalize_percent_encoding(userinfo),
+                normalize_percent_encoding(&host_port.to_ascii_lowercase())
+            )
+        });
+        let path = normalize_percent_encoding(&self.path);
+        self.path = if self.scheme.is_some() && path.starts_with('/') {
+            remove_dot_segments(&path)
+        } else {
+            path
+        };
+        self.query = self.query.map(|q| normalize_percent_encoding(&q));
+        self.fragment = self.fragment.map(|f| normalize_percent_encoding(&f));
+        self
+    }
+}
+
+fn is_scheme(scheme: &str) -> bool {
+    let mut chars = scheme.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        }
+        _ => false,
+    }
+}
+
+fn is_ucschar(c: char) -> bool {
+    match c as u32 {
+        0xA0..=0xD7FF | 0xF900..=0xFDCF | 0xFDF0..=0xFFEF => true,
+        c @ 0x10000..=0xEFFFD => c & 0xFFFF <= 0xFFFD,
+        _ => false,
+    }
+}
+
+fn is_iprivate(c: char) -> bool {
+    match c as u32 {
+        0xE000..=0xF8FF => true,
+        c @ 0xF0000..=0x10FFFD => c & 0xFFFF <= 0xFFFD,
+        _ => false,
+    }
+}
+
+fn is_unreserved(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == '_' || c == '~'
+}
+
+fn is_iunreserved(c: char) -> bool {
+    is_unreserved(c) || is_ucschar(c)
+}
+
+fn is_sub_delim(c: char) -> bool {
+    "!$&'()*+,;=".contains(c)
+}
+
+fn is_ipchar(c: char) -> bool {
+    is_iunreserved(c) || is_sub_delim(c) || c == ':' || c == '@'
+}
+
+/// Checks that a component consists of allowed characters and well-formed percent-encodings.
+fn check_characters<F: Fn(char) -> bool>(
+    component: &str,
+    name: &str,
+    allowed: F,
+) -> Result<(), String> {
+    let mut chars = component.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == '%' {
+            let hex = component[i + 1..].chars().take(2);
+            if hex.clone().count() < 2 || !hex.clone().all(|h| h.is_ascii_hexdigit()) {
+                return Err(format!("Malformed percent-encoding in the {}", name));
+            }
+            chars.nth(1);
+        } else if !allowed(c) {
+            return Err(format!("{:?} is not allowed in the {}", c, name));
+        }
+    }
+    Ok(())
+}
+
+fn check_authority(authority: &str) -> Result<(), String> {
+    let (userinfo, host_port) = match authority.rfind('@') {
+        Some(i) => (Some(&authority[..i]), &authority[i + 1..]),
+        None => (None, authority),
+    };
+    if let Some(userinfo) = userinfo {
+        check_characters(userinfo, "user information", |c| {
+            is_iunreserved(c) || is_sub_delim(c) || c == ':'
+        })?;
+    }
+    let (host, port) = if host_port.starts_with('[') {
+        match host_port.find(']') {
+            Some(i) => (&host_port[..=i], &host_port[i + 1..]),
+            None => return Err("Unterminated IP literal".to_string()),
+        }
+    } else {
+        match host_port.rfind(':') {
+            Some(i) => (&host_port[..i], &host_port[i..]),
+            None => (host_port, ""),
+        }
+    };
+    let valid_port = match port.strip_prefix(':') {
+        Some(digits) => digits.chars().all(|c| c.is_ascii_digit()),
+        None => port.is_empty(),
+    };
+    if !valid_port {
+        return Err(format!("Invalid port {}", port));
+    }
+    if host.starts_with('[') {
+        check_ip_literal(&host[1..host.len() - 1])
+    } else {
+        check_characters(host, "host", |c| is_iunreserved(c) || is_sub_delim(c))
+    }
+}
+
+fn check_ip_literal(literal: &str) -> Result<(), String> {
+    if literal.starts_with('v') || literal.starts_with('V') {
+        let valid = match literal[1..].find('.') {
+            Some(i) => {
+                i > 0
+                    && literal[1..=i].chars().all(|c| c.is_ascii_hexdigit())
+                    && literal.len() > i + 2
+                    && literal[i + 2..]
+                        .chars()
+                        .all(|c| is_unreserved(c) || is_sub_delim(c) || c == ':')
+            }
+            None => false,
+        };
+        return if valid {
+            Ok(())
+        } else {
+            Err(format!("Invalid IP literal {}", literal))
+        };
+    }
+    if is_ipv6(literal) {
+        Ok(())
+    } else {
+        Err(format!("Invalid IPv6 address {}", literal))
+    }
+}
+
+fn is_ipv4(address: &str) -> bool {
+    let octets: Vec<&str> = address.split('.').collect();
+    octets.len() == 4
+        && octets.iter().all(|o| {
+            !o.is_empty()
+                && o.len() <= 3
+                && o.chars().all(|c| c.is_ascii_digit())
+                && (o.len() == 1 || !o.starts_with('0'))
+                && matches!(o.parse::<u16>(), Ok(n) if n <= 255)
+        })
+}
+
+fn is_ipv6(address: &str) -> bool {
+    let groups = |part: &str| -> Option<usize> {
+        if part.is_empty() {
+            return Some(0);
+        }
+        let pieces: Vec<&str> = part.split(':').collect();
+        let mut count = 0;
+        for (i, piece) in pieces.iter().enumerate() {
+            if i == pieces.len() - 1 && piece.contains('.') {
+                if !is_ipv4(piece) {
+                    return None;
+                }
+                count += 2;
+            } else if !piece.is_empty()
+                && piece.len() <= 4
+                && piece.chars().all(|c| c.is_ascii_hexdigit())
+            {
+                count += 1;
+            } else {
+                return None;
+            }
+        }
+        Some(count)
+    };
+    match address.find("::") {
+        Some(i) => {
+            let (head, tail) = (&address[..i], &address[i + 2..]);
+            match (groups(head), groups(tail)) {
+                (Some(h), Some(t)) => !tail.contains("::") && h + t < 8,
+                _ => false,
+            }
+        }
+        None => groups(address) == Some(8),
+    }
+}
+
+/// Writes percent-encodings in uppercase and decodes those of unreserved ASCII characters.
+fn normalize_percent_encoding(component: &str) -> String {
+    let mut output = String::with_capacity(component.len());
+    let mut rest = component;
+    while let Some(i) = rest.find('%') {
+        output.push_str(&rest[..i]);
+        let encoded = &rest[i + 1..i + 3];
+        let c = u8::from_str_radix(encoded, 16).unwrap() as char;
+        if is_unreserved(c) {
+            output.push(c);
+        } else {
+            output.push('%');
+            output.push_str(&encoded.to_ascii_uppercase());
+        }
+        rest = &rest[i + 3..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Removes `.` and `..` segments from a path, as in section 5.2.4 of RFC 3986.
+fn remove_dot_segments(path: &str) -> String {
+    let mut input = path;
+    let mut output: Vec<&str> = vec![];
+    while !input.is_empty() {
+        if let Some(rest) = input.strip_prefix("../") {
+            input = rest;
+        } else if let Some(rest) = input.strip_prefix("./") {
+            input = rest;
+        } else if input.starts_with("/./") {
+            input = &input[2..];
+        } else if input == "/." {
+            input = "/";
+        } else if input.starts_with("/../") || input == "/.." {
+            input = if input == "/.." { "/" } else { &input[3..] };
+            output.pop();
+        } else if input == "." || input == ".." {
+            input = "";
+        } else {
+            let start = if input.starts_with('/') { 1 } else { 0 };
+            let end = input[start..].find('/').map_or(input.len(), |i| i + start);
+            output.push(&input[..end]);
+            input = &input[end..];
+        }
+    }
+    output.concat()
+}
+
+fn error<T>(iri: &str, message: String) -> Result<T, IriError> {
+    Err(IriError {
+        iri: iri.to_string(),
+        message,
+    })
+}
+
+/// Checks that a string is an absolute IRI and normalizes it.
+pub fn parse(iri: &str) -> Result<String, IriError> {
+    let components = Components::split(iri);
+    if components.scheme.is_none() {
+        return error(iri, "The IRI is relative".to_string());
+    }
+    if let Err(message) = components.check() {
+        return error(iri, message);
+    }
+    Ok(components.normalize().to_iri())
+}
+
+/// Resolves an IRI reference against an absolute base IRI, as in section 5.2 of RFC 3986, and
+/// normalizes the result.
+pub fn resolve(reference: &str, base: &str) -> Result<String, IriError> {
+    let r = Components::split(reference);
+    if let Err(message) = r.check() {
+        return error(reference, message);
+    }
+    let base = Components::split(&parse(base)?);
+    let target = if r.scheme.is_some() {
+        r
+    } else if r.authority.is_some() {
+        Components {
+            scheme: base.scheme,
+            ..r
+        }
+    } else if r.path.is_empty() {
+        Components {
+            query: r.query.or(base.query),
+            fragment: r.fragment,
+            ..base
+        }
+    } else {
+        let path = if r.path.starts_with('/') {
+            r.path
+        } else if base.authority.is_some() && base.path.is_empty() {
+            format!("/{}", r.path)
+        } else {
+            let directory = base.path.rfind('/').map_or("", |i| &base.path[..=i]);
+            format!("{}{}", directory, r.path)
+        };
+        Components {
+            path: remove_dot_segments(&path),
+            query: r.query,
+            fragment: r.fragment,
+            ..base
+        }
+    };
+    Ok(target.normalize().to_iri())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::iri::{parse, resolve};
+
+    #[test]
+    fn parses_and_normalizes_absolute_iris() {
+        assert_eq!(
+            parse("HTTP://User@Example.ORG:8080/a/./b/../c%7e%2f?q=%c3%a9#Straße"),
+            Ok("http://User@example.org:8080/a/c~%2F?q=%C3%A9#Straße".to_string())
+        );
+        assert_eq!(
+            parse("urn:isbn:0451450523"),
+            Ok("urn:isbn:0451450523".to_string())
+        );
+        assert_eq!(
+            parse("http://[2001:db8::7]/c"),
+            Ok("http://[2001:db8::7]/c".to_string())
+        );
+
+        for invalid in &[
+            "g1",
+            "#a",
+            "http://example.org/a b",
+            "http://example.org/<a>",
+            "http://example.org/%zz",
+            "http://example.org:80a/",
+            "http://[2001:db8::7::1]/",
+            "1http://example.org/",
+        ] {
+            assert!(parse(invalid).is_err(), "{} should be invalid", invalid);
+        }
+    }
+
+    #[test]
+    fn resolves_references() {
+        let base = "http://a/b/c/d;p?q";
+        for (reference, expected) in &[
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"),
+            ("//g", "http://g"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("#s", "http://a/b/c/d;p?q#s"),
+            ("", "http://a/b/c/d;p?q"),
+            ("../..", "http://a/"),
+            ("../../../g", "http://a/g"),
+            ("g;x=1/../y", "http://a/b/c/y"),
+            ("urn:x", "urn:x"),
+        ] {
+            assert_eq!(resolve(reference, base), Ok(expected.to_string()));
+        }
+        assert!(resolve("a:b c", base).is_err());
+        assert!(resolve("g", "relative/base").is_err());
+    }
+}
@@ -0,0 +1,704 @@
+//! Conversion between graphs and JSON-LD documents.
+//!
+//! This is not a full JSON-LD processor. Contexts are read from a single object of term
+//! definitions, which may be simple IRIs, prefixes ending in `/` or `#`, or objects with `@id`,
+//! `@type` and `@language`, and from `@vocab`. When compacting, other term definitions are not
+//! used. A document that is compacted against another context must not use remote contexts,
+//! arrays of contexts, scoped contexts, `@base`, keyword aliases, `@container`, `@reverse`,
+//! `@list`, `@set`, `@index`, `@nest` or `@included`, so that none of its data is lost.
+
+use rdf::node::Node;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const XSD_STRING: &str = "http://www.w3.org/2001/XMLSchema#string";
+
+/// Converts a graph to a JSON-LD document.
+///
+/// Without a context, the document is in expanded form. With a context, which is the value of
+/// an `@context` entry, IRIs and values are compacted against its term definitions, prefixes and
+/// `@vocab`.
+pub fn from_graph(graph: &rdf::graph::Graph, context: Option<&Value>) -> Value {
+    let expanded = expand(graph);
+    match context {
+        Some(context) => compact(expanded, context),
+        None => Value::Array(expanded),
+    }
+}
+
+/// A JSON-LD document that uses a feature that this module does not support.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct JsonLdError {
+    pub message: String,
+}
+
+impl fmt::Display for JsonLdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Unsupported JSON-LD: {}", self.message)
+    }
+}
+
+impl std::error::Error for JsonLdError {}
+
+fn unsupported<T>(feature: &str) -> Result<T, JsonLdError> {
+    Err(JsonLdError {
+        message: format!("{} is not supported", feature),
+    })
+}
+
+/// Returns a JSON-LD document, such as one returned by a graph store, as it is, or compacts it
+/// against another context. A document that cannot be compacted without losing data gives an
+/// error.
+pub fn from_document(document: Value, context: Option<&Value>) -> Result<Value, JsonLdError> {
+    match context {
+        Some(context) => Ok(compact(expand_document(document)?, context)),
+        None => Ok(document),
+    }
+}
+
+/// The `@id` of an IRI or a blank node, or `None` for a literal.
+fn node_id(node: &Node) -> Option<String> {
+    match node {
+        Node::UriNode { uri } => Some(uri.to_string().to_string()),
+        Node::BlankNode { id } => Some(format!("_:{}", id)),
+        Node::LiteralNode { .. } => None,
+    }
+}
+
+fn expand_object(node: &Node) -> Value {
+    match node {
+        Node::LiteralNode {
+            literal,
+            data_type: Some(data_type),
+            language: _,
+        } if data_type.to_string() != XSD_STRING => {
+            json!({ "@value": literal, "@type": data_type.to_string() })
+        }
+        Node::LiteralNode {
+            literal,
+            data_type: _,
+            language: Some(language),
+        } => json!({ "@value": literal, "@language": language }),
+        Node::LiteralNode { literal, .. } => json!({ "@value": literal }),
+        Node::UriNode { uri } => json!({ "@id": uri.to_string() }),
+        Node::BlankNode { id } => json!({ "@id": format!("_:{}", id) }),
+    }
+}
+
+fn expand(graph: &rdf::graph::Graph) -> Vec<Value> {
+    let mut nodes = Nodes::default();
+    for triple in graph.triples_iter() {
+        // Literals cannot be subjects or predicates in RDF, so such triples are skipped.
+        let (subject, predicate) = match (node_id(triple.subject()), node_id(triple.predicate())) {
+            (Some(subject), Some(predicate)) => (subject, predicate),
+            _ => continue,
+        };
+        let (key, value) = match node_id(triple.object()) {
+            Some(object) if predicate == RDF_TYPE => ("@type".to_string(), Value::String(object)),
+            _ => (predicate, expand_object(triple.object())),
+        };
+        nodes.add(subject, key, value);
+    }
+    nodes.into_values()
+}
+
+/// Node objects in expanded form, in the order in which their subjects were first seen.
+#[derive(Default)]
+struct Nodes {
+    subjects: Vec<String>,
+    objects: HashMap<String, Map<String, Value>>,
+}
+
+impl Nodes {
+    fn node(&mut self, subject: String) -> &mut Map<String, Value> {
+        let subjects = &mut self.subjects;
+        self.objects.entry(subject.clone()).or_insert_with(|| {
+            subjects.push(subject.clone());
+            let mut object = Map::new();
+            object.insert("@id".to_string(), Value::String(subject));
+            object
+        })
+    }
+
+    fn add(&mut self, subject: String, key: String, value: Value) {
+        match self
+            .node(subject)
+            .entry(key)
+            .or_insert_with(|| Value::Array(vec![]))
+        {
+            Value::Array(values) if !values.contains(&value) => values.push(value),
+            _ => (),
+        }
+    }
+
+    fn into_values(mut self) -> Vec<Value> {
+        let objects = &mut self.objects;
+        self.subjects
+            .into_iter()
+            .map(|s| Value::Object(objects.remove(&s).unwrap()))
+            .collect()
+    }
+}
+
+/// Expands the node objects of a document against its top-level context. Embedded node objects
+/// are moved to the top level and referenced by their `@id`.
+fn expand_document(document: Value) -> Result<Vec<Value>, JsonLdError> {
+    let mut nodes = Nodes::default();
+    let mut blank_nodes = 0;
+    let elements = match document {
+        Value::Array(elements) => elements,
+        document => vec![document],
+    };
+    for element in elements {
+        let mut element = match element {
+            Value::Object(element) => element,
+            _ => return unsupported("A top-level value that is not an object"),
+        };
+        let context = match element.remove("@context") {
+            Some(context) => {
+                Context::check(&context)?;
+                Context::parse(&context)
+            }
+            None => Context::default(),
+        };
+        let mut expansion = Expansion {
+            context: &context,
+            nodes: &mut nodes,
+            blank_nodes: &mut blank_nodes,
+        };
+        match element.remove("@graph") {
+            Some(graph) if element.is_empty() => {
+                for node in values(graph) {
+                    match node {
+                        Value::Object(node) => expansion.node(node)?,
+                        _ => return unsupported("A graph member that is not a node object"),
+                    };
+                }
+            }
+            Some(_) => return unsupported("A named graph"),
+            None if !element.is_empty() => {
+                expansion.node(element)?;
+            }
+            None => (),
+        }
+    }
+    Ok(nodes.into_values())
+}
+
+/// The state of expanding a document with a context.
+struct Expansion<'a> {
+    context: &'a Context,
+    nodes: &'a mut Nodes,
+    blank_nodes: &'a mut usize,
+}
+
+impl Expansion<'_> {
+    /// Adds a node object and returns its `@id`, labeling it as a blank node if it has none.
+    fn node(&mut self, mut node: Map<String, Value>) -> Result<String, JsonLdError> {
+        let subject = match node.remove("@id") {
+            Some(Value::String(id)) => self.context.expand_iri(&id, false),
+            _ => {
+                *self.blank_nodes += 1;
+                format!("_:b{}", self.blank_nodes)
+            }
+        };
+        self.nodes.node(subject.clone());
+        for (key, value) in node {
+            if key == "@type" {
+                for t in values(value) {
+                    let t = match t {
+                        Value::String(t) => Value::String(self.context.expand_iri(&t, true)),
+                        _ => return unsupported("A type that is not a string"),
+                    };
+                    self.nodes.add(subject.clone(), key.clone(), t);
+                }
+            } else if key.starts_with('@') {
+                return unsupported(&key);
+            } else {
+                let property = self.context.expand_iri(&key, true);
+                let term = self.context.terms.iter().find(|t| t.name == key);
+                for value in values(value) {
+                    if let Some(value) = self.value(value, term)? {
+                        self.nodes.add(subject.clone(), property.clone(), value);
+                    }
+                }
+            }
+        }
+        Ok(subject)
+    }
+
+    /// Expands a value, or returns `None` for `null`, which JSON-LD ignores.
+    fn value(&mut self, value: Value, term: Option<&Term>) -> Result<Option<Value>, JsonLdError> {
+        let coercion = term.and_then(|t| t.value_type.as_deref());
+        let value = match value {
+            Value::Object(mut object) if object.contains_key("@value") => {
+                if let Some(key) = object
+                    .keys()
+                    .find(|k| !["@value", "@type", "@language"].contains(&k.as_str()))
+                {
+                    return unsupported(key);
+                }
+                if let Some(Value::String(t)) = object.remove("@type") {
+                    let t = self.context.expand_iri(&t, true);
+                    object.insert("@type".to_string(), Value::String(t));
+                }
+                Value::Object(object)
+            }
+            Value::Object(object) if object.len() == 1 && object.contains_key("@id") => {
+                match object.get("@id") {
+                    Some(Value::String(id)) => json!({ "@id": self.context.expand_iri(id, false) }),
+                    _ => return unsupported("An @id that is not a string"),
+                }
+            }
+            Value::Object(object) => match object
+                .keys()
+                .find(|k| k.starts_with('@') && !["@id", "@type"].contains(&k.as_str()))
+            {
+                Some(key) => return unsupported(key),
+                None => json!({ "@id": self.node(object)? }),
+            },
+            Value::String(id) if coercion == Some("@id") => {
+                json!({ "@id": self.context.expand_iri(&id, false) })
+            }
+            Value::String(s) => match (coercion, term.and_then(|t| t.language.as_deref())) {
+                (Some(t), _) => json!({ "@value": s, "@type": self.context.expand_iri(t, true) }),
+                (None, Some(language)) => json!({ "@value": s, "@language": language }),
+                (None, None) => json!({ "@value": s }),
+            },
+            Value::Null => return Ok(None),
+            Value::Array(_) => return unsupported("A nested array"),
+            value => json!({ "@value": value }),
+        };
+        Ok(Some(value))
+    }
+}
+
+/// The values of a property, which may be a single value or an array.
+fn values(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(values) => values,
+        value => vec![value],
+    }
+}
+
+/// A term definition from a context, reduced to what compaction needs.
+struct Term {
+    name: String,
+    id: String,
+    value_type: Option<String>,
+    language: Option<String>,
+}
+
+#[derive(Default)]
+struct Context {
+    terms: Vec<Term>,
+    prefixes: Vec<(String, String)>,
+    vocab: Option<String>,
+}
+
+impl Context {
+    /// Checks that a context of a document uses only the supported features, so that
+    /// expanding the document does not lose data.
+    fn check(value: &Value) -> Result<(), JsonLdError> {
+        let definitions = match value {
+            Value::Object(definitions) => definitions,
+            Value::String(_) => return unsupported("A remote context"),
+            _ => return unsupported("A context that is not an object"),
+        };
+        for (name, definition) in definitions {
+            match (name.as_str(), definition) {
+                ("@vocab", _) | ("@version", _) => (),
+                (keyword, _) if keyword.starts_with('@') => return unsupported(keyword),
+                (_, Value::String(id)) if id.starts_with('@') => {
+                    return unsupported("A keyword alias")
+                }
+                (_, Value::String(_)) | (_, Value::Null) => (),
+                (_, Value::Object(definition)) => {
+                    if let Some(key) = definition
+                        .keys()
+                        .find(|k| !["@id", "@type", "@language"].contains(&k.as_str()))
+                    {
+                        return unsupported(key);
+                    }
+                    match definition.get("@id").and_then(Value::as_str) {
+                        Some(id) if !id.starts_with('@') => (),
+                        _ => return unsupported("A term definition without an IRI"),
+                    }
+                }
+                _ => return unsupported("A term definition that is not an object"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the supported term definitions of a context, leaving out others.
+    fn parse(value: &Value) -> Context {
+        let mut context = Context::default();
+        let definitions = match value {
+            Value::Object(definitions) => definitions,
+            _ => return context,
+        };
+        context.vocab = definitions
+            .get("@vocab")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        for (name, definition) in definitions.iter().filter(|(k, _)| !k.starts_with('@')) {
+            match definition {
+                Value::String(id) => {
+                    if id.ends_with('/') || id.ends_with('#') {
+                        context.prefixes.push((name.to_string(), id.to_string()));
+                    }
+                    context.terms.push(Term {
+                        name: name.to_string(),
+                        id: id.to_string(),
+                        value_type: None,
+                        language: None,
+                    });
+                }
+                Value::Object(definition) => {
+                    if let Some(id) = definition.get("@id").and_then(Value::as_str) {
+                        context.terms.push(Term {
+                            name: name.to_string(),
+                            id: id.to_string(),
+                            value_type: definition
+                                .get("@type")
+                                .and_then(Value::as_str)
+                                .map(str::to_string),
+                            language: definition
+                                .get("@language")
+                                .and_then(Value::as_str)
+                                .map(str::to_string),
+                        });
+                    }
+                }
+                _ => (),
+            }
+        }
+        let expanded: Vec<(String, String)> = context
+            .terms
+            .iter()
+            .map(|t| (t.name.to_string(), context.expand_curie(&t.id)))
+            .collect();
+        for (term, (_, id)) in context.terms.iter_mut().zip(expanded) {
+            term.id = id;
+        }
+        context
+    }
+
+    fn expand_curie(&self, value: &str) -> String {
+        match value.find(':') {
+            Some(i) => match self.prefixes.iter().find(|(p, _)| p == &value[..i]) {
+                Some((_, iri)) => format!("{}{}", iri, &value[i + 1..]),
+                None => value.to_string(),
+            },
+            None => value.to_string(),
+        }
+    }
+
+    /// Expands a term, if relative to the vocabulary, a compact IRI, or a vocabulary-relative
+    /// IRI.
+    fn expand_iri(&self, value: &str, vocab: bool) -> String {
+        if vocab {
+            if let Some(term) = self.terms.iter().find(|t| t.name == value) {
+                return term.id.to_string();
+            }
+        }
+        if value.contains(':') {
+            return self.expand_curie(value);
+        }
+        match &self.vocab {
+            Some(v) if vocab => format!("{}{}", v, value),
+            _ => value.to_string(),
+        }
+    }
+
+    /// Compacts an IRI relative to the vocabulary, such as a property or a type, to a term
+    /// without value coercion or with `@vocab`. Otherwise, and for other IRIs such as those of
+    /// nodes, uses the longest matching prefix.
+    fn compact_iri(&self, iri: &str, vocab: bool) -> String {
+        if vocab {
+            if let Some(term) = self
+                .terms
+                .iter()
+                .find(|t| t.id == iri && t.value_type.is_none() && t.language.is_none())
+            {
+                return term.name.to_string();
+            }
+            if let Some(v) = &self.vocab {
+                if iri.len() > v.len() && iri.starts_with(v.as_str()) {
+                    return iri[v.len()..].to_string();
+                }
+            }
+        }
+        match self
+            .prefixes
+            .iter()
+            .filter(|(_, p)| iri.len() > p.len() && iri.starts_with(p.as_str()))
+            .max_by_key(|(_, p)| p.len())
+        {
+            Some((prefix, p)) => format!("{}:{}", prefix, &iri[p.len()..]),
+            None => iri.to_string(),
+        }
+    }
+
+    /// Selects the key for a property and its values, preferring a term whose coercion matches.
+    fn compact_property(&self, iri: &str, values: &[Value]) -> (String, Option<&Term>) {
+        let coerced = self.terms.iter().find(|t| {
+            t.id == iri
+                && (t.value_type.is_some() || t.language.is_some())
+                && values.iter().all(|v| self.matches(t, v))
+        });
+        match coerced {
+            Some(term) => (term.name.to_string(), Some(term)),
+            None => (self.compact_iri(iri, true), None),
+        }
+    }
+
+    fn matches(&self, term: &Term, value: &Value) -> bool {
+        match (term.value_type.as_deref(), value) {
+            (Some("@id"), Value::Object(o)) => o.contains_key("@id"),
+            (Some(t), Value::Object(o)) => {
+                o.get("@type").and_then(Value::as_str) == Some(&self.expand_curie(t))
+            }
+            (None, Value::Object(o)) => {
+                o.get("@language").and_then(Value::as_str) == term.language.as_deref()
+            }
+            _ => false,
+        }
+    }
+
+    fn compact_value(&self, value: Value, term: Option<&Term>) -> Value {
+        let mut object = match value {
+            Value::Object(object) => object,
+            value => return value,
+        };
+        if let Some(Value::String(id)) = object.remove("@id") {
+            return match term {
+                Some(_) => Value::String(self.compact_iri(&id, false)),
+                None => json!({ "@id": self.compact_iri(&id, false) }),
+            };
+        }
+        if term.is_some() || object.len() == 1 {
+            return object.remove("@value").unwrap_or(Value::Null);
+        }
+        if let Some(Value::String(t)) = object.remove("@type") {
+            object.insert(
+                "@type".to_string(),
+                Value::String(self.compact_iri(&t, true)),
+            );
+        }
+        Value::Object(object)
+    }
+
+    fn compact_node(&self, node: Value) -> Value {
+        let node = match node {
+            Value::Object(node) => node,
+            node => return node,
+        };
+        let mut result = Map::new();
+        for (key, value) in node {
+            let (key, value) = match (key.as_str(), value) {
+                ("@id", Value::String(id)) => (key, Value::String(self.compact_iri(&id, false))),
+                ("@type", Value::Array(types)) => (
+                    key,
+                    Value::Array(
+                        types
+                            .iter()
+                            .filter_map(Value::as_str)
+                            .map(|t| Value::String(self.compact_iri(t, true)))
+                            .collect(),
+                    ),
+                ),
+                (_, Value::Array(values)) => {
+                    let (property, term) = self.compact_property(&key, &values);
+                    (
+                        property,
+                        Value::Array(
+                            values
+                                .into_iter()
+                                .map(|v| self.compact_value(v, term))
+                                .collect(),
+                        ),
+                    )
+                }
+                (_, value) => (key, value),
+            };
+            let value = match value {
+                Value::Array(mut values) if values.len() == 1 => values.remove(0),
+                value => value,
+            };
+            result.insert(key, value);
+        }
+        Value::Object(result)
+    }
+}
+
+fn compact(expanded: Vec<Value>, context: &Value) -> Value {
+    let parsed = Context::parse(context);
+    let mut nodes: Vec<Value> = expanded
+        .into_iter()
+        .map(|node| parsed.compact_node(node))
+        .collect();
+    let mut document = Map::new();
+    document.insert("@context".to_string(), context.clone());
+    if nodes.len() == 1 {
+        if let Value::Object(node) = nodes.remove(0) {
+            document.extend(node);
+        }
+    } else {
+        document.insert("@graph".to_string(), Value::Array(nodes));
+    }
+    Value::Object(document)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json_ld::{from_document, from_graph};
+    use rdf::graph::Graph;
+    use rdf::triple::Triple;
+    use rdf::uri::Uri;
+    use serde_json::json;
+
+    fn example() -> Graph {
+        let mut graph = Graph::new(None);
+        let uri = |s: &str| graph.create_uri_node(&Uri::new(s.to_string()));
+        let alice = uri("http://example.org/alice");
+        let bob = uri("http://example.org/bob");
+        let knows = uri("http://xmlns.com/foaf/0.1/knows");
+        let name = uri("http://xmlns.com/foaf/0.1/name");
+        let rdf_type = uri("http://www.w3.org/1999/02/22-rdf-syntax-ns#type");
+        let person = uri("http://xmlns.com/foaf/0.1/Person");
+        let label = graph.create_literal_node("Alice".to_string());
+        graph.add_triple(&Triple::new(&alice, &rdf_type, &person));
+        graph.add_triple(&Triple::new(&alice, &name, &label));
+        graph.add_triple(&Triple::new(&alice, &knows, &bob));
+        graph
+    }
+
+    #[test]
+    fn expands_without_context() {
+        assert_eq!(
+            from_graph(&example(), None),
+            json!([{
+                "@id": "http://example.org/alice",
+                "@type": ["http://xmlns.com/foaf/0.1/Person"],
+                "http://xmlns.com/foaf/0.1/name": [{ "@value": "Alice" }],
+                "http://xmlns.com/foaf/0.1/knows": [{ "@id": "http://example.org/bob" }]
+            }])
+        );
+    }
+
+    #[test]
+    fn compacts_against_context() {
+        let context = json!({
+            "foaf": "http://xmlns.com/foaf/0.1/",
+            "ex": "http://example.org/",
+            "name": "foaf:name",
+            "knows": { "@id": "foaf:knows", "@type": "@id" }
+        });
+        assert_eq!(
+            from_graph(&example(), Some(&context)),
+            json!({
+                "@context": context,
+                "@id": "ex:alice",
+                "@type": "foaf:Person",
+                "name": "Alice",
+                "knows": "ex:bob"
+            })
+        );
+    }
+
+    #[test]
+    fn keeps_literal_types_as_values() {
+        let mut graph = Graph::new(None);
+        let a = graph.create_uri_node(&Uri::new("http://example.org/a".to_string()));
+        let rdf_type = graph.create_uri_node(&Uri::new(
+            "http://www.w3.org/1999/02/22-rdf-syntax-ns#type".to_string(),
+        ));
+        let literal = graph.create_literal_node("Thing".to_string());
+        graph.add_triple(&Triple::new(&a, &rdf_type, &literal));
+
+        assert_eq!(
+            from_graph(&graph, None),
+            json!([{
+                "@id": "http://example.org/a",
+                "http://www.w3.org/1999/02/22-rdf-syntax-ns#type": [{ "@value": "Thing" }]
+            }])
+        );
+    }
+
+    #[test]
+    fn compacts_node_iris_without_terms() {
+        let context = json!({ "ex": "http://example.org/", "alice": "http://example.org/alice" });
+
+        assert_eq!(
+            from_graph(&example(), Some(&context))["@id"],
+            json!("ex:alice")
+        );
+    }
+
+    #[test]
+    fn compacts_documents_against_other_contexts() {
+        let document = json!({
+            "@context": {
+                "name": { "@id": "http://xmlns.com/foaf/0.1/name" },
+                "knows": { "@id": "http://xmlns.com/foaf/0.1/knows", "@type": "@id" },
+                "foaf": "http://xmlns.com/foaf/0.1/"
+            },
+            "@graph": [{
+                "@id": "http://example.org/alice",
+                "@type": "foaf:Person",
+                "name": "Alice",
+                "knows": "http://example.org/bob"
+            }]
+        });
+        let context = json!({
+            "foaf": "http://xmlns.com/foaf/0.1/",
+            "ex": "http://example.org/",
+            "name": "foaf:name",
+            "knows": { "@id": "foaf:knows", "@type": "@id" }
+        });
+
+        assert_eq!(from_document(document.clone(), None), Ok(document.clone()));
+        assert_eq!(
+            from_document(document, Some(&context)),
+            Ok(from_graph(&example(), Some(&context)))
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_features_instead_of_dropping_values() {
+        let context = json!({ "ex": "http://example.org/" });
+        let list = json!({
+            "@id": "http://example.org/a",
+            "http://example.org/items": { "@list": [{ "@value": 1 }, { "@value": 2 }] }
+        });
+        let container = json!({
+            "@context": { "items": { "@id": "http://example.org/items", "@container": "@set" } },
+            "@id": "http://example.org/a",
+            "items": ["x", "y"]
+        });
+        let reverse = json!({
+            "@id": "http://example.org/a",
+            "@reverse": { "http://example.org/knows": { "@id": "http://example.org/b" } }
+        });
+
+        assert_eq!(from_document(list.clone(), None), Ok(list.clone()));
+        assert_eq!(
+            from_document(list, Some(&context)).unwrap_err().message,
+            "@list is not supported"
+        );
+        assert_eq!(
+            from_document(container, Some(&context))
+                .unwrap_err()
+                .message,
+            "@container is not supported"
+        );
+        assert_eq!(
+            from_document(reverse, Some(&context)).unwrap_err().message,
+            "@reverse is not supported"
+        );
+    }
+}
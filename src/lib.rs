@@ -1,45 +1,386 @@
+pub mod auth;
+pub mod blocking;
+pub mod builder;
 pub mod doc;
+pub mod error;
 pub mod http;
+pub mod interceptor;
+pub mod iri;
+pub mod json_ld;
+pub mod prefix;
+pub mod query;
+pub mod retry;
+pub mod syntax;
 pub mod table;
+mod telemetry;
+pub mod term;
+pub mod void;
+pub mod writer;
 
+pub use graph_store_macros::{sparql, sparql_file};
+/// Parsing, writing and rewriting queries.
+pub use graph_store_sparql as sparql_ast;
+/// Re-exported for code generated by `sparql!`.
+pub use rdf;
+
+use crate::error::Error;
+use crate::iri::IriError;
+use crate::prefix::PrefixMap;
+use crate::query::{ParameterError, ParameterizedQuery};
+use crate::syntax::{Dialect, SyntaxError};
 use crate::table::{Table, Variable};
 use async_trait::async_trait;
 use rdf::node::Node;
+use std::convert::TryFrom;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncRead;
 
-/// Any resource, identified by an IRI string.
+/// Any resource, identified by an absolute IRI.
 #[derive(Hash, PartialEq, Eq, Debug, Clone)]
 pub struct Resource(String);
 
-impl From<&str> for Resource {
-    fn from(s: &str) -> Self {
-        Resource(s.to_string())
+impl TryFrom<&str> for Resource {
+    type Error = IriError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Resource::parse(s)
+    }
+}
+
+impl Resource {
+    /// Checks and normalizes an absolute IRI.
+    pub fn parse(iri: &str) -> Result<Resource, IriError> {
+        iri::parse(iri).map(Resource)
+    }
+
+    /// Resolves an IRI reference, such as `g1` or `#a`, against a base.
+    pub fn resolve(reference: &str, base: &Resource) -> Result<Resource, IriError> {
+        iri::resolve(reference, &base.0).map(Resource)
+    }
+
+    /// Wraps an IRI without checking it, e.g. one returned by a graph store.
+    pub fn unsafe_from(iri: &str) -> Resource {
+        Resource(iri.to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Expands a prefixed name such as `rdfs:label`, if its prefix is known.
+    pub fn from_curie(curie: &str, prefixes: &PrefixMap) -> Option<Resource> {
+        prefixes.expand(curie)
     }
 }
 
 /// The default graph or a named graph of a graph store.
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Graph {
     Default,
     Named(Resource),
 }
 
+/// The syntax of a data file.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Format {
+    Turtle,
+    NTriples,
+    RdfXml,
+    JsonLd,
+}
+
+impl Format {
+    /// Infers the format from a file extension.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Format> {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("ttl") => Some(Format::Turtle),
+            Some("nt") => Some(Format::NTriples),
+            Some("rdf") | Some("owl") | Some("xml") => Some(Format::RdfXml),
+            Some("jsonld") | Some("json") => Some(Format::JsonLd),
+            _ => None,
+        }
+    }
+}
+
+/// The syntax of a file with the default graph and named graphs of a dataset.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum QuadFormat {
+    NQuads,
+    TriG,
+}
+
+impl QuadFormat {
+    /// Infers the format from a file extension.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<QuadFormat> {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("nq") => Some(QuadFormat::NQuads),
+            Some("trig") => Some(QuadFormat::TriG),
+            _ => None,
+        }
+    }
+
+    fn mime(self) -> &'static str {
+        match self {
+            QuadFormat::NQuads => "application/n-quads",
+            QuadFormat::TriG => "application/trig",
+        }
+    }
+}
+
 /// The content of a data file, used for importing.
 #[derive(Debug)]
 pub enum DataFile {
-    Turtle { content: Vec<u8> },
-    RdfXml { content: Vec<u8> },
+    Turtle {
+        content: Vec<u8>,
+    },
+    NTriples {
+        content: Vec<u8>,
+    },
+    RdfXml {
+        content: Vec<u8>,
+    },
+    JsonLd {
+        content: Vec<u8>,
+    },
+    /// Content that is streamed, compressed or reported on while uploading.
+    Upload(Upload),
+}
+
+/// A data file with options for uploading it, made by `DataFile::from_reader`,
+/// `DataFile::gzip` or `DataFile::on_progress`.
+pub struct Upload {
+    format: Format,
+    content: Content,
+    gzip: bool,
+    progress: Option<Arc<dyn Fn(u64) + Send + Sync>>,
+}
+
+enum Content {
+    Bytes(Vec<u8>),
+    Reader(Box<dyn AsyncRead + Send + Sync + Unpin>),
+}
+
+impl fmt::Debug for Upload {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let content = match &self.content {
+            Content::Bytes(bytes) => format!("{} bytes", bytes.len()),
+            Content::Reader(_) => "stream".to_string(),
+        };
+        f.debug_struct("Upload")
+            .field("format", &self.format)
+            .field("content", &content)
+            .field("gzip", &self.gzip)
+            .finish()
+    }
+}
+
+/// A reason why a data file could not be constructed.
+#[derive(Debug)]
+pub enum DataFileError {
+    Io(std::io::Error),
+    UnknownExtension(PathBuf),
+    Syntax(SyntaxError),
+    UnsupportedFormat(Format),
+    InvalidGraph(String),
+}
+
+impl fmt::Display for DataFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DataFileError::Io(e) => write!(f, "Could not read data file: {}", e),
+            DataFileError::UnknownExtension(path) => {
+                write!(f, "Unknown data file extension: {}", path.display())
+            }
+            DataFileError::Syntax(e) => write!(f, "Invalid data file: {}", e),
+            DataFileError::UnsupportedFormat(format) => {
+                write!(f, "Cannot write graphs as {:?}", format)
+            }
+            DataFileError::InvalidGraph(message) => write!(f, "Invalid graph: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for DataFileError {}
+
+impl From<std::io::Error> for DataFileError {
+    fn from(e: std::io::Error) -> Self {
+        DataFileError::Io(e)
+    }
+}
+
+impl From<SyntaxError> for DataFileError {
+    fn from(e: SyntaxError) -> Self {
+        DataFileError::Syntax(e)
+    }
 }
 
 impl DataFile {
+    /// Accepts content without checking it locally.
+    pub fn unsafe_from(format: Format, content: Vec<u8>) -> Self {
+        match format {
+            Format::Turtle => DataFile::Turtle { content },
+            Format::NTriples => DataFile::NTriples { content },
+            Format::RdfXml => DataFile::RdfXml { content },
+            Format::JsonLd => DataFile::JsonLd { content },
+        }
+    }
+
     pub fn unsafe_from_turtle(s: &str) -> Self {
-        DataFile::Turtle {
-            content: Vec::from(s.as_bytes()),
+        DataFile::unsafe_from(Format::Turtle, Vec::from(s.as_bytes()))
+    }
+
+    /// Checks the Turtle syntax locally before accepting the content.
+    pub fn from_turtle(s: &str) -> Result<Self, DataFileError> {
+        syntax::check(s, Dialect::Turtle)?;
+        Ok(DataFile::unsafe_from_turtle(s))
+    }
+
+    /// Checks the N-Triples syntax locally before accepting the content.
+    pub fn from_n_triples(s: &str) -> Result<Self, DataFileError> {
+        syntax::check(s, Dialect::NTriples)?;
+        Ok(DataFile::unsafe_from(
+            Format::NTriples,
+            Vec::from(s.as_bytes()),
+        ))
+    }
+
+    /// Checks that the content is a JSON object or array before accepting it.
+    pub fn from_json_ld(s: &str) -> Result<Self, DataFileError> {
+        syntax::check_json_ld(s)?;
+        Ok(DataFile::unsafe_from(
+            Format::JsonLd,
+            Vec::from(s.as_bytes()),
+        ))
+    }
+
+    /// Reads a file in the format indicated by its extension.
+    ///
+    /// Turtle (`.ttl`), N-Triples (`.nt`) and JSON-LD (`.jsonld`, `.json`) are checked locally.
+    /// RDF/XML (`.rdf`, `.owl`, `.xml`) is only checked by the graph store.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, DataFileError> {
+        let path = path.as_ref();
+        match Format::from_path(path) {
+            Some(Format::Turtle) => DataFile::from_turtle(&std::fs::read_to_string(path)?),
+            Some(Format::NTriples) => DataFile::from_n_triples(&std::fs::read_to_string(path)?),
+            Some(Format::JsonLd) => DataFile::from_json_ld(&std::fs::read_to_string(path)?),
+            Some(Format::RdfXml) => Ok(DataFile::unsafe_from(Format::RdfXml, std::fs::read(path)?)),
+            None => Err(DataFileError::UnknownExtension(path.to_path_buf())),
+        }
+    }
+
+    /// Serializes a graph as Turtle, including its namespaces as prefixes, as N-Triples or as
+    /// expanded JSON-LD.
+    pub fn from_graph(graph: &rdf::graph::Graph, format: Format) -> Result<Self, DataFileError> {
+        let content = match format {
+            Format::Turtle => writer::write_turtle(graph),
+            Format::NTriples => writer::write_n_triples(graph),
+            Format::JsonLd => Ok(json_ld::from_graph(graph, None).to_string()),
+            Format::RdfXml => return Err(DataFileError::UnsupportedFormat(format)),
+        }
+        .map_err(DataFileError::InvalidGraph)?;
+        Ok(DataFile::unsafe_from(format, content.into_bytes()))
+    }
+
+    /// Streams content from a reader during import, without checking it locally.
+    pub fn from_reader<R>(format: Format, reader: R) -> Self
+    where
+        R: AsyncRead + Send + Sync + Unpin + 'static,
+    {
+        DataFile::Upload(Upload {
+            format,
+            content: Content::Reader(Box::new(reader)),
+            gzip: false,
+            progress: None,
+        })
+    }
+
+    /// Streams a file in the format indicated by its extension during import, without reading
+    /// it into memory or checking it locally.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, DataFileError> {
+        let path = path.as_ref();
+        match Format::from_path(path) {
+            Some(format) => Ok(DataFile::from_reader(
+                format,
+                tokio::fs::File::from_std(std::fs::File::open(path)?),
+            )),
+            None => Err(DataFileError::UnknownExtension(path.to_path_buf())),
+        }
+    }
+
+    /// Compresses the content with gzip while uploading. The content is sent as a file with a
+    /// `.gz` extension in a multipart request, without a `Content-Encoding` header, so this
+    /// relies on the graph store decompressing files by their extension, as Fuseki does.
+    pub fn gzip(self) -> Self {
+        let mut upload = self.into_upload();
+        upload.gzip = true;
+        DataFile::Upload(upload)
+    }
+
+    /// Reports the total number of content bytes read so far while uploading.
+    pub fn on_progress<F>(self, callback: F) -> Self
+    where
+        F: Fn(u64) + Send + Sync + 'static,
+    {
+        let mut upload = self.into_upload();
+        upload.progress = Some(Arc::new(callback));
+        DataFile::Upload(upload)
+    }
+
+    pub fn format(&self) -> Format {
+        match self {
+            DataFile::Turtle { .. } => Format::Turtle,
+            DataFile::NTriples { .. } => Format::NTriples,
+            DataFile::RdfXml { .. } => Format::RdfXml,
+            DataFile::JsonLd { .. } => Format::JsonLd,
+            DataFile::Upload(upload) => upload.format,
+        }
+    }
+
+    fn into_upload(self) -> Upload {
+        let format = self.format();
+        let content = match self {
+            DataFile::Turtle { content }
+            | DataFile::NTriples { content }
+            | DataFile::RdfXml { content }
+            | DataFile::JsonLd { content } => Content::Bytes(content),
+            DataFile::Upload(upload) => return upload,
+        };
+        Upload {
+            format,
+            content,
+            gzip: false,
+            progress: None,
         }
     }
 }
 
-/// A query to select data.
+/// The RDF dataset of a query: the graphs merged into its default graph and the named graphs it
+/// can match. Without graphs, the query runs against the whole graph store. Graphs given in the
+/// query text with `FROM` take precedence.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct QueryDataset {
+    pub default_graphs: Vec<Graph>,
+    pub named_graphs: Vec<Resource>,
+}
+
+/// A query to select data. The built-in selections use prefixes of the default `PrefixMap`.
 pub struct Selection {
     sparql_value: String,
+    dataset: QueryDataset,
+    timeout: Option<Duration>,
 }
 
 impl Selection {
@@ -52,43 +393,70 @@ impl Selection {
         Selection::unsafe_from("SELECT DISTINCT ?graph WHERE { GRAPH ?graph { ?s ?p ?o } }")
     }
 
-    pub fn of_resources_from_named_graphs() -> Selection {
-        Selection::unsafe_from("SELECT ?s WHERE { GRAPH ?g { ?s ?p ?o } }")
+    /// Counting triples, distinct subjects and distinct objects per graph, where the default
+    /// graph leaves `?graph` unbound.
+    pub fn of_triple_counts() -> Selection {
+        Selection::unsafe_from(
+            "SELECT ?graph (COUNT(*) AS ?triples) (COUNT(DISTINCT ?s) AS ?subjects)
+  (COUNT(DISTINCT ?o) AS ?objects)
+WHERE { { ?s ?p ?o } UNION { GRAPH ?graph { ?s ?p ?o } } }
+GROUP BY ?graph",
+        )
     }
 
-    pub fn of_relations_from(resource: &Resource) -> Selection {
-        Selection::unsafe_from(&format!(
-            "PREFIX rdfs: <http://www.w3.org/2000/01/rdf-schema#>
+    /// Counting instances per class and graph.
+    pub fn of_class_counts() -> Selection {
+        Selection::unsafe_from(
+            "SELECT ?graph ?class (COUNT(DISTINCT ?s) AS ?entities)
+WHERE { { ?s a ?class } UNION { GRAPH ?graph { ?s a ?class } } }
+GROUP BY ?graph ?class",
+        )
+    }
 
-SELECT ?predicate ?predicate_label ?object ?object_label
-WHERE {{
-  GRAPH ?g1 {{ <{}> ?predicate ?object }}
-  OPTIONAL {{ GRAPH ?g2 {{ ?predicate rdfs:label ?predicate_label }} }}
-  OPTIONAL {{ GRAPH ?g3 {{ ?object rdfs:label ?object_label }} }}
-}}",
-            resource.0
-        ))
+    /// Counting triples per property and graph.
+    pub fn of_property_counts() -> Selection {
+        Selection::unsafe_from(
+            "SELECT ?graph ?property (COUNT(*) AS ?triples)
+WHERE { { ?s ?property ?o } UNION { GRAPH ?graph { ?s ?property ?o } } }
+GROUP BY ?graph ?property",
+        )
     }
 
-    pub fn of_relations_to(resource: &Resource) -> Selection {
-        Selection::unsafe_from(&format!(
-            "PREFIX rdfs: <http://www.w3.org/2000/01/rdf-schema#>
+    pub fn of_resources_from_named_graphs() -> Selection {
+        Selection::unsafe_from("SELECT ?s WHERE { GRAPH ?g { ?s ?p ?o } }")
+    }
 
-SELECT ?subject ?subject_label ?predicate ?predicate_label
-WHERE {{
-  GRAPH ?g1 {{ ?subject ?predicate <{}> }}
-  OPTIONAL {{ GRAPH ?g2 {{ ?predicate rdfs:label ?predicate_label }} }}
-  OPTIONAL {{ GRAPH ?g3 {{ ?subject rdfs:label ?subject_label }} }}
-}}",
-            resource.0
-        ))
+    /// Relations from a resource to objects, or an error if the resource is not a valid IRI.
+    pub fn of_relations_from(resource: &Resource) -> Result<Selection, ParameterError> {
+        ParameterizedQuery::new(
+            "SELECT ?predicate ?predicate_label ?object ?object_label
+WHERE {
+  GRAPH ?g1 { ?resource ?predicate ?object }
+  OPTIONAL { GRAPH ?g2 { ?predicate rdfs:label ?predicate_label } }
+  OPTIONAL { GRAPH ?g3 { ?object rdfs:label ?object_label } }
+}",
+        )
+        .bind("resource", resource)
+        .selection()
+    }
+
+    /// Relations from subjects to a resource, or an error if the resource is not a valid IRI.
+    pub fn of_relations_to(resource: &Resource) -> Result<Selection, ParameterError> {
+        ParameterizedQuery::new(
+            "SELECT ?subject ?subject_label ?predicate ?predicate_label
+WHERE {
+  GRAPH ?g1 { ?subject ?predicate ?resource }
+  OPTIONAL { GRAPH ?g2 { ?predicate rdfs:label ?predicate_label } }
+  OPTIONAL { GRAPH ?g3 { ?subject rdfs:label ?subject_label } }
+}",
+        )
+        .bind("resource", resource)
+        .selection()
     }
 
     pub fn of_resources_with_labels() -> Selection {
         Selection::unsafe_from(
-            "PREFIX rdfs: <http://www.w3.org/2000/01/rdf-schema#>
-
-SELECT DISTINCT ?resource ?label
+            "SELECT DISTINCT ?resource ?label
 WHERE {
   GRAPH ?graph1 {
     { ?resource ?predicate ?object }
@@ -108,51 +476,244 @@ WHERE {
     pub fn unsafe_from(value: &str) -> Selection {
         Selection {
             sparql_value: value.to_string(),
+            dataset: QueryDataset::default(),
+            timeout: None,
+        }
+    }
+
+    /// Parses a SELECT query.
+    pub fn parse(value: &str) -> Result<Selection, sparql_ast::SyntaxError> {
+        let query = sparql_ast::parse_query(value)?;
+        Selection::from_query(&query).ok_or_else(|| sparql_ast::SyntaxError {
+            line: 1,
+            column: 1,
+            message: "Expected a SELECT query".to_string(),
+        })
+    }
+
+    /// Writes a SELECT query, or returns `None` for other forms.
+    pub fn from_query(query: &sparql_ast::Query) -> Option<Selection> {
+        match query.form {
+            sparql_ast::QueryForm::Select(_) => Some(Selection::unsafe_from(&query.to_string())),
+            _ => None,
+        }
+    }
+
+    /// Parses the query, for example to find its projected variables.
+    pub fn query(&self) -> Result<sparql_ast::Query, sparql_ast::SyntaxError> {
+        sparql_ast::parse_query(&self.sparql_value)
+    }
+
+    /// Rewrites the query, keeping its dataset and timeout.
+    ///
+    /// ```ignore
+    /// let page = Selection::of_graphs().rewrite(|query| query.with_limit(10).with_offset(20))?;
+    /// ```
+    pub fn rewrite<F>(self, rewrite: F) -> Result<Selection, sparql_ast::SyntaxError>
+    where
+        F: FnOnce(sparql_ast::Query) -> sparql_ast::Query,
+    {
+        Ok(Selection {
+            sparql_value: rewrite(self.query()?).to_string(),
+            ..self
+        })
+    }
+}
+
+/// A query to test whether a pattern has a solution.
+pub struct AskQuery {
+    sparql_value: String,
+    dataset: QueryDataset,
+    timeout: Option<Duration>,
+}
+
+impl AskQuery {
+    pub fn unsafe_from(value: &str) -> AskQuery {
+        AskQuery {
+            sparql_value: value.to_string(),
+            dataset: QueryDataset::default(),
+            timeout: None,
         }
     }
 }
 
 pub struct DescribeQuery {
     sparql_value: String,
+    dataset: QueryDataset,
+    timeout: Option<Duration>,
 }
 
 impl DescribeQuery {
     pub fn unsafe_from(value: &str) -> DescribeQuery {
         DescribeQuery {
             sparql_value: value.to_string(),
+            dataset: QueryDataset::default(),
+            timeout: None,
+        }
+    }
+}
+
+/// A query to construct a graph from a template.
+pub struct ConstructQuery {
+    sparql_value: String,
+    dataset: QueryDataset,
+    timeout: Option<Duration>,
+}
+
+impl ConstructQuery {
+    pub fn unsafe_from(value: &str) -> ConstructQuery {
+        ConstructQuery {
+            sparql_value: value.to_string(),
+            dataset: QueryDataset::default(),
+            timeout: None,
         }
     }
 }
 
+/// Adds the methods that set the dataset and the timeout of a query type.
+macro_rules! query_options {
+    ($($query:ident),*) => {
+        $(
+            impl $query {
+                /// Adds a graph to the default graph of the query, as with `FROM`.
+                pub fn from_graph(mut self, graph: Graph) -> $query {
+                    self.dataset.default_graphs.push(graph);
+                    self
+                }
+
+                /// Makes a graph available to `GRAPH` patterns, as with `FROM NAMED`.
+                pub fn from_named_graph(mut self, graph: Resource) -> $query {
+                    self.dataset.named_graphs.push(graph);
+                    self
+                }
+
+                /// Limits the time the query may take, overriding the timeout of the graph
+                /// store.
+                pub fn with_timeout(mut self, timeout: Duration) -> $query {
+                    self.timeout = Some(timeout);
+                    self
+                }
+            }
+        )*
+    };
+}
+
+query_options!(Selection, AskQuery, DescribeQuery, ConstructQuery);
+
 /// A collection of RDF graphs.
 #[async_trait]
-pub trait GraphStore {
+pub trait GraphStore: Send + Sync {
     /// Imports a file into a dataset.
-    async fn import(&self, graph: Graph, file: DataFile);
+    async fn import(&self, graph: Graph, file: DataFile) -> Result<(), Error>;
 
     /// Performs a SPARQL query.
-    async fn select(&self, query: Selection) -> Table<Node>;
-
-    async fn describe(&self, query: DescribeQuery) -> rdf::graph::Graph;
-
-    async fn describe_everything(&self) -> rdf::graph::Graph {
-        let graphs = self.select(Selection::of_graphs()).await;
-        let from: String = graphs
-            .bindings
-            .iter()
-            .map(|b| {
-                format!(
-                    "FROM <{}>",
-                    match b.get(&Variable::from("graph")) {
-                        Some(rdf::node::Node::UriNode { uri: id }) => id.to_string(),
-                        _ => panic!("Unexpected node"),
-                    }
-                )
-            })
-            .collect::<Vec<_>>()
-            .join(" ");
-        let query =
-            DescribeQuery::unsafe_from(&format!("DESCRIBE ?x {} WHERE {{ ?x ?y ?z }}", from));
+    async fn select(&self, query: Selection) -> Result<Table<Node>, Error>;
+
+    async fn ask(&self, query: AskQuery) -> Result<bool, Error>;
+
+    async fn describe(&self, query: DescribeQuery) -> Result<rdf::graph::Graph, Error>;
+
+    async fn construct(&self, query: ConstructQuery) -> Result<rdf::graph::Graph, Error>;
+
+    /// Retrieves the content of a graph.
+    async fn get(&self, graph: Graph) -> Result<rdf::graph::Graph, Error>;
+
+    /// Describes resources as JSON-LD, compacted if a context is given. By default, the graphs
+    /// of these JSON-LD methods are converted locally, with the limits described in `json_ld`.
+    async fn describe_json_ld(
+        &self,
+        query: DescribeQuery,
+        context: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, Error> {
+        Ok(json_ld::from_graph(&self.describe(query).await?, context))
+    }
+
+    /// Constructs a graph as JSON-LD, compacted if a context is given.
+    async fn construct_json_ld(
+        &self,
+        query: ConstructQuery,
+        context: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, Error> {
+        Ok(json_ld::from_graph(&self.construct(query).await?, context))
+    }
+
+    /// Retrieves the content of a graph as JSON-LD, compacted if a context is given.
+    async fn get_json_ld(
+        &self,
+        graph: Graph,
+        context: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, Error> {
+        Ok(json_ld::from_graph(&self.get(graph).await?, context))
+    }
+
+    /// Describes the resources in all named graphs. See `http::Dataset::dump` to export the
+    /// default graph and graph membership as well.
+    async fn describe_everything(&self) -> Result<rdf::graph::Graph, Error> {
+        let graphs = self.select(Selection::of_graphs()).await?;
+        let query = graphs.bindings.iter().try_fold(
+            DescribeQuery::unsafe_from("DESCRIBE ?x WHERE { ?x ?y ?z }"),
+            |query, b| match b.get(&Variable::from("graph")) {
+                Some(rdf::node::Node::UriNode { uri: id }) => {
+                    Ok(query.from_graph(Graph::Named(Resource::unsafe_from(id.to_string()))))
+                }
+                node => Err(Error::Response(format!("Unexpected graph {:?}", node))),
+            },
+        )?;
         self.describe(query).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{sparql_ast, DataFile, DataFileError, Format, Selection};
+    use std::time::Duration;
+
+    #[test]
+    fn checks_data_files() {
+        let turtle = DataFile::from_turtle("<urn:example:a> <urn:example:b> \"c\" .").unwrap();
+        let json_ld = DataFile::from_json_ld(r#"{ "@id": "urn:example:a" }"#).unwrap();
+
+        assert_eq!(turtle.format(), Format::Turtle);
+        assert_eq!(json_ld.format(), Format::JsonLd);
+        assert!(matches!(
+            DataFile::from_turtle("<urn:example:a> <urn:example:b> \"c\""),
+            Err(DataFileError::Syntax(_))
+        ));
+        assert!(matches!(
+            DataFile::from_n_triples("<urn:example:a> <urn:example:b> c ."),
+            Err(DataFileError::Syntax(_))
+        ));
+        assert!(DataFile::from_json_ld("\"urn:example:a\"").is_err());
+        assert!(matches!(
+            DataFile::from_path("data.csv"),
+            Err(DataFileError::UnknownExtension(_))
+        ));
+    }
+
+    #[test]
+    fn rewrites_selections() {
+        let selection = Selection::parse("SELECT ?s WHERE { ?s ?p ?o }")
+            .unwrap()
+            .with_timeout(Duration::from_secs(1))
+            .rewrite(|query| {
+                query
+                    .with_limit(10)
+                    .in_graph(sparql_ast::VarOrIri::Variable("g".to_string()))
+            })
+            .unwrap();
+
+        assert_eq!(
+            selection.sparql_value,
+            "SELECT ?s
+WHERE {
+  GRAPH ?g {
+    ?s ?p ?o .
+  }
+}
+LIMIT 10"
+        );
+        assert_eq!(selection.timeout, Some(Duration::from_secs(1)));
+        assert_eq!(selection.query().unwrap().projected_variables(), vec!["s"]);
+        assert!(Selection::parse("ASK { ?s ?p ?o }").is_err());
+    }
+}
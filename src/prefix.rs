@@ -0,0 +1,226 @@
+use crate::iri::{self, IriError};
+use crate::query::{iri_length, string_length};
+use crate::syntax::{is_pn_chars, is_pn_chars_base};
+use crate::writer::is_local_name;
+use crate::Resource;
+use rdf::namespace::Namespace;
+use rdf::uri::Uri;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Prefixes for namespaces, declared in queries as needed and used to abbreviate IRIs.
+///
+/// The default map contains the rdf, rdfs, owl, xsd, skos and dcterms vocabularies.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PrefixMap {
+    prefixes: BTreeMap<String, String>,
+}
+
+const DEFAULT_PREFIXES: [(&str, &str); 6] = [
+    ("rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#"),
+    ("rdfs", "http://www.w3.org/2000/01/rdf-schema#"),
+    ("owl", "http://www.w3.org/2002/07/owl#"),
+    ("xsd", "http://www.w3.org/2001/XMLSchema#"),
+    ("skos", "http://www.w3.org/2004/02/skos/core#"),
+    ("dcterms", "http://purl.org/dc/terms/"),
+];
+
+impl Default for PrefixMap {
+    fn default() -> Self {
+        PrefixMap {
+            prefixes: DEFAULT_PREFIXES
+                .iter()
+                .map(|(prefix, namespace)| (prefix.to_string(), namespace.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl PrefixMap {
+    pub fn empty() -> PrefixMap {
+        PrefixMap {
+            prefixes: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a prefix, replacing an existing one with the same name, or returns an error if the
+    /// namespace is not an absolute IRI. This keeps declarations from ending the IRI early,
+    /// including through escape sequences, which servers decode before parsing a query.
+    pub fn insert(&mut self, prefix: &str, namespace: &str) -> Result<(), IriError> {
+        iri::parse(namespace)?;
+        self.prefixes
+            .insert(prefix.to_string(), namespace.to_string());
+        Ok(())
+    }
+
+    pub fn with(mut self, prefix: &str, namespace: &str) -> Result<PrefixMap, IriError> {
+        self.insert(prefix, namespace)?;
+        Ok(self)
+    }
+
+    pub fn namespace(&self, prefix: &str) -> Option<&str> {
+        self.prefixes.get(prefix).map(String::as_str)
+    }
+
+    /// Expands a prefixed name such as `rdfs:label`, if the prefix is known and the result is a
+    /// valid IRI.
+    pub fn expand(&self, curie: &str) -> Option<Resource> {
+        let i = curie.find(':')?;
+        let namespace = self.namespace(&curie[..i])?;
+        Resource::parse(&format!("{}{}", namespace, &curie[i + 1..])).ok()
+    }
+
+    /// Abbreviates an IRI with the longest matching namespace, if the rest is a valid local
+    /// name.
+    pub fn compact(&self, iri: &str) -> Option<String> {
+        self.prefixes
+            .iter()
+            .filter(|(_, namespace)| iri.starts_with(namespace.as_str()))
+            .map(|(prefix, namespace)| (prefix, &iri[namespace.len()..]))
+            .filter(|(_, local)| is_local_name(local))
+            .min_by_key(|(_, local)| local.len())
+            .map(|(prefix, local)| format!("{}:{}", prefix, local))
+    }
+
+    /// Prepends declarations for the prefixes that a query uses without declaring them.
+    /// Prefixes that are not in the map are left for the server to report.
+    pub fn declare(&self, query: &str) -> String {
+        let (used, declared) = scan_prefixes(query);
+        let mut output = String::new();
+        for prefix in used.difference(&declared) {
+            if let Some(namespace) = self.namespace(prefix) {
+                output.push_str(&format!("PREFIX {}: <{}>\n", prefix, namespace));
+            }
+        }
+        if output.is_empty() {
+            return query.to_string();
+        }
+        output.push('\n');
+        output.push_str(query);
+        output
+    }
+
+    /// Adds the prefixes to a graph, keeping those it already has, so that they are used when it
+    /// is written as Turtle.
+    pub fn add_to(&self, graph: &mut rdf::graph::Graph) {
+        for (prefix, namespace) in &self.prefixes {
+            if !graph.namespaces().contains_key(prefix) {
+                graph.add_namespace(&Namespace::new(
+                    prefix.to_string(),
+                    Uri::new(namespace.to_string()),
+                ));
+            }
+        }
+    }
+
+    pub(crate) fn pairs(&self) -> Vec<(String, String)> {
+        self.prefixes
+            .iter()
+            .map(|(prefix, namespace)| (prefix.to_string(), namespace.to_string()))
+            .collect()
+    }
+}
+
+/// Finds the prefixes used in prefixed names of a query and those declared by it.
+fn scan_prefixes(query: &str) -> (BTreeSet<String>, BTreeSet<String>) {
+    let mut used = BTreeSet::new();
+    let mut declared = BTreeSet::new();
+    let mut declaration = false;
+    let mut rest = query;
+    while let Some(c) = rest.chars().next() {
+        let skip = match c {
+            '#' => rest.find('\n').unwrap_or(rest.len()),
+            '"' | '\'' => string_length(rest),
+            '<' => iri_length(rest).unwrap_or(1),
+            '?' | '$' | '@' | '_' => rest[1..]
+                .find(|c: char| !(is_pn_chars(c) || c == ':'))
+                .map_or(rest.len(), |i| i + 1),
+            c if c == ':' || is_pn_chars_base(c) => {
+                let word = rest
+                    .find(|c: char| !(is_pn_chars(c) || c == '.'))
+                    .unwrap_or(rest.len());
+                if rest[word..].starts_with(':') {
+                    let prefix = rest[..word].to_string();
+                    if declaration {
+                        declared.insert(prefix);
+                    } else {
+                        used.insert(prefix);
+                    }
+                    declaration = false;
+                    let local = rest[word + 1..]
+                        .find(|c: char| !(is_pn_chars(c) || ".:%\\".contains(c)))
+                        .unwrap_or(rest.len() - word - 1);
+                    word + 1 + local
+                } else {
+                    declaration = rest[..word].eq_ignore_ascii_case("PREFIX");
+                    word
+                }
+            }
+            c => c.len_utf8(),
+        };
+        rest = &rest[skip..];
+    }
+    (used, declared)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prefix::PrefixMap;
+    use crate::Resource;
+
+    #[test]
+    fn declares_used_prefixes() {
+        let prefixes = PrefixMap::default()
+            .with("ex", "http://example.org/")
+            .unwrap();
+        let query = "PREFIX ex: <http://example.org/other/>
+SELECT ?s # skos:note
+WHERE { ?s rdfs:label \"owl:Thing\" ; ex:p:q <http://example.org/xsd:x> ; a unknown:Type }";
+
+        assert_eq!(
+            prefixes.declare(query),
+            format!(
+                "PREFIX rdfs: <http://www.w3.org/2000/01/rdf-schema#>\n\n{}",
+                query
+            )
+        );
+        assert_eq!(prefixes.declare("ASK { ?s ?p ?o }"), "ASK { ?s ?p ?o }");
+    }
+
+    #[test]
+    fn rejects_namespaces_that_are_not_iris() {
+        let mut prefixes = PrefixMap::empty();
+
+        for namespace in &[
+            "http://example.org/> } DROP ALL #",
+            "http://example.org/\\u003E\\u0020\\u007D\\u0020DROP\\u0020ALL",
+            "http://example.org/{x}",
+            "example/",
+        ] {
+            assert!(prefixes.insert("ex", namespace).is_err(), "{}", namespace);
+        }
+        assert!(PrefixMap::empty()
+            .with("ex", "http://example.org/a b#")
+            .is_err());
+        assert_eq!(prefixes, PrefixMap::empty());
+    }
+
+    #[test]
+    fn expands_and_compacts() {
+        let prefixes = PrefixMap::default()
+            .with("ex", "http://example.org/")
+            .unwrap();
+
+        assert_eq!(
+            prefixes.expand("skos:prefLabel"),
+            Some(Resource::unsafe_from(
+                "http://www.w3.org/2004/02/skos/core#prefLabel"
+            ))
+        );
+        assert_eq!(prefixes.expand("unknown:a"), None);
+        assert_eq!(
+            prefixes.compact("http://purl.org/dc/terms/title"),
+            Some("dcterms:title".to_string())
+        );
+        assert_eq!(prefixes.compact("http://example.org/a/b"), None);
+    }
+}
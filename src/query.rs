@@ -0,0 +1,433 @@
+use crate::iri::{self, IriError};
+use crate::sparql_ast::{self, Iri, Literal};
+use crate::syntax::is_pn_chars;
+use crate::writer;
+use crate::{AskQuery, ConstructQuery, DescribeQuery, Resource, Selection};
+use std::collections::HashMap;
+use std::fmt;
+
+const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+/// A typed value that can be bound to a query parameter.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Value {
+    Resource(Resource),
+    Literal {
+        lexical: String,
+        datatype: Option<Resource>,
+        language: Option<String>,
+    },
+    BlankNode(String),
+}
+
+impl Value {
+    /// A plain string literal.
+    pub fn string(lexical: &str) -> Value {
+        Value::Literal {
+            lexical: lexical.to_string(),
+            datatype: None,
+            language: None,
+        }
+    }
+
+    pub fn typed(lexical: &str, datatype: Resource) -> Value {
+        Value::Literal {
+            lexical: lexical.to_string(),
+            datatype: Some(datatype),
+            language: None,
+        }
+    }
+
+    pub fn language_tagged(lexical: &str, language: &str) -> Value {
+        Value::Literal {
+            lexical: lexical.to_string(),
+            datatype: None,
+            language: Some(language.to_string()),
+        }
+    }
+
+    pub fn blank_node(label: &str) -> Value {
+        Value::BlankNode(label.to_string())
+    }
+
+    /// Checks that the resource or the datatype is an absolute IRI.
+    pub fn check(&self) -> Result<(), IriError> {
+        match self {
+            Value::Resource(resource)
+            | Value::Literal {
+                datatype: Some(resource),
+                ..
+            } => iri::parse(&resource.0).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes the value as a SPARQL term, or returns an error if it has an invalid IRI. Strings
+    /// are escaped, language tags are reduced to the characters their syntax allows and blank
+    /// node labels are escaped like in `writer`, so that the term cannot change the query
+    /// structure.
+    pub fn to_sparql(&self) -> Result<String, IriError> {
+        self.check()?;
+        Ok(self.write())
+    }
+
+    /// Writes a value that has been checked.
+    pub(crate) fn write(&self) -> String {
+        self.term().to_string()
+    }
+
+    /// The value as a term of the query syntax tree, with a language tag and a blank node label
+    /// that cannot change the query structure.
+    pub(crate) fn term(&self) -> sparql_ast::Term {
+        match self {
+            Value::Resource(resource) => sparql_ast::Term::Iri(Iri::Full(resource.0.clone())),
+            Value::Literal {
+                lexical,
+                datatype,
+                language,
+            } => sparql_ast::Term::Literal(Literal::String {
+                value: lexical.clone(),
+                language: language.as_deref().map(language_tag),
+                datatype: match language {
+                    Some(_) => None,
+                    None => datatype.as_ref().map(|d| Iri::Full(d.0.clone())),
+                },
+            }),
+            Value::BlankNode(label) => {
+                sparql_ast::Term::BlankNode(writer::blank_node(label)[2..].to_string())
+            }
+        }
+    }
+}
+
+impl From<Resource> for Value {
+    fn from(resource: Resource) -> Self {
+        Value::Resource(resource)
+    }
+}
+
+impl From<&Resource> for Value {
+    fn from(resource: &Resource) -> Self {
+        Value::Resource(resource.clone())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::typed(&n.to_string(), Resource(format!("{}integer", XSD)))
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        let lexical = match n {
+            n if n.is_nan() => "NaN".to_string(),
+            n if n.is_infinite() && n > 0.0 => "INF".to_string(),
+            n if n.is_infinite() => "-INF".to_string(),
+            n => format!("{:e}", n),
+        };
+        Value::typed(&lexical, Resource(format!("{}double", XSD)))
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::typed(&b.to_string(), Resource(format!("{}boolean", XSD)))
+    }
+}
+
+fn language_tag(value: &str) -> String {
+    let tag: String = value
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .collect();
+    let tag = tag.trim_matches('-');
+    match tag.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => tag.to_string(),
+        _ => "und".to_string(),
+    }
+}
+
+/// A binding that cannot be substituted into a parameterized query.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParameterError {
+    pub name: String,
+    pub message: String,
+}
+
+impl fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cannot bind ?{}: {}", self.name, self.message)
+    }
+}
+
+impl std::error::Error for ParameterError {}
+
+/// A query with named parameters, written as variables (`?name` or `$name`) and replaced by
+/// escaped values. Variables within IRIs, strings and comments are left alone, as are variables
+/// without a bound value.
+///
+/// Parameters can only stand for terms in patterns and expressions. A bound variable that the
+/// query projects, groups or orders by, or introduces with `AS` or `VALUES`, is an error.
+#[derive(Debug, Clone)]
+pub struct ParameterizedQuery {
+    text: String,
+    bindings: HashMap<String, Value>,
+}
+
+/// Where a variable in the query text occurs, as far as substitution is concerned.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Clause {
+    /// A pattern or an expression, where a value can replace the variable.
+    Term,
+    /// The variables of a `SELECT` at this depth of parentheses.
+    Projection(usize),
+    /// The conditions of a `GROUP BY` or `ORDER BY` at this depth of parentheses.
+    Modifier(usize),
+    /// The variables of a `VALUES` block, up to its data.
+    Values,
+}
+
+impl ParameterizedQuery {
+    pub fn new(text: &str) -> ParameterizedQuery {
+        ParameterizedQuery {
+            text: text.to_string(),
+            bindings: HashMap::new(),
+        }
+    }
+
+    /// Binds a value to every occurrence of the parameter.
+    pub fn bind<V: Into<Value>>(mut self, name: &str, value: V) -> ParameterizedQuery {
+        self.bindings.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// Writes the query with all bound parameters replaced, or returns an error if a value has
+    /// an invalid IRI or a bound variable occurs where no value can replace it.
+    pub fn to_sparql(&self) -> Result<String, ParameterError> {
+        for (name, value) in &self.bindings {
+            value.check().map_err(|e| ParameterError {
+                name: name.to_string(),
+                message: e.to_string(),
+            })?;
+        }
+        let text = &self.text;
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text.as_str();
+        let mut depth: usize = 0;
+        let mut clause = Clause::Term;
+        let mut alias = false;
+        while let Some(c) = rest.chars().next() {
+            let skip = match c {
+                '#' => rest.find('\n').unwrap_or(rest.len()),
+                '"' | '\'' => string_length(rest),
+                '<' => iri_length(rest).unwrap_or(1),
+                '(' => {
+                    depth += 1;
+                    1
+                }
+                ')' => {
+                    depth = depth.saturating_sub(1);
+                    1
+                }
+                '{' | '}' => {
+                    clause = Clause::Term;
+                    1
+                }
+                c if c.is_alphabetic() || c == '_' || c == ':' => {
+                    let length: usize = rest
+                        .chars()
+                        .take_while(|c| c.is_alphanumeric() || ['_', ':', '-', '.'].contains(c))
+                        .map(char::len_utf8)
+                        .sum();
+                    let word = &rest[..length];
+                    let keyword = |k: &str| word.eq_ignore_ascii_case(k);
+                    if keyword("SELECT") {
+                        clause = Clause::Projection(depth);
+                    } else if keyword("BY") {
+                        clause = Clause::Modifier(depth);
+                    } else if keyword("VALUES") {
+                        clause = Clause::Values;
+                    } else if keyword("AS") {
+                        alias = true;
+                    } else if ["WHERE", "FROM", "HAVING", "LIMIT", "OFFSET"]
+                        .iter()
+                        .any(|k| keyword(k))
+                    {
+                        clause = Clause::Term;
+                    }
+                    length
+                }
+                '?' | '$' => {
+                    let length: usize = rest[1..]
+                        .chars()
+                        .take_while(|c| is_pn_chars(*c) && *c != '-')
+                        .map(char::len_utf8)
+                        .sum();
+                    let name = &rest[1..1 + length];
+                    let substitutable = !alias
+                        && match clause {
+                            Clause::Term => true,
+                            Clause::Projection(d) | Clause::Modifier(d) => depth > d,
+                            Clause::Values => false,
+                        };
+                    alias = false;
+                    match self.bindings.get(name) {
+                        Some(value) if length > 0 && substitutable => {
+                            output.push_str(&value.write());
+                            rest = &rest[1 + length..];
+                            continue;
+                        }
+                        Some(_) if length > 0 => {
+                            return Err(ParameterError {
+                                name: name.to_string(),
+                                message: "The variable is not a term in a pattern or an \
+                                          expression"
+                                    .to_string(),
+                            })
+                        }
+                        _ => 1 + length,
+                    }
+                }
+                c => c.len_utf8(),
+            };
+            output.push_str(&rest[..skip]);
+            rest = &rest[skip..];
+        }
+        Ok(output)
+    }
+
+    pub fn selection(&self) -> Result<Selection, ParameterError> {
+        Ok(Selection::unsafe_from(&self.to_sparql()?))
+    }
+
+    pub fn ask_query(&self) -> Result<AskQuery, ParameterError> {
+        Ok(AskQuery::unsafe_from(&self.to_sparql()?))
+    }
+
+    pub fn describe_query(&self) -> Result<DescribeQuery, ParameterError> {
+        Ok(DescribeQuery::unsafe_from(&self.to_sparql()?))
+    }
+
+    pub fn construct_query(&self) -> Result<ConstructQuery, ParameterError> {
+        Ok(ConstructQuery::unsafe_from(&self.to_sparql()?))
+    }
+}
+
+/// Returns the length of the string literal at the start of the text, including quotes, or the
+/// rest of the text if it is unterminated.
+pub(crate) fn string_length(text: &str) -> usize {
+    let quote = &text[..1];
+    let delimiter = if text[1..].starts_with(&quote.repeat(2)) {
+        quote.repeat(3)
+    } else {
+        quote.to_string()
+    };
+    let mut i = delimiter.len();
+    while i < text.len() {
+        if text[i..].starts_with('\\') {
+            i += 1 + text[i + 1..].chars().next().map_or(0, char::len_utf8);
+        } else if text[i..].starts_with(delimiter.as_str()) {
+            return i + delimiter.len();
+        } else {
+            i += text[i..].chars().next().unwrap().len_utf8();
+        }
+    }
+    text.len()
+}
+
+/// Returns the length of the IRI reference at the start of the text, if it is not a comparison
+/// operator.
+pub(crate) fn iri_length(text: &str) -> Option<usize> {
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            '>' => return Some(i + 1),
+            '\u{0}'..=' ' | '<' | '"' | '{' | '}' | '|' | '^' | '`' => return None,
+            _ => (),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::{ParameterizedQuery, Value};
+    use crate::Resource;
+
+    #[test]
+    fn binds_parameters_outside_strings_iris_and_comments() {
+        let query = ParameterizedQuery::new(
+            "SELECT ?o # about ?s
+WHERE { ?s <http://example.org/?s> ?o . FILTER(?o != \"?s\" && ?n < $s) }",
+        )
+        .bind("s", Resource::parse("http://example.org/a").unwrap());
+
+        assert_eq!(
+            query.to_sparql().unwrap(),
+            "SELECT ?o # about ?s
+WHERE { <http://example.org/a> <http://example.org/?s> ?o . \
+FILTER(?o != \"?s\" && ?n < <http://example.org/a>) }"
+        );
+    }
+
+    #[test]
+    fn escapes_injected_values() {
+        let query = ParameterizedQuery::new("SELECT * WHERE { ?s ?p ?o }")
+            .bind("p", Value::language_tagged("x", "en} ?y"))
+            .bind("o", Value::string("\" } DROP ALL #\\"));
+        let invalid = ParameterizedQuery::new("SELECT * WHERE { ?s ?p ?o }").bind(
+            "s",
+            Resource::unsafe_from("http://example.org/> ?x } DROP ALL #"),
+        );
+        let invalid_datatype = ParameterizedQuery::new("ASK { ?s ?p ?o }").bind(
+            "o",
+            Value::typed("1", Resource::unsafe_from("http://example.org/{x}")),
+        );
+
+        assert_eq!(
+            query.to_sparql().unwrap(),
+            "SELECT * WHERE { ?s \"x\"@eny \"\\\" } DROP ALL #\\\\\" }"
+        );
+        assert_eq!(invalid.to_sparql().unwrap_err().name, "s");
+        assert_eq!(invalid_datatype.to_sparql().unwrap_err().name, "o");
+    }
+
+    #[test]
+    fn binds_parameters_only_in_patterns_and_expressions() {
+        let a = || Resource::parse("http://example.org/a").unwrap();
+        let bind = |text: &str| ParameterizedQuery::new(text).bind("s", a()).to_sparql();
+
+        assert_eq!(
+            bind("SELECT ?o (COUNT(?s) AS ?n) WHERE { ?s ?p ?o } GROUP BY ?o").unwrap(),
+            "SELECT ?o (COUNT(<http://example.org/a>) AS ?n) \
+WHERE { <http://example.org/a> ?p ?o } GROUP BY ?o"
+        );
+        assert_eq!(
+            bind("DESCRIBE ?s").unwrap(),
+            "DESCRIBE <http://example.org/a>"
+        );
+        assert_eq!(
+            bind("SELECT ?o WHERE { ?x as:s ?o } ORDER BY DESC(?s)").unwrap(),
+            "SELECT ?o WHERE { ?x as:s ?o } ORDER BY DESC(<http://example.org/a>)"
+        );
+        assert!(bind("SELECT ?s WHERE { ?s ?p ?o }").is_err());
+        assert!(bind("SELECT (?o AS ?s) WHERE { ?x ?p ?o }").is_err());
+        assert!(bind("SELECT * WHERE { ?x ?p ?o BIND (?o AS ?s) }").is_err());
+        assert!(bind("SELECT * WHERE { VALUES ?s { 1 } }").is_err());
+        assert!(bind("SELECT * WHERE { ?s ?p ?o } ORDER BY ?s").is_err());
+        assert!(bind("SELECT ?o WHERE { { SELECT ?s { ?s ?p ?o } } }").is_err());
+    }
+
+    #[test]
+    fn writes_typed_values() {
+        assert_eq!(
+            Value::from(42i64).to_sparql().unwrap(),
+            "\"42\"^^<http://www.w3.org/2001/XMLSchema#integer>"
+        );
+        assert_eq!(Value::blank_node("a b").to_sparql().unwrap(), "_:a_20_b");
+        assert_ne!(
+            Value::blank_node("a_b").to_sparql(),
+            Value::blank_node("a b").to_sparql()
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Retrying requests after transient failures.
+
+use crate::error::Error;
+use rand::Rng;
+use reqwest::StatusCode;
+use std::time::Duration;
+
+/// When and how often a request is sent again after a connection error or a response with a
+/// retriable status. Requests with a streamed body are never retried.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    statuses: Vec<StatusCode>,
+    non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    /// Three attempts, after waiting up to 100 ms and then up to 200 ms, on a connection error
+    /// or on Bad Gateway, Service Unavailable or Gateway Timeout.
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            statuses: vec![
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Sends each request once.
+    pub fn none() -> RetryPolicy {
+        RetryPolicy::default().with_max_attempts(1)
+    }
+
+    /// Limits the number of attempts, including the first.
+    pub fn with_max_attempts(mut self, attempts: u32) -> RetryPolicy {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Waits a random time up to a backoff that starts at `initial` and doubles after each
+    /// retry, up to `max`.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> RetryPolicy {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    pub fn with_retriable_statuses(mut self, statuses: Vec<StatusCode>) -> RetryPolicy {
+        self.statuses = statuses;
+        self
+    }
+
+    /// Also retries requests that may have effect more than once, such as updates and merges.
+    pub fn with_non_idempotent(mut self) -> RetryPolicy {
+        self.non_idempotent = true;
+        self
+    }
+
+    pub(crate) fn allows(&self, retries: u32, idempotent: bool) -> bool {
+        retries + 1 < self.max_attempts && (idempotent || self.non_idempotent)
+    }
+
+    pub(crate) fn is_retriable(&self, error: &Error) -> bool {
+        match error {
+            Error::Status { status, .. } => self.statuses.contains(status),
+            Error::Http(e) => e.is_connect(),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn backoff(&self, retries: u32) -> Duration {
+        let limit = 2u32
+            .checked_pow(retries)
+            .and_then(|factor| self.initial_backoff.checked_mul(factor))
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff));
+        limit.mul_f64(rand::thread_rng().gen::<f64>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::retry::RetryPolicy;
+    use std::time::Duration;
+
+    #[test]
+    fn backs_off_exponentially_with_jitter() {
+        let policy = RetryPolicy::default()
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(300));
+
+        for _ in 0..100 {
+            assert!(policy.backoff(0) <= Duration::from_millis(100));
+            assert!(policy.backoff(1) <= Duration::from_millis(200));
+            assert!(policy.backoff(40) <= Duration::from_millis(300));
+        }
+        assert!(policy.allows(1, true));
+        assert!(!policy.allows(2, true));
+        assert!(!policy.allows(0, false));
+        assert!(policy.with_non_idempotent().allows(0, false));
+        assert!(!RetryPolicy::none().allows(0, true));
+    }
+}
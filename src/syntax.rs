@@ -0,0 +1,803 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// A syntax error at a position in a document, both starting at 1.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.message, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+/// A grammar accepted by the checker.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Dialect {
+    Turtle,
+    NTriples,
+}
+
+/// Checks that the input conforms to the grammar, without building a graph.
+pub fn check(input: &str, dialect: Dialect) -> Result<(), SyntaxError> {
+    let mut parser = Parser {
+        lexer: Lexer::new(input),
+        dialect,
+        prefixes: HashSet::new(),
+        peeked: None,
+    };
+    parser.document()
+}
+
+/// Returns the literal values of a predicate in the statements about top-level subjects of a
+/// Turtle document, leaving out those in blank node property lists and collections. For
+/// example, these are the names of the services in a Fuseki configuration, and not those of
+/// their endpoints. Escape sequences are not expanded.
+pub(crate) fn top_level_values(input: &str, predicate: &str) -> Result<Vec<String>, SyntaxError> {
+    check(input, Dialect::Turtle)?;
+    let mut lexer = Lexer::new(input);
+    let mut namespaces = HashMap::new();
+    let mut values = vec![];
+    let mut depth = 0;
+    let mut verb = false;
+    loop {
+        let (token, text) = lexer.next_token_with_text()?;
+        match token {
+            Token::End => return Ok(values),
+            Token::PrefixDirective | Token::SparqlPrefix => {
+                let (_, prefix) = lexer.next_token_with_text()?;
+                let (_, namespace) = lexer.next_token_with_text()?;
+                namespaces.insert(prefix.trim_end_matches(':'), iri_ref_content(namespace));
+                continue;
+            }
+            Token::OpenBracket | Token::OpenParenthesis => depth += 1,
+            Token::CloseBracket | Token::CloseParenthesis => depth -= 1,
+            Token::Literal if verb && depth == 0 => values.push(string_content(text).to_string()),
+            _ => (),
+        }
+        verb = match token {
+            Token::IriRef => iri_ref_content(text) == predicate,
+            Token::PrefixedName { prefix } => match namespaces.get(prefix.as_str()) {
+                Some(namespace) => {
+                    let local = &text[prefix.len() + 1..];
+                    predicate.strip_prefix(namespace) == Some(local)
+                }
+                None => false,
+            },
+            _ => false,
+        };
+    }
+}
+
+fn iri_ref_content(text: &str) -> &str {
+    &text[1..text.len() - 1]
+}
+
+fn string_content(text: &str) -> &str {
+    let quote = &text[..1];
+    let quotes = if text.len() >= 6 && text.starts_with(&quote.repeat(3)) {
+        3
+    } else {
+        1
+    };
+    &text[quotes..text.len() - quotes]
+}
+
+/// Checks that the input is a JSON object or array, as JSON-LD documents are.
+pub fn check_json_ld(input: &str) -> Result<(), SyntaxError> {
+    match serde_json::from_str::<serde_json::Value>(input) {
+        Ok(serde_json::Value::Object(_)) | Ok(serde_json::Value::Array(_)) => Ok(()),
+        Ok(_) => Err(SyntaxError {
+            line: 1,
+            column: 1,
+            message: "Expected a JSON object or array".to_string(),
+        }),
+        Err(e) => Err(SyntaxError {
+            line: e.line(),
+            column: e.column(),
+            message: format!("Invalid JSON: {}", e),
+        }),
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    IriRef,
+    PrefixedName { prefix: String },
+    Namespace { prefix: String },
+    BlankNodeLabel,
+    LanguageTag,
+    Literal,
+    Number,
+    Boolean,
+    A,
+    PrefixDirective,
+    BaseDirective,
+    SparqlPrefix,
+    SparqlBase,
+    Dot,
+    Semicolon,
+    Comma,
+    OpenBracket,
+    CloseBracket,
+    OpenParenthesis,
+    CloseParenthesis,
+    DataType,
+    End,
+}
+
+struct Lexer<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    line: usize,
+    column: usize,
+}
+
+type Positioned = (Token, usize, usize);
+
+pub(crate) fn is_pn_chars_base(c: char) -> bool {
+    matches!(
+        c,
+        'A'..='Z'
+        | 'a'..='z'
+        | '\u{00C0}'..='\u{00D6}'
+        | '\u{00D8}'..='\u{00F6}'
+        | '\u{00F8}'..='\u{02FF}'
+        | '\u{0370}'..='\u{037D}'
+        | '\u{037F}'..='\u{1FFF}'
+        | '\u{200C}'..='\u{200D}'
+        | '\u{2070}'..='\u{218F}'
+        | '\u{2C00}'..='\u{2FEF}'
+        | '\u{3001}'..='\u{D7FF}'
+        | '\u{F900}'..='\u{FDCF}'
+        | '\u{FDF0}'..='\u{FFFD}'
+        | '\u{10000}'..='\u{EFFFF}'
+    )
+}
+
+pub(crate) fn is_pn_chars_u(c: char) -> bool {
+    is_pn_chars_base(c) || c == '_'
+}
+
+pub(crate) fn is_pn_chars(c: char) -> bool {
+    match c {
+        '-' | '0'..='9' | '\u{00B7}' | '\u{0300}'..='\u{036F}' | '\u{203F}'..='\u{2040}' => true,
+        c => is_pn_chars_u(c),
+    }
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str) -> Lexer<'a> {
+        Lexer {
+            input,
+            chars: input.char_indices().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, SyntaxError> {
+        Err(SyntaxError {
+            line: self.line,
+            column: self.column,
+            message: message.to_string(),
+        })
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|(_, c)| *c)
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        let mut chars = self.chars.clone();
+        chars.next();
+        chars.next().map(|(_, c)| c)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next().map(|(_, c)| c);
+        match c {
+            Some('\n') => {
+                self.line += 1;
+                self.column = 1;
+            }
+            Some(_) => self.column += 1,
+            None => (),
+        }
+        c
+    }
+
+    fn rest(&mut self) -> &'a str {
+        match self.chars.peek() {
+            Some((i, _)) => &self.input[*i..],
+            None => "",
+        }
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' | '\r' | '\n' => {
+                    self.bump();
+                }
+                '#' => {
+                    while !matches!(self.peek(), None | Some('\n')) {
+                        self.bump();
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Positioned, SyntaxError> {
+        self.skip_whitespace_and_comments();
+        let (line, column) = (self.line, self.column);
+        let token = match self.peek() {
+            None => Token::End,
+            Some('<') => self.iri_ref()?,
+            Some('"') | Some('\'') => self.string()?,
+            Some('@') => self.at_keyword()?,
+            Some('_') if self.peek_second() == Some(':') => self.blank_node_label()?,
+            Some('^') => {
+                self.bump();
+                match self.bump() {
+                    Some('^') => Token::DataType,
+                    _ => return self.error("Expected '^^'"),
+                }
+            }
+            Some('.') if !matches!(self.peek_second(), Some('0'..='9')) => {
+                self.bump();
+                Token::Dot
+            }
+            Some(';') => self.single(Token::Semicolon),
+            Some(',') => self.single(Token::Comma),
+            Some('[') => self.single(Token::OpenBracket),
+            Some(']') => self.single(Token::CloseBracket),
+            Some('(') => self.single(Token::OpenParenthesis),
+            Some(')') => self.single(Token::CloseParenthesis),
+            Some('+') | Some('-') | Some('.') | Some('0'..='9') => self.number()?,
+            Some(':') => self.prefixed_name(String::new())?,
+            Some(c) if is_pn_chars_base(c) => self.word()?,
+            Some(c) => return self.error(&format!("Unexpected character '{}'", c)),
+        };
+        Ok((token, line, column))
+    }
+
+    /// Lexes the next token, with its text for the values that the token does not hold.
+    fn next_token_with_text(&mut self) -> Result<(Token, &'a str), SyntaxError> {
+        self.skip_whitespace_and_comments();
+        let start = self.rest();
+        let (token, _, _) = self.next_token()?;
+        let length = start.len() - self.rest().len();
+        Ok((token, &start[..length]))
+    }
+
+    fn single(&mut self, token: Token) -> Token {
+        self.bump();
+        token
+    }
+
+    fn uchar(&mut self) -> Result<(), SyntaxError> {
+        let length = match self.bump() {
+            Some('u') => 4,
+            Some('U') => 8,
+            _ => return self.error("Invalid escape sequence"),
+        };
+        for _ in 0..length {
+            match self.bump() {
+                Some(c) if c.is_ascii_hexdigit() => (),
+                _ => return self.error("Invalid escape sequence"),
+            }
+        }
+        Ok(())
+    }
+
+    fn iri_ref(&mut self) -> Result<Token, SyntaxError> {
+        self.bump();
+        loop {
+            match self.peek() {
+                Some('>') => {
+                    self.bump();
+                    return Ok(Token::IriRef);
+                }
+                Some('\\') => {
+                    self.bump();
+                    self.uchar()?;
+                }
+                Some(c) if c <= ' ' || ['<', '"', '{', '}', '|', '^', '`'].contains(&c) => {
+                    return self.error(&format!("Invalid character {:?} in IRI", c))
+                }
+                Some(_) => {
+                    self.bump();
+                }
+                None => return self.error("Unterminated IRI"),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<Token, SyntaxError> {
+        let quote = self.bump().unwrap();
+        let long = self.peek() == Some(quote) && self.peek_second() == Some(quote);
+        if long {
+            self.bump();
+            self.bump();
+        }
+        loop {
+            match self.peek() {
+                None => return self.error("Unterminated string"),
+                Some('\\') => {
+                    self.bump();
+                    match self.peek() {
+                        Some('t') | Some('b') | Some('n') | Some('r') | Some('f') | Some('"')
+                        | Some('\'') | Some('\\') => {
+                            self.bump();
+                        }
+                        _ => self.uchar()?,
+                    }
+                }
+                Some(c) if c == quote && !long => {
+                    self.bump();
+                    return Ok(Token::Literal);
+                }
+                Some(c) if c == quote && self.rest().starts_with(&quote.to_string().repeat(3)) => {
+                    self.bump();
+                    self.bump();
+                    self.bump();
+                    return Ok(Token::Literal);
+                }
+                Some('\n') | Some('\r') if !long => return self.error("Line break in string"),
+                Some(_) => {
+                    self.bump();
+                }
+            }
+        }
+    }
+
+    fn at_keyword(&mut self) -> Result<Token, SyntaxError> {
+        self.bump();
+        let mut word = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || (c == '-' && !word.is_empty()) {
+                word.push(c);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        match word.as_str() {
+            "prefix" => Ok(Token::PrefixDirective),
+            "base" => Ok(Token::BaseDirective),
+            "" => self.error("Expected a language tag"),
+            _ => Ok(Token::LanguageTag),
+        }
+    }
+
+    fn blank_node_label(&mut self) -> Result<Token, SyntaxError> {
+        self.bump();
+        self.bump();
+        match self.peek() {
+            Some(c) if is_pn_chars_u(c) || c.is_ascii_digit() => {
+                self.bump();
+            }
+            _ => return self.error("Invalid blank node label"),
+        }
+        self.name_chars(false)?;
+        Ok(Token::BlankNodeLabel)
+    }
+
+    /// Consumes name characters, which may contain but not end with dots.
+    fn name_chars(&mut self, local: bool) -> Result<(), SyntaxError> {
+        loop {
+            match self.peek() {
+                Some('.') => match self.peek_second() {
+                    Some(c) if is_pn_chars(c) || (local && [':', '%', '\\'].contains(&c)) => {
+                        self.bump();
+                    }
+                    _ => return Ok(()),
+                },
+                Some(':') if local => {
+                    self.bump();
+                }
+                Some('%') if local => {
+                    self.bump();
+                    for _ in 0..2 {
+                        match self.bump() {
+                            Some(c) if c.is_ascii_hexdigit() => (),
+                            _ => return self.error("Invalid percent encoding"),
+                        }
+                    }
+                }
+                Some('\\') if local => {
+                    self.bump();
+                    match self.bump() {
+                        Some(c) if "_~.-!$&'()*+,;=/?#@%".contains(c) => (),
+                        _ => return self.error("Invalid escape in local name"),
+                    }
+                }
+                Some(c) if is_pn_chars(c) => {
+                    self.bump();
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn prefixed_name(&mut self, prefix: String) -> Result<Token, SyntaxError> {
+        self.bump();
+        match self.peek() {
+            Some(c) if is_pn_chars_u(c) || c.is_ascii_digit() || [':', '%', '\\'].contains(&c) => {
+                self.name_chars(true)?;
+                Ok(Token::PrefixedName { prefix })
+            }
+            _ => Ok(Token::Namespace { prefix }),
+        }
+    }
+
+    fn word(&mut self) -> Result<Token, SyntaxError> {
+        let start = self.rest();
+        let mut length = 0;
+        while let Some(c) = self.peek() {
+            if is_pn_chars(c)
+                || (c == '.' && matches!(self.peek_second(), Some(c) if is_pn_chars(c)))
+            {
+                length += c.len_utf8();
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        let word = &start[..length];
+        if self.peek() == Some(':') {
+            return self.prefixed_name(word.to_string());
+        }
+        match word {
+            "a" => Ok(Token::A),
+            "true" | "false" => Ok(Token::Boolean),
+            w if w.eq_ignore_ascii_case("prefix") => Ok(Token::SparqlPrefix),
+            w if w.eq_ignore_ascii_case("base") => Ok(Token::SparqlBase),
+            w => self.error(&format!("Unexpected word '{}'", w)),
+        }
+    }
+
+    fn digits(&mut self) -> usize {
+        let mut count = 0;
+        while let Some('0'..='9') = self.peek() {
+            self.bump();
+            count += 1;
+        }
+        count
+    }
+
+    fn number(&mut self) -> Result<Token, SyntaxError> {
+        if let Some('+') | Some('-') = self.peek() {
+            self.bump();
+        }
+        let mut count = self.digits();
+        if self.peek() == Some('.') && matches!(self.peek_second(), Some('0'..='9')) {
+            self.bump();
+            count += self.digits();
+        }
+        if count == 0 {
+            return self.error("Invalid number");
+        }
+        if let Some('e') | Some('E') = self.peek() {
+            self.bump();
+            if let Some('+') | Some('-') = self.peek() {
+                self.bump();
+            }
+            if self.digits() == 0 {
+                return self.error("Invalid exponent");
+            }
+        }
+        Ok(Token::Number)
+    }
+}
+
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    dialect: Dialect,
+    prefixes: HashSet<String>,
+    peeked: Option<Positioned>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&mut self) -> Result<&Token, SyntaxError> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.lexer.next_token()?);
+        }
+        Ok(&self.peeked.as_ref().unwrap().0)
+    }
+
+    fn next(&mut self) -> Result<Positioned, SyntaxError> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.lexer.next_token(),
+        }
+    }
+
+    fn unexpected<T>(
+        &self,
+        (token, line, column): Positioned,
+        expected: &str,
+    ) -> Result<T, SyntaxError> {
+        let found = match token {
+            Token::End => "end of input".to_string(),
+            token => format!("{:?}", token),
+        };
+        Err(SyntaxError {
+            line,
+            column,
+            message: format!("Expected {}, found {}", expected, found),
+        })
+    }
+
+    fn expect(&mut self, expected: Token, description: &str) -> Result<(), SyntaxError> {
+        let token = self.next()?;
+        if token.0 == expected {
+            Ok(())
+        } else {
+            self.unexpected(token, description)
+        }
+    }
+
+    fn document(&mut self) -> Result<(), SyntaxError> {
+        let turtle = self.dialect == Dialect::Turtle;
+        loop {
+            match self.peek()? {
+                Token::End => return Ok(()),
+                Token::PrefixDirective | Token::SparqlPrefix if turtle => {
+                    let (directive, _, _) = self.next()?;
+                    let token = self.next()?;
+                    match token.0 {
+                        Token::Namespace { prefix } => {
+                            self.prefixes.insert(prefix);
+                        }
+                        _ => return self.unexpected(token, "a prefix"),
+                    }
+                    self.expect(Token::IriRef, "an IRI")?;
+                    if directive == Token::PrefixDirective {
+                        self.expect(Token::Dot, "'.'")?;
+                    }
+                }
+                Token::BaseDirective | Token::SparqlBase if turtle => {
+                    let (directive, _, _) = self.next()?;
+                    self.expect(Token::IriRef, "an IRI")?;
+                    if directive == Token::BaseDirective {
+                        self.expect(Token::Dot, "'.'")?;
+                    }
+                }
+                _ => {
+                    self.triples()?;
+                    self.expect(Token::Dot, "'.'")?;
+                }
+            }
+        }
+    }
+
+    fn triples(&mut self) -> Result<(), SyntaxError> {
+        if self.dialect == Dialect::NTriples {
+            let token = self.next()?;
+            match token.0 {
+                Token::IriRef | Token::BlankNodeLabel => (),
+                _ => return self.unexpected(token, "a subject"),
+            }
+            self.expect(Token::IriRef, "a predicate")?;
+            return self.object();
+        }
+        if self.peek()? == &Token::OpenBracket {
+            self.next()?;
+            if self.peek()? == &Token::CloseBracket {
+                self.next()?;
+                return self.predicate_object_list();
+            }
+            self.predicate_object_list()?;
+            self.expect(Token::CloseBracket, "']'")?;
+            match self.peek()? {
+                Token::Dot => Ok(()),
+                _ => self.predicate_object_list(),
+            }
+        } else {
+            let token = self.next()?;
+            match token.0 {
+                Token::IriRef | Token::BlankNodeLabel => (),
+                Token::PrefixedName { .. } | Token::Namespace { .. } => {
+                    self.check_prefix(&token)?
+                }
+                Token::OpenParenthesis => self.collection()?,
+                _ => return self.unexpected(token, "a subject"),
+            }
+            self.predicate_object_list()
+        }
+    }
+
+    fn check_prefix(&self, token: &Positioned) -> Result<(), SyntaxError> {
+        match token {
+            (Token::PrefixedName { prefix }, line, column)
+            | (Token::Namespace { prefix }, line, column)
+                if !self.prefixes.contains(prefix) =>
+            {
+                Err(SyntaxError {
+                    line: *line,
+                    column: *column,
+                    message: format!("Undefined prefix '{}:'", prefix),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn predicate_object_list(&mut self) -> Result<(), SyntaxError> {
+        self.verb()?;
+        self.object_list()?;
+        while self.peek()? == &Token::Semicolon {
+            self.next()?;
+            match self.peek()? {
+                Token::Semicolon | Token::Dot | Token::CloseBracket => continue,
+                _ => {
+                    self.verb()?;
+                    self.object_list()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn verb(&mut self) -> Result<(), SyntaxError> {
+        let token = self.next()?;
+        match token.0 {
+            Token::IriRef | Token::A => Ok(()),
+            Token::PrefixedName { .. } | Token::Namespace { .. } => self.check_prefix(&token),
+            _ => self.unexpected(token, "a predicate"),
+        }
+    }
+
+    fn object_list(&mut self) -> Result<(), SyntaxError> {
+        self.object()?;
+        while self.peek()? == &Token::Comma {
+            self.next()?;
+            self.object()?;
+        }
+        Ok(())
+    }
+
+    fn object(&mut self) -> Result<(), SyntaxError> {
+        let dialect = self.dialect;
+        let token = self.next()?;
+        match (&token.0, dialect) {
+            (Token::IriRef, _) | (Token::BlankNodeLabel, _) => Ok(()),
+            (Token::Literal, _) => match self.peek()? {
+                Token::LanguageTag => self.next().map(|_| ()),
+                Token::DataType => {
+                    self.next()?;
+                    let token = self.next()?;
+                    match (&token.0, dialect) {
+                        (Token::IriRef, _) => Ok(()),
+                        (Token::PrefixedName { .. }, Dialect::Turtle)
+                        | (Token::Namespace { .. }, Dialect::Turtle) => self.check_prefix(&token),
+                        _ => self.unexpected(token, "a datatype IRI"),
+                    }
+                }
+                _ => Ok(()),
+            },
+            (Token::PrefixedName { .. }, Dialect::Turtle)
+            | (Token::Namespace { .. }, Dialect::Turtle) => self.check_prefix(&token),
+            (Token::Number, Dialect::Turtle) | (Token::Boolean, Dialect::Turtle) => Ok(()),
+            (Token::OpenParenthesis, Dialect::Turtle) => self.collection(),
+            (Token::OpenBracket, Dialect::Turtle) => {
+                if self.peek()? != &Token::CloseBracket {
+                    self.predicate_object_list()?;
+                }
+                self.expect(Token::CloseBracket, "']'")
+            }
+            _ => self.unexpected(token, "an object"),
+        }
+    }
+
+    fn collection(&mut self) -> Result<(), SyntaxError> {
+        while self.peek()? != &Token::CloseParenthesis {
+            self.object()?;
+        }
+        self.next().map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::{check, top_level_values, Dialect, SyntaxError};
+
+    #[test]
+    fn accepts_turtle() {
+        let input = "@base <urn:uuid:bc36c84d-30bf-4014-9940-255150891034> .
+PREFIX ex: <http://example.org/>
+@prefix : <http://example.org/default#> .
+
+<#a> <#b> \"c\" ; a ex:Thing ; ex:list ( 1 2.5 -3e2 true ) .
+[ ex:p \"x\"@en-GB ] ex:q [ ] , _:b1 .
+ex:a.b :c \"\"\"long
+string\"\"\"^^ex:type .
+[ ex:only \"subject\" ] .";
+
+        assert_eq!(check(input, Dialect::Turtle), Ok(()));
+    }
+
+    #[test]
+    fn accepts_n_triples() {
+        let input = "<http://a> <http://b> \"c\"@en .
+_:x <http://b> \"1\"^^<http://www.w3.org/2001/XMLSchema#integer> . # comment
+";
+
+        assert_eq!(check(input, Dialect::NTriples), Ok(()));
+    }
+
+    #[test]
+    fn reports_position() {
+        let input = "@prefix ex: <http://example.org/> .\nex:a ex:b\n  ex:c";
+
+        assert_eq!(
+            check(input, Dialect::Turtle),
+            Err(SyntaxError {
+                line: 3,
+                column: 7,
+                message: "Expected '.', found end of input".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn accepts_prefixed_names_without_local_part() {
+        let input = "@prefix ex: <http://example.org/> .\nex: ex: ex: .";
+
+        assert_eq!(check(input, Dialect::Turtle), Ok(()));
+        assert_eq!(
+            check("ex: <http://b> <http://c> .", Dialect::Turtle)
+                .unwrap_err()
+                .message,
+            "Undefined prefix 'ex:'"
+        );
+    }
+
+    #[test]
+    fn rejects_undefined_prefix() {
+        assert_eq!(
+            check("ex:a <http://b> <http://c> .", Dialect::Turtle)
+                .unwrap_err()
+                .message,
+            "Undefined prefix 'ex:'"
+        );
+    }
+
+    #[test]
+    fn reads_top_level_values() {
+        let input = r#"PREFIX fuseki: <http://jena.apache.org/fuseki#>
+@prefix ex: <http://example.org/> .
+
+<#service> a fuseki:Service ;
+    fuseki:name "ds" ;
+    fuseki:endpoint [ fuseki:operation fuseki:query ; fuseki:name "sparql" ] .
+ex:other <http://jena.apache.org/fuseki#name> """other""" ; ex:name "ignored" ."#;
+
+        assert_eq!(
+            top_level_values(input, "http://jena.apache.org/fuseki#name"),
+            Ok(vec!["ds".to_string(), "other".to_string()])
+        );
+        assert!(top_level_values("<a> <b>", "http://example.org/b").is_err());
+    }
+
+    #[test]
+    fn rejects_turtle_in_n_triples() {
+        assert!(check("<http://a> a <http://c> .", Dialect::NTriples).is_err());
+    }
+}
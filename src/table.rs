@@ -1,8 +1,12 @@
+use crate::prefix::PrefixMap;
+use crate::term::Term;
+use crate::writer;
+use rdf::node::Node;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::hash::{Hash, Hasher};
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Variable {
     name: String,
 }
@@ -51,9 +55,49 @@ impl<A> Table<A> {
     }
 }
 
+impl Table<Node> {
+    /// Writes the values as Turtle terms, abbreviating IRIs with the prefixes.
+    pub fn compact(&self, prefixes: &PrefixMap) -> Table<String> {
+        let prefixes = prefixes.pairs();
+        Table {
+            variables: self.variables.clone(),
+            bindings: self
+                .bindings
+                .iter()
+                .map(|binding| {
+                    binding
+                        .iter()
+                        .map(|(k, v)| (k.clone(), writer::term(v, &prefixes)))
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    /// Reads the values of literals of known datatypes.
+    pub fn terms(&self) -> Table<Term> {
+        Table {
+            variables: self.variables.clone(),
+            bindings: self
+                .bindings
+                .iter()
+                .map(|binding| {
+                    binding
+                        .iter()
+                        .map(|(k, v)| (k.clone(), Term::from(v)))
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::table::Table;
+    use crate::prefix::PrefixMap;
+    use crate::table::{Table, Variable};
+    use rdf::node::Node;
+    use rdf::uri::Uri;
     use std::collections::HashMap;
     use std::iter::FromIterator;
 
@@ -82,9 +126,42 @@ mod tests {
         println!("|\n|---|---|");
         for row in &table.bindings {
             for v in &table.variables {
-                print!("|{:?}", row.get(&v).unwrap());
+                print!("|{:?}", row.get(v).unwrap());
             }
             println!("|");
         }
     }
+
+    #[test]
+    fn compacts_iris() {
+        let vars = vec!["s".to_string(), "o".to_string()];
+        let bindings = vec![HashMap::from_iter(
+            vec![
+                (
+                    "s".to_string(),
+                    Node::UriNode {
+                        uri: Uri::new("http://www.w3.org/2002/07/owl#Thing".to_string()),
+                    },
+                ),
+                (
+                    "o".to_string(),
+                    Node::LiteralNode {
+                        literal: "1".to_string(),
+                        data_type: Some(Uri::new(
+                            "http://www.w3.org/2001/XMLSchema#integer".to_string(),
+                        )),
+                        language: None,
+                    },
+                ),
+            ]
+            .into_iter(),
+        )];
+        let table = Table::from(vars, bindings, |a| a).compact(&PrefixMap::default());
+
+        assert_eq!(table.bindings[0][&Variable::from("s")], "owl:Thing");
+        assert_eq!(
+            table.bindings[0][&Variable::from("o")],
+            "\"1\"^^xsd:integer"
+        );
+    }
 }
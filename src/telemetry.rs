@@ -0,0 +1,253 @@
+//! Tracing spans for graph store operations, and with the `metrics` feature, counters and
+//! latency histograms.
+//!
+//! Each operation runs in a `graph_store` span with the fields `kind`, `dataset`, `query_hash`,
+//! `graph`, `duration_ms`, `rows`, `bytes` and `error`. The metrics are
+//! `graph_store_operations_total`, `graph_store_errors_total` and
+//! `graph_store_operation_duration_seconds`, labeled with `kind` and `dataset`.
+
+use crate::error::Error;
+use crate::{Graph, Resource};
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tracing::field::Empty;
+use tracing::Instrument;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Kind {
+    Import,
+    Merge,
+    Update,
+    Select,
+    Ask,
+    Describe,
+    Construct,
+    Get,
+}
+
+impl Kind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Kind::Import => "import",
+            Kind::Merge => "merge",
+            Kind::Update => "update",
+            Kind::Select => "select",
+            Kind::Ask => "ask",
+            Kind::Describe => "describe",
+            Kind::Construct => "construct",
+            Kind::Get => "get",
+        }
+    }
+}
+
+/// Runs an operation on a dataset in a span, and records its duration and outcome. The
+/// operation can record `rows` and `bytes` in the current span.
+pub(crate) async fn observe<T, F>(
+    kind: Kind,
+    dataset: &str,
+    query: Option<&str>,
+    graph: Option<&Graph>,
+    operation: F,
+) -> Result<T, Error>
+where
+    F: Future<Output = Result<T, Error>>,
+{
+    let span = tracing::info_span!(
+        "graph_store",
+        kind = kind.as_str(),
+        dataset,
+        query_hash = query.map(hash).as_deref(),
+        graph = graph.map(graph_name),
+        duration_ms = Empty,
+        rows = Empty,
+        bytes = Empty,
+        error = Empty,
+    );
+    let start = Instant::now();
+    let result = operation.instrument(span.clone()).await;
+    let elapsed = start.elapsed();
+    let error = result.as_ref().err().map(Error::kind);
+    span.record("duration_ms", elapsed.as_secs_f64() * 1000.0);
+    span.record("error", error);
+    span.in_scope(|| match &result {
+        Ok(_) => tracing::debug!("Completed {}", kind.as_str()),
+        Err(e) => tracing::warn!("Failed {}: {}", kind.as_str(), e),
+    });
+    record_metrics(kind, dataset, elapsed, error);
+    result
+}
+
+/// Identifies a query without recording its text, which may contain personal data.
+fn hash(query: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.input(query.as_bytes());
+    hex::encode(&hasher.result()[..8])
+}
+
+fn graph_name(graph: &Graph) -> &str {
+    match graph {
+        Graph::Default => "default",
+        Graph::Named(Resource(id)) => id,
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn record_metrics(kind: Kind, dataset: &str, elapsed: Duration, error: Option<&'static str>) {
+    let labels = [
+        ("kind", kind.as_str().to_string()),
+        ("dataset", dataset.to_string()),
+    ];
+    metrics::counter!("graph_store_operations_total", &labels).increment(1);
+    metrics::histogram!("graph_store_operation_duration_seconds", &labels)
+        .record(elapsed.as_secs_f64());
+    if let Some(error) = error {
+        let labels = [
+            ("kind", kind.as_str().to_string()),
+            ("dataset", dataset.to_string()),
+            ("error", error.to_string()),
+        ];
+        metrics::counter!("graph_store_errors_total", &labels).increment(1);
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+fn record_metrics(_kind: Kind, _dataset: &str, _elapsed: Duration, _error: Option<&'static str>) {}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::telemetry::{hash, observe, Kind};
+    use crate::{Graph, Resource};
+    use std::collections::HashMap;
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+    use tracing_core::span::Current;
+
+    type Fields = HashMap<String, String>;
+
+    /// Keeps the fields of the spans, in the order of their creation, and the entered spans
+    /// so that operations can record fields in the current span.
+    #[derive(Default)]
+    struct Spans {
+        fields: Arc<Mutex<Vec<Fields>>>,
+        metadata: Mutex<Vec<&'static Metadata<'static>>>,
+        entered: Mutex<Vec<Id>>,
+    }
+
+    struct FieldVisitor<'a>(&'a mut Fields);
+
+    impl Visit for FieldVisitor<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+
+    impl Subscriber for Spans {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, attributes: &Attributes) -> Id {
+            let mut fields = Fields::new();
+            attributes.record(&mut FieldVisitor(&mut fields));
+            let mut spans = self.fields.lock().unwrap();
+            spans.push(fields);
+            self.metadata.lock().unwrap().push(attributes.metadata());
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &Id, values: &Record) {
+            let mut spans = self.fields.lock().unwrap();
+            values.record(&mut FieldVisitor(&mut spans[span.into_u64() as usize - 1]));
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, _: &Event) {}
+
+        fn enter(&self, span: &Id) {
+            self.entered.lock().unwrap().push(span.clone());
+        }
+
+        fn exit(&self, _: &Id) {
+            self.entered.lock().unwrap().pop();
+        }
+
+        fn current_span(&self) -> Current {
+            match self.entered.lock().unwrap().last() {
+                Some(span) => {
+                    let metadata = self.metadata.lock().unwrap()[span.into_u64() as usize - 1];
+                    Current::new(span.clone(), metadata)
+                }
+                None => Current::none(),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn observes_operations() {
+        let result = observe(Kind::Get, "test", None, Some(&Graph::Default), async {
+            tracing::Span::current().record("bytes", 42);
+            Ok(1)
+        })
+        .await;
+        let error = observe(Kind::Select, "test", Some("SELECT * {}"), None, async {
+            Err::<(), _>(Error::Timeout)
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 1);
+        assert_eq!(error.unwrap_err().kind(), "timeout");
+        assert_eq!(hash("SELECT * {}"), hash("SELECT * {}"));
+        assert_eq!(hash("ASK {}").len(), 16);
+    }
+
+    #[tokio::test]
+    async fn records_span_fields() {
+        let spans = Spans::default();
+        let fields = spans.fields.clone();
+        let _guard = tracing::subscriber::set_default(spans);
+        let graph = Graph::Named(Resource::unsafe_from("http://example.org/g"));
+
+        observe(Kind::Get, "test", None, Some(&graph), async {
+            tracing::Span::current().record("bytes", 42);
+            Ok(())
+        })
+        .await
+        .unwrap();
+        observe(Kind::Select, "test", Some("SELECT * {}"), None, async {
+            tracing::Span::current().record("rows", 3);
+            Err::<(), _>(Error::Timeout)
+        })
+        .await
+        .unwrap_err();
+
+        let fields = fields.lock().unwrap();
+        let get = &fields[0];
+        assert_eq!(get["kind"], "get");
+        assert_eq!(get["dataset"], "test");
+        assert_eq!(get["graph"], "http://example.org/g");
+        assert_eq!(get["bytes"], "42");
+        assert!(!get.contains_key("query_hash"));
+        assert!(!get.contains_key("rows"));
+        assert!(!get.contains_key("error"));
+        assert!(get.contains_key("duration_ms"));
+        let select = &fields[1];
+        assert_eq!(select["kind"], "select");
+        assert_eq!(select["dataset"], "test");
+        assert_eq!(select["query_hash"], hash("SELECT * {}"));
+        assert_eq!(select["rows"], "3");
+        assert_eq!(select["error"], "timeout");
+        assert!(!select.contains_key("graph"));
+        assert!(!select.contains_key("bytes"));
+    }
+}
@@ -0,0 +1,1139 @@
+//! RDF terms whose literals carry the values of common XSD datatypes, compared as SPARQL
+//! operators compare them.
+
+use crate::writer;
+use crate::Resource;
+use rdf::node::Node;
+use rdf::uri::Uri;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+const RDF_LANG_STRING: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#langString";
+
+/// The largest timezone offset, in minutes.
+const MAX_TIMEZONE: i64 = 14 * 60;
+
+/// The largest absolute year of a date, so that its instant in seconds fits in an `i64`.
+const MAX_YEAR: i64 = 999_999_999;
+
+/// An IRI, a blank node or a literal.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Term {
+    Iri(Resource),
+    BlankNode(String),
+    Literal(Literal),
+}
+
+/// A literal with its value, for the datatypes that SPARQL operators know.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Literal {
+    /// A simple literal or an `xsd:string`.
+    String(String),
+    LangString {
+        value: String,
+        language: String,
+    },
+    Integer(i64),
+    Decimal(Decimal),
+    Double(f64),
+    Boolean(bool),
+    Date(Date),
+    DateTime(DateTime),
+    Duration(Duration),
+    /// A literal of another datatype, or one that is not valid for its datatype, such as an
+    /// integer out of range.
+    Other {
+        value: String,
+        datatype: Resource,
+    },
+}
+
+impl Term {
+    pub fn resource(&self) -> Option<&Resource> {
+        match self {
+            Term::Iri(resource) => Some(resource),
+            _ => None,
+        }
+    }
+
+    pub fn literal(&self) -> Option<&Literal> {
+        match self {
+            Term::Literal(literal) => Some(literal),
+            _ => None,
+        }
+    }
+
+    /// Compares two terms as the SPARQL `=` operator does, where `None` is a type error.
+    pub fn sparql_eq(&self, other: &Term) -> Option<bool> {
+        match (self, other) {
+            (Term::Literal(a), Term::Literal(b)) => a.sparql_eq(b),
+            _ => Some(self == other),
+        }
+    }
+
+    /// Orders two terms as the SPARQL `<` and `>` operators do, where `None` is a type error.
+    pub fn sparql_cmp(&self, other: &Term) -> Option<Ordering> {
+        match (self, other) {
+            (Term::Literal(a), Term::Literal(b)) => a.sparql_cmp(b),
+            _ => None,
+        }
+    }
+
+    /// Writes literals in the canonical form of their datatype.
+    pub fn to_node(&self) -> Node {
+        match self {
+            Term::Iri(resource) => Node::UriNode {
+                uri: Uri::new(resource.as_str().to_string()),
+            },
+            Term::BlankNode(id) => Node::BlankNode { id: id.to_string() },
+            Term::Literal(Literal::String(value)) => Node::LiteralNode {
+                literal: value.to_string(),
+                data_type: None,
+                language: None,
+            },
+            Term::Literal(Literal::LangString { value, language }) => Node::LiteralNode {
+                literal: value.to_string(),
+                data_type: None,
+                language: Some(language.to_string()),
+            },
+            Term::Literal(literal) => Node::LiteralNode {
+                literal: literal.lexical_form(),
+                data_type: Some(Uri::new(literal.datatype().as_str().to_string())),
+                language: None,
+            },
+        }
+    }
+}
+
+impl From<&Node> for Term {
+    fn from(node: &Node) -> Self {
+        match node {
+            Node::UriNode { uri } => Term::Iri(Resource::unsafe_from(uri.to_string())),
+            Node::BlankNode { id } => Term::BlankNode(id.to_string()),
+            Node::LiteralNode {
+                literal,
+                language: Some(language),
+                ..
+            } => Term::Literal(Literal::LangString {
+                value: literal.to_string(),
+                language: language.to_string(),
+            }),
+            Node::LiteralNode {
+                literal,
+                data_type: Some(data_type),
+                ..
+            } => Term::Literal(Literal::parse(literal, data_type.to_string())),
+            Node::LiteralNode { literal, .. } => {
+                Term::Literal(Literal::String(literal.to_string()))
+            }
+        }
+    }
+}
+
+impl From<&Term> for Node {
+    fn from(term: &Term) -> Self {
+        term.to_node()
+    }
+}
+
+impl From<Resource> for Term {
+    fn from(resource: Resource) -> Self {
+        Term::Iri(resource)
+    }
+}
+
+impl From<Literal> for Term {
+    fn from(literal: Literal) -> Self {
+        Term::Literal(literal)
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", writer::term(&self.to_node(), &[]))
+    }
+}
+
+impl Literal {
+    /// Reads the value of a literal, keeping it as it is if it is not valid for its datatype.
+    pub fn parse(value: &str, datatype: &str) -> Literal {
+        let parsed = match datatype.strip_prefix(XSD) {
+            Some("string") => Some(Literal::String(value.to_string())),
+            Some("integer") => parse_integer(value.trim()).map(Literal::Integer),
+            Some("decimal") => value.trim().parse().ok().map(Literal::Decimal),
+            Some("double") => parse_double(value.trim()).map(Literal::Double),
+            Some("boolean") => match value.trim() {
+                "true" | "1" => Some(Literal::Boolean(true)),
+                "false" | "0" => Some(Literal::Boolean(false)),
+                _ => None,
+            },
+            Some("date") => value.trim().parse().ok().map(Literal::Date),
+            Some("dateTime") => value.trim().parse().ok().map(Literal::DateTime),
+            Some("duration") => value.trim().parse().ok().map(Literal::Duration),
+            _ => None,
+        };
+        parsed.unwrap_or_else(|| Literal::Other {
+            value: value.to_string(),
+            datatype: Resource::unsafe_from(datatype),
+        })
+    }
+
+    pub fn datatype(&self) -> Resource {
+        let name = match self {
+            Literal::String(_) => "string",
+            Literal::LangString { .. } => return Resource::unsafe_from(RDF_LANG_STRING),
+            Literal::Integer(_) => "integer",
+            Literal::Decimal(_) => "decimal",
+            Literal::Double(_) => "double",
+            Literal::Boolean(_) => "boolean",
+            Literal::Date(_) => "date",
+            Literal::DateTime(_) => "dateTime",
+            Literal::Duration(_) => "duration",
+            Literal::Other { datatype, .. } => return datatype.clone(),
+        };
+        Resource::unsafe_from(&format!("{}{}", XSD, name))
+    }
+
+    /// The canonical lexical form of the value.
+    pub fn lexical_form(&self) -> String {
+        match self {
+            Literal::String(value)
+            | Literal::LangString { value, .. }
+            | Literal::Other { value, .. } => value.to_string(),
+            Literal::Integer(value) => value.to_string(),
+            Literal::Decimal(value) => value.to_string(),
+            Literal::Double(value) => write_double(*value),
+            Literal::Boolean(value) => value.to_string(),
+            Literal::Date(value) => value.to_string(),
+            Literal::DateTime(value) => value.to_string(),
+            Literal::Duration(value) => value.to_string(),
+        }
+    }
+
+    /// The text of a string, with or without a language tag.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Literal::String(value) | Literal::LangString { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Literal::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value of any number, as a double.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Literal::Integer(value) => Some(*value as f64),
+            Literal::Decimal(value) => Some(value.to_f64()),
+            Literal::Double(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Literal::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Compares two literals as the SPARQL `=` operator does. Values of different datatypes are
+    /// compared after numeric type promotion, and other literals are only equal if they are the
+    /// same term.
+    pub fn sparql_eq(&self, other: &Literal) -> Option<bool> {
+        match (self, other) {
+            (Literal::String(a), Literal::String(b)) => Some(a == b),
+            (
+                Literal::LangString {
+                    value: a,
+                    language: l,
+                },
+                Literal::LangString {
+                    value: b,
+                    language: m,
+                },
+            ) => Some(a == b && l.eq_ignore_ascii_case(m)),
+            (Literal::Boolean(a), Literal::Boolean(b)) => Some(a == b),
+            (Literal::Duration(a), Literal::Duration(b)) => Some(a == b),
+            (Literal::Date(_), Literal::Date(_)) | (Literal::DateTime(_), Literal::DateTime(_)) => {
+                self.sparql_cmp(other).map(|o| o == Ordering::Equal)
+            }
+            _ => match (self.number(), other.number()) {
+                (Some(a), Some(b)) => Some(a.compare(&b) == Some(Ordering::Equal)),
+                _ if self == other => Some(true),
+                _ => None,
+            },
+        }
+    }
+
+    /// Orders two literals of the same kind as the SPARQL `<` and `>` operators do. Dates and
+    /// times with and without a timezone are only ordered if they are more than 14 hours apart,
+    /// and durations are only ordered if both are in months or both are in seconds.
+    pub fn sparql_cmp(&self, other: &Literal) -> Option<Ordering> {
+        match (self, other) {
+            (Literal::String(a), Literal::String(b)) => Some(a.cmp(b)),
+            (Literal::Boolean(a), Literal::Boolean(b)) => Some(a.cmp(b)),
+            (Literal::Date(a), Literal::Date(b)) => {
+                compare_instants(a.instant(), a.timezone, b.instant(), b.timezone)
+            }
+            (Literal::DateTime(a), Literal::DateTime(b)) => {
+                compare_instants(a.instant(), a.date.timezone, b.instant(), b.date.timezone)
+            }
+            (Literal::Duration(a), Literal::Duration(b)) => a.compare(b),
+            _ => match (self.number(), other.number()) {
+                (Some(a), Some(b)) => a.compare(&b),
+                _ => None,
+            },
+        }
+    }
+
+    fn number(&self) -> Option<Number> {
+        match self {
+            Literal::Integer(value) => Some(Number::Decimal(Decimal::from(*value))),
+            Literal::Decimal(value) => Some(Number::Decimal(*value)),
+            Literal::Double(value) => Some(Number::Double(*value)),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! from_values {
+    ($($type:ty => $variant:ident),*) => {
+        $(
+            impl From<$type> for Literal {
+                fn from(value: $type) -> Self {
+                    Literal::$variant(value.into())
+                }
+            }
+
+            impl From<$type> for Term {
+                fn from(value: $type) -> Self {
+                    Term::Literal(Literal::from(value))
+                }
+            }
+        )*
+    };
+}
+
+from_values!(
+    &str => String,
+    String => String,
+    i64 => Integer,
+    Decimal => Decimal,
+    f64 => Double,
+    bool => Boolean,
+    Date => Date,
+    DateTime => DateTime,
+    Duration => Duration
+);
+
+/// A number after type promotion, where integers are decimals.
+enum Number {
+    Decimal(Decimal),
+    Double(f64),
+}
+
+impl Number {
+    fn compare(&self, other: &Number) -> Option<Ordering> {
+        match (self, other) {
+            (Number::Decimal(a), Number::Decimal(b)) => Some(a.cmp(b)),
+            _ => self.to_f64().partial_cmp(&other.to_f64()),
+        }
+    }
+
+    fn to_f64(&self) -> f64 {
+        match self {
+            Number::Decimal(value) => value.to_f64(),
+            Number::Double(value) => *value,
+        }
+    }
+}
+
+/// The error of parsing a value from an invalid lexical form.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ValueError(String);
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid value {}", self.0)
+    }
+}
+
+impl std::error::Error for ValueError {}
+
+/// An `xsd:decimal` with up to 38 digits, of which up to 38 after the decimal point.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Decimal {
+    digits: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    /// The decimal `digits × 10^-scale`, if the scale is at most 38.
+    pub fn new(digits: i128, scale: u32) -> Option<Decimal> {
+        if scale > 38 {
+            return None;
+        }
+        let mut decimal = Decimal { digits, scale };
+        while decimal.scale > 0 && decimal.digits % 10 == 0 {
+            decimal.digits /= 10;
+            decimal.scale -= 1;
+        }
+        Some(decimal)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits == 0
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.digits as f64 / 10f64.powi(self.scale as i32)
+    }
+
+    /// The integer part and the fraction, both with the sign of the decimal.
+    fn split(&self) -> (i128, i128) {
+        let unit = 10i128.pow(self.scale);
+        (self.digits / unit, self.digits % unit)
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Self {
+        Decimal {
+            digits: value.into(),
+            scale: 0,
+        }
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let ((a, x), (b, y)) = (self.split(), other.split());
+        let scale = self.scale.max(other.scale);
+        a.cmp(&b).then_with(|| {
+            let x = x * 10i128.pow(scale - self.scale);
+            let y = y * 10i128.pow(scale - other.scale);
+            x.cmp(&y)
+        })
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = ValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ValueError(s.to_string());
+        let (negative, unsigned) = split_sign(s);
+        let (integer, fraction) = match unsigned.find('.') {
+            Some(i) => (&unsigned[..i], &unsigned[i + 1..]),
+            None => (unsigned, ""),
+        };
+        if integer.is_empty() && fraction.is_empty() {
+            return Err(error());
+        }
+        let fraction = fraction.trim_end_matches('0');
+        let mut digits: i128 = 0;
+        for c in integer.chars().chain(fraction.chars()) {
+            let digit = c.to_digit(10).ok_or_else(error)?;
+            digits = digits
+                .checked_mul(10)
+                .and_then(|d| d.checked_add(digit.into()))
+                .ok_or_else(error)?;
+        }
+        let digits = if negative { -digits } else { digits };
+        Decimal::new(digits, fraction.len() as u32).ok_or_else(error)
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (integer, fraction) = self.split();
+        if self.digits < 0 {
+            write!(f, "-")?;
+        }
+        write!(f, "{}.", integer.unsigned_abs())?;
+        if self.scale == 0 {
+            write!(f, "0")
+        } else {
+            let width = self.scale as usize;
+            write!(f, "{:0width$}", fraction.unsigned_abs(), width = width)
+        }
+    }
+}
+
+/// An `xsd:date`, with a timezone offset in minutes if it has one.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Date {
+    year: i64,
+    month: u8,
+    day: u8,
+    timezone: Option<i16>,
+}
+
+impl Date {
+    /// A valid date in the proleptic Gregorian calendar, without a timezone, in a year of at most
+    /// nine digits.
+    pub fn new(year: i64, month: u8, day: u8) -> Option<Date> {
+        if year.abs() <= MAX_YEAR
+            && (1..=12).contains(&month)
+            && day >= 1
+            && day <= days_in_month(year, month)
+        {
+            Some(Date {
+                year,
+                month,
+                day,
+                timezone: None,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Sets the timezone offset in minutes, of at most 14 hours.
+    pub fn with_timezone(mut self, timezone: Option<i16>) -> Option<Date> {
+        match timezone {
+            Some(offset) if i64::from(offset).abs() > MAX_TIMEZONE => None,
+            _ => {
+                self.timezone = timezone;
+                Some(self)
+            }
+        }
+    }
+
+    pub fn year(&self) -> i64 {
+        self.year
+    }
+
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    pub fn timezone(&self) -> Option<i16> {
+        self.timezone
+    }
+
+    /// Seconds since 1970-01-01T00:00:00Z at the start of the day, in UTC if the date has no
+    /// timezone.
+    fn instant(&self) -> (i64, u32) {
+        (self.local_seconds() - self.offset_seconds(), 0)
+    }
+
+    fn local_seconds(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * 86400
+    }
+
+    fn offset_seconds(&self) -> i64 {
+        self.timezone.map_or(0, |offset| i64::from(offset) * 60)
+    }
+}
+
+impl FromStr for Date {
+    type Err = ValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut reader = Reader(s);
+        let date = reader
+            .date()
+            .and_then(|date| date.with_timezone(reader.timezone()?));
+        match date {
+            Some(date) if reader.0.is_empty() => Ok(date),
+            _ => Err(ValueError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_date(f, self)?;
+        write_timezone(f, self.timezone)
+    }
+}
+
+/// An `xsd:dateTime`, with a timezone offset in minutes if it has one.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct DateTime {
+    date: Date,
+    hour: u8,
+    minute: u8,
+    second: u8,
+    nanosecond: u32,
+}
+
+impl DateTime {
+    /// A valid time of a date, which keeps the timezone of the date.
+    pub fn new(date: Date, hour: u8, minute: u8, second: u8, nanosecond: u32) -> Option<DateTime> {
+        if hour < 24 && minute < 60 && second < 60 && nanosecond < 1_000_000_000 {
+            Some(DateTime {
+                date,
+                hour,
+                minute,
+                second,
+                nanosecond,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn date(&self) -> Date {
+        self.date
+    }
+
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+
+    pub fn nanosecond(&self) -> u32 {
+        self.nanosecond
+    }
+
+    pub fn timezone(&self) -> Option<i16> {
+        self.date.timezone
+    }
+
+    fn instant(&self) -> (i64, u32) {
+        let seconds = i64::from(self.hour) * 3600
+            + i64::from(self.minute) * 60
+            + i64::from(self.second)
+            + self.date.local_seconds()
+            - self.date.offset_seconds();
+        (seconds, self.nanosecond)
+    }
+}
+
+impl FromStr for DateTime {
+    type Err = ValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut reader = Reader(s);
+        let date_time = reader.date().and_then(|date| {
+            reader.expect("T")?;
+            let hour = reader.digits(2, 2)?;
+            reader.expect(":")?;
+            let minute = reader.digits(2, 2)?;
+            reader.expect(":")?;
+            let second = reader.digits(2, 2)?;
+            let mut nanosecond = 0;
+            if reader.expect(".").is_some() {
+                let length = reader.digit_count();
+                if length == 0 {
+                    return None;
+                }
+                for (i, c) in reader.0[..length].chars().take(9).enumerate() {
+                    nanosecond += c.to_digit(10)? * 10u32.pow(8 - i as u32);
+                }
+                reader.0 = &reader.0[length..];
+            }
+            let date = date.with_timezone(reader.timezone()?)?;
+            DateTime::new(date, hour as u8, minute as u8, second as u8, nanosecond)
+        });
+        match date_time {
+            Some(date_time) if reader.0.is_empty() => Ok(date_time),
+            _ => Err(ValueError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_date(f, &self.date)?;
+        write!(f, "T{:02}:{:02}:{:02}", self.hour, self.minute, self.second)?;
+        if self.nanosecond > 0 {
+            let fraction = format!("{:09}", self.nanosecond);
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        write_timezone(f, self.date.timezone)
+    }
+}
+
+/// An `xsd:duration`, as a number of months and a number of seconds with the same sign.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Duration {
+    months: i64,
+    seconds: Decimal,
+}
+
+impl Duration {
+    /// A duration, if the months and the seconds do not have opposite signs.
+    pub fn new(months: i64, seconds: Decimal) -> Option<Duration> {
+        if (months < 0 && seconds.digits > 0) || (months > 0 && seconds.digits < 0) {
+            None
+        } else {
+            Some(Duration { months, seconds })
+        }
+    }
+
+    pub fn months(&self) -> i64 {
+        self.months
+    }
+
+    pub fn seconds(&self) -> Decimal {
+        self.seconds
+    }
+
+    fn compare(&self, other: &Duration) -> Option<Ordering> {
+        if self == other {
+            Some(Ordering::Equal)
+        } else if self.months == 0 && other.months == 0 {
+            Some(self.seconds.cmp(&other.seconds))
+        } else if self.seconds.is_zero() && other.seconds.is_zero() {
+            Some(self.months.cmp(&other.months))
+        } else {
+            None
+        }
+    }
+}
+
+impl FromStr for Duration {
+    type Err = ValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ValueError(s.to_string());
+        let (negative, unsigned) = split_sign(s);
+        let unsigned = unsigned.strip_prefix('P').ok_or_else(error)?;
+        let (date, time) = match unsigned.find('T') {
+            Some(i) if i + 1 < unsigned.len() => (&unsigned[..i], Some(&unsigned[i + 1..])),
+            Some(_) => return Err(error()),
+            None => (unsigned, None),
+        };
+        if date.is_empty() && time.is_none() {
+            return Err(error());
+        }
+        let mut months: i128 = 0;
+        let mut seconds: i128 = 0;
+        let mut decimal_seconds = Decimal::from(0);
+        let parts = vec![(date, "YMD", true)];
+        for (part, designators, in_date) in parts.into_iter().chain(time.map(|t| (t, "HMS", false)))
+        {
+            let mut rest = part;
+            let mut designators = designators.chars();
+            while !rest.is_empty() {
+                let end = rest
+                    .find(|c: char| c.is_ascii_alphabetic())
+                    .ok_or_else(error)?;
+                let (number, designator) = (&rest[..end], rest[end..].chars().next().unwrap());
+                rest = &rest[end + 1..];
+                if !designators.any(|d| d == designator) {
+                    return Err(error());
+                }
+                if designator == 'S' && number.contains('.') {
+                    if number.starts_with('.')
+                        || number.ends_with('.')
+                        || !number.chars().all(|c| c.is_ascii_digit() || c == '.')
+                    {
+                        return Err(error());
+                    }
+                    decimal_seconds = number.parse().map_err(|_| error())?;
+                    continue;
+                }
+                if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+                    return Err(error());
+                }
+                let value: i128 = number.parse().map_err(|_| error())?;
+                let (total, unit) = match (designators.clone().count(), in_date) {
+                    (2, true) => (&mut months, 12),
+                    (1, true) => (&mut months, 1),
+                    (0, true) => (&mut seconds, 86400),
+                    (2, false) => (&mut seconds, 3600),
+                    (1, false) => (&mut seconds, 60),
+                    _ => (&mut seconds, 1),
+                };
+                *total = value
+                    .checked_mul(unit)
+                    .and_then(|v| v.checked_add(*total))
+                    .ok_or_else(error)?;
+            }
+        }
+        let sign = if negative { -1 } else { 1 };
+        let months = i64::try_from(months * sign).map_err(|_| error())?;
+        let seconds = seconds
+            .checked_mul(10i128.pow(decimal_seconds.scale))
+            .and_then(|s| s.checked_add(decimal_seconds.digits))
+            .and_then(|digits| Decimal::new(digits * sign, decimal_seconds.scale))
+            .ok_or_else(error)?;
+        Duration::new(months, seconds).ok_or_else(error)
+    }
+}
+
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.months < 0 || self.seconds.digits < 0 {
+            write!(f, "-")?;
+        }
+        write!(f, "P")?;
+        let months = self.months.abs();
+        if months >= 12 {
+            write!(f, "{}Y", months / 12)?;
+        }
+        if months % 12 > 0 {
+            write!(f, "{}M", months % 12)?;
+        }
+        let (integer, fraction) = self.seconds.split();
+        let (integer, fraction) = (integer.unsigned_abs(), fraction.unsigned_abs());
+        if integer >= 86400 {
+            write!(f, "{}D", integer / 86400)?;
+        }
+        let time = integer % 86400;
+        if time > 0 || fraction > 0 {
+            write!(f, "T")?;
+            if time >= 3600 {
+                write!(f, "{}H", time / 3600)?;
+            }
+            if time % 3600 >= 60 {
+                write!(f, "{}M", time % 3600 / 60)?;
+            }
+            if fraction > 0 {
+                let width = self.seconds.scale as usize;
+                write!(f, "{}.{:0width$}S", time % 60, fraction, width = width)?;
+            } else if time % 60 > 0 {
+                write!(f, "{}S", time % 60)?;
+            }
+        } else if months == 0 && integer == 0 {
+            write!(f, "T0S")?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the parts of dates and times.
+struct Reader<'a>(&'a str);
+
+impl Reader<'_> {
+    fn expect(&mut self, s: &str) -> Option<()> {
+        self.0 = self.0.strip_prefix(s)?;
+        Some(())
+    }
+
+    fn digit_count(&self) -> usize {
+        self.0
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.0.len())
+    }
+
+    fn digits(&mut self, min: usize, max: usize) -> Option<i64> {
+        let length = self.digit_count();
+        if length < min || length > max {
+            return None;
+        }
+        let value = self.0[..length].parse().ok();
+        self.0 = &self.0[length..];
+        value
+    }
+
+    fn date(&mut self) -> Option<Date> {
+        let negative = self.expect("-").is_some();
+        let start = self.0;
+        let year = self.digits(4, 18)?;
+        if start.len() - self.0.len() > 4 && start.starts_with('0') {
+            return None;
+        }
+        self.expect("-")?;
+        let month = self.digits(2, 2)?;
+        self.expect("-")?;
+        let day = self.digits(2, 2)?;
+        Date::new(if negative { -year } else { year }, month as u8, day as u8)
+    }
+
+    /// Reads an optional timezone, where the outer `None` means that it is invalid.
+    fn timezone(&mut self) -> Option<Option<i16>> {
+        if self.expect("Z").is_some() {
+            return Some(Some(0));
+        }
+        let sign = match self.0.chars().next() {
+            Some('+') => 1,
+            Some('-') => -1,
+            _ => return Some(None),
+        };
+        self.0 = &self.0[1..];
+        let hours = self.digits(2, 2)?;
+        self.expect(":")?;
+        let minutes = self.digits(2, 2)?;
+        let offset = hours * 60 + minutes;
+        if minutes >= 60 || offset > MAX_TIMEZONE {
+            return None;
+        }
+        Some(Some((sign * offset) as i16))
+    }
+}
+
+fn parse_integer(s: &str) -> Option<i64> {
+    let (_, digits) = split_sign(s);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    s.trim_start_matches('+').parse().ok()
+}
+
+fn parse_double(s: &str) -> Option<f64> {
+    match s {
+        "INF" | "+INF" => Some(f64::INFINITY),
+        "-INF" => Some(f64::NEG_INFINITY),
+        "NaN" => Some(f64::NAN),
+        _ if s.chars().all(|c| c.is_ascii_digit() || "+-.eE".contains(c)) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn write_double(value: f64) -> String {
+    if value.is_nan() {
+        return "NaN".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "INF" } else { "-INF" }.to_string();
+    }
+    let written = format!("{:E}", value);
+    match written.find('E') {
+        Some(i) if !written[..i].contains('.') => format!("{}.0{}", &written[..i], &written[i..]),
+        _ => written,
+    }
+}
+
+fn split_sign(s: &str) -> (bool, &str) {
+    match s.chars().next() {
+        Some('-') => (true, &s[1..]),
+        Some('+') => (false, &s[1..]),
+        _ => (false, s),
+    }
+}
+
+fn write_date(f: &mut fmt::Formatter, date: &Date) -> fmt::Result {
+    if date.year < 0 {
+        write!(f, "-")?;
+    }
+    write!(
+        f,
+        "{:04}-{:02}-{:02}",
+        date.year.abs(),
+        date.month,
+        date.day
+    )
+}
+
+fn write_timezone(f: &mut fmt::Formatter, timezone: Option<i16>) -> fmt::Result {
+    match timezone {
+        None => Ok(()),
+        Some(0) => write!(f, "Z"),
+        Some(offset) => {
+            let sign = if offset < 0 { '-' } else { '+' };
+            let offset = offset.abs();
+            write!(f, "{}{:02}:{:02}", sign, offset / 60, offset % 60)
+        }
+    }
+}
+
+/// Compares instants, where one without a timezone may be up to 14 hours earlier or later.
+fn compare_instants(
+    a: (i64, u32),
+    a_timezone: Option<i16>,
+    b: (i64, u32),
+    b_timezone: Option<i16>,
+) -> Option<Ordering> {
+    match (a_timezone, b_timezone) {
+        (None, Some(_)) => {
+            let margin = MAX_TIMEZONE * 60;
+            if (a.0 + margin, a.1) < b {
+                Some(Ordering::Less)
+            } else if (a.0 - margin, a.1) > b {
+                Some(Ordering::Greater)
+            } else {
+                None
+            }
+        }
+        (Some(_), None) => compare_instants(b, b_timezone, a, a_timezone).map(Ordering::reverse),
+        _ => Some(a.cmp(&b)),
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// The number of days since 1970-01-01, after Howard Hinnant's `days_from_civil`.
+fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::term::{Date, DateTime, Decimal, Duration, Literal, Term};
+    use crate::Resource;
+    use rdf::node::Node;
+    use rdf::uri::Uri;
+    use std::cmp::Ordering;
+
+    fn literal(value: &str, datatype: &str) -> Literal {
+        Literal::parse(
+            value,
+            &format!("http://www.w3.org/2001/XMLSchema#{}", datatype),
+        )
+    }
+
+    #[test]
+    fn parses_values() {
+        assert_eq!(literal(" 042", "integer"), Literal::Integer(42));
+        assert_eq!(
+            literal("-1.50", "decimal"),
+            Literal::Decimal(Decimal::new(-15, 1).unwrap())
+        );
+        assert_eq!(literal("1e3", "double"), Literal::Double(1000.0));
+        assert_eq!(literal("1", "boolean"), Literal::Boolean(true));
+        assert_eq!(literal("2020-02-29Z", "date").lexical_form(), "2020-02-29Z");
+        assert_eq!(
+            literal("2020-01-02T03:04:05.250+01:30", "dateTime").lexical_form(),
+            "2020-01-02T03:04:05.25+01:30"
+        );
+        assert_eq!(
+            literal("P1Y14M2DT25H0.5S", "duration").lexical_form(),
+            "P2Y2M3DT1H0.5S"
+        );
+        assert_eq!(literal("-PT0S", "duration").lexical_form(), "PT0S");
+        assert_eq!(literal("1.0", "double").lexical_form(), "1.0E0");
+        assert_eq!(literal("5", "decimal").lexical_form(), "5.0");
+        assert_eq!(
+            literal("-999999999-12-31", "date").lexical_form(),
+            "-999999999-12-31"
+        );
+        assert_eq!(
+            Decimal::new(i128::MIN, 0).unwrap().to_string(),
+            "-170141183460469231731687303715884105728.0"
+        );
+        for (value, datatype) in &[
+            ("1.5", "integer"),
+            ("99999999999999999999", "integer"),
+            ("one", "double"),
+            ("2019-02-29", "date"),
+            ("999999999999999999-12-31", "date"),
+            ("-999999999999999999-01-01T00:00:00", "dateTime"),
+            ("2020-01-01T24:00:00", "dateTime"),
+            ("P1S", "duration"),
+            ("PT", "duration"),
+        ] {
+            assert!(
+                matches!(literal(value, datatype), Literal::Other { .. }),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn compares_as_sparql() {
+        let integer = Term::from(1i64);
+        let decimal = Term::from(literal("1.0", "decimal"));
+        let double = Term::from(1.5);
+        assert_eq!(integer.sparql_eq(&decimal), Some(true));
+        assert_eq!(integer.sparql_cmp(&double), Some(Ordering::Less));
+        assert_eq!(Term::from(f64::NAN).sparql_eq(&double), Some(false));
+        assert_eq!(Term::from("1").sparql_eq(&integer), None);
+        assert_eq!(
+            Term::from("a").sparql_cmp(&Term::from("b")),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            Term::from(Resource::unsafe_from("http://example.org/a")).sparql_eq(&integer),
+            Some(false)
+        );
+
+        let time = |s: &str| Term::from(s.parse::<DateTime>().unwrap());
+        assert_eq!(
+            time("2020-01-01T12:00:00Z").sparql_eq(&time("2020-01-01T13:00:00+01:00")),
+            Some(true)
+        );
+        assert_eq!(
+            time("2020-01-01T12:00:00").sparql_cmp(&time("2020-01-01T13:00:00Z")),
+            None
+        );
+        assert_eq!(
+            time("2020-01-01T12:00:00").sparql_cmp(&time("2020-01-02T13:00:00Z")),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            Term::from("2020-01-01".parse::<Date>().unwrap())
+                .sparql_cmp(&Term::from("1969-12-31".parse::<Date>().unwrap())),
+            Some(Ordering::Greater)
+        );
+
+        let duration = |s: &str| Term::from(s.parse::<Duration>().unwrap());
+        assert_eq!(duration("P1Y").sparql_eq(&duration("P12M")), Some(true));
+        assert_eq!(
+            duration("PT1H").sparql_cmp(&duration("PT59M")),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(duration("P1M").sparql_cmp(&duration("P30D")), None);
+    }
+
+    #[test]
+    fn converts_nodes() {
+        let node = Node::LiteralNode {
+            literal: "+7".to_string(),
+            data_type: Some(Uri::new(
+                "http://www.w3.org/2001/XMLSchema#integer".to_string(),
+            )),
+            language: None,
+        };
+        let term = Term::from(&node);
+        assert_eq!(term, Term::Literal(Literal::Integer(7)));
+        assert_eq!(
+            term.to_string(),
+            "\"7\"^^<http://www.w3.org/2001/XMLSchema#integer>"
+        );
+
+        let node = Node::LiteralNode {
+            literal: "chat".to_string(),
+            data_type: None,
+            language: Some("fr".to_string()),
+        };
+        let term = Term::from(&node);
+        assert_eq!(term.literal().and_then(Literal::as_str), Some("chat"));
+        assert_eq!(term.to_node(), node);
+        assert_eq!(
+            Term::from(&Node::BlankNode {
+                id: "b0".to_string()
+            }),
+            Term::BlankNode("b0".to_string())
+        );
+    }
+}
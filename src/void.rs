@@ -0,0 +1,325 @@
+//! Statistics about the content of a graph store, and their description in the VoID vocabulary.
+
+use crate::error::Error;
+use crate::table::{Table, Variable};
+use crate::{Graph, GraphStore, Resource, Selection};
+use async_trait::async_trait;
+use rdf::namespace::Namespace;
+use rdf::node::Node;
+use rdf::triple::Triple;
+use rdf::uri::Uri;
+use std::collections::BTreeMap;
+
+const VOID: &str = "http://rdfs.org/ns/void#";
+const SD: &str = "http://www.w3.org/ns/sparql-service-description#";
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const XSD_INTEGER: &str = "http://www.w3.org/2001/XMLSchema#integer";
+
+/// Statistics of the default graph and the named graphs of a graph store.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Statistics {
+    /// The default graph first, if it has triples, then the named graphs ordered by IRI.
+    pub graphs: Vec<GraphStatistics>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct GraphStatistics {
+    pub graph: Graph,
+    pub triples: u64,
+    pub distinct_subjects: u64,
+    pub distinct_objects: u64,
+    /// Classes with their number of instances, most used first.
+    pub classes: Vec<(Resource, u64)>,
+    /// Properties with their number of triples, most used first.
+    pub properties: Vec<(Resource, u64)>,
+}
+
+impl Statistics {
+    pub fn graph(&self, graph: &Graph) -> Option<&GraphStatistics> {
+        self.graphs
+            .iter()
+            .find(|statistics| &statistics.graph == graph)
+    }
+
+    /// The number of triples in all graphs.
+    pub fn triples(&self) -> u64 {
+        self.graphs
+            .iter()
+            .map(|statistics| statistics.triples)
+            .sum()
+    }
+
+    /// Describes a dataset with a subset for each graph.
+    pub fn to_void(&self, dataset: &Resource) -> rdf::graph::Graph {
+        let mut void = rdf::graph::Graph::new(None);
+        void.add_namespace(&Namespace::new(
+            "void".to_string(),
+            Uri::new(VOID.to_string()),
+        ));
+        void.add_namespace(&Namespace::new("sd".to_string(), Uri::new(SD.to_string())));
+        let dataset = void.create_uri_node(&Uri::new(dataset.as_str().to_string()));
+        add_dataset(&mut void, &dataset, self.triples());
+        for statistics in &self.graphs {
+            let subset = void.create_blank_node();
+            add(&mut void, &dataset, &void_term("subset"), &subset);
+            statistics.add_to(&mut void, &subset);
+        }
+        void
+    }
+}
+
+impl GraphStatistics {
+    fn new(graph: Graph) -> GraphStatistics {
+        GraphStatistics {
+            graph,
+            triples: 0,
+            distinct_subjects: 0,
+            distinct_objects: 0,
+            classes: vec![],
+            properties: vec![],
+        }
+    }
+
+    fn add_to(&self, void: &mut rdf::graph::Graph, subset: &Node) {
+        add_dataset(void, subset, self.triples);
+        if let Graph::Named(graph) = &self.graph {
+            let name = void.create_uri_node(&Uri::new(graph.as_str().to_string()));
+            add(void, subset, &uri(&format!("{}name", SD)), &name);
+        }
+        add_count(void, subset, "distinctSubjects", self.distinct_subjects);
+        add_count(void, subset, "distinctObjects", self.distinct_objects);
+        add_count(void, subset, "classes", self.classes.len() as u64);
+        add_count(void, subset, "properties", self.properties.len() as u64);
+        for (class, entities) in &self.classes {
+            let partition = void.create_blank_node();
+            add(void, subset, &void_term("classPartition"), &partition);
+            let class = void.create_uri_node(&Uri::new(class.as_str().to_string()));
+            add(void, &partition, &void_term("class"), &class);
+            add_count(void, &partition, "entities", *entities);
+        }
+        for (property, triples) in &self.properties {
+            let partition = void.create_blank_node();
+            add(void, subset, &void_term("propertyPartition"), &partition);
+            let property = void.create_uri_node(&Uri::new(property.as_str().to_string()));
+            add(void, &partition, &void_term("property"), &property);
+            add_count(void, &partition, "triples", *triples);
+        }
+    }
+}
+
+/// Computes statistics with aggregate queries, one for the sizes of the graphs, one for the
+/// classes and one for the properties.
+#[async_trait]
+pub trait VoidStatistics: GraphStore {
+    async fn statistics(&self) -> Result<Statistics, Error> {
+        let mut graphs = BTreeMap::new();
+        for row in self.select(Selection::of_triple_counts()).await?.bindings {
+            let statistics = entry(&mut graphs, &row);
+            statistics.triples = count(&row, "triples")?;
+            statistics.distinct_subjects = count(&row, "subjects")?;
+            statistics.distinct_objects = count(&row, "objects")?;
+        }
+        let classes = self.select(Selection::of_class_counts()).await?;
+        for (row, class) in resources(&classes, "class") {
+            let entities = count(row, "entities")?;
+            entry(&mut graphs, row).classes.push((class, entities));
+        }
+        let properties = self.select(Selection::of_property_counts()).await?;
+        for (row, property) in resources(&properties, "property") {
+            let triples = count(row, "triples")?;
+            entry(&mut graphs, row).properties.push((property, triples));
+        }
+        Ok(Statistics {
+            graphs: graphs
+                .into_values()
+                .map(|mut statistics| {
+                    sort(&mut statistics.classes);
+                    sort(&mut statistics.properties);
+                    statistics
+                })
+                .collect(),
+        })
+    }
+}
+
+impl<T: GraphStore + ?Sized> VoidStatistics for T {}
+
+type Row = std::collections::HashMap<Variable, Node>;
+
+/// The statistics of the graph of a row, keyed so that the default graph comes first.
+fn entry<'a>(
+    graphs: &'a mut BTreeMap<Option<String>, GraphStatistics>,
+    row: &Row,
+) -> &'a mut GraphStatistics {
+    let name = match row.get(&Variable::from("graph")) {
+        Some(Node::UriNode { uri }) => Some(uri.to_string().to_string()),
+        _ => None,
+    };
+    graphs.entry(name.clone()).or_insert_with(|| {
+        GraphStatistics::new(match name {
+            Some(name) => Graph::Named(Resource::unsafe_from(&name)),
+            None => Graph::Default,
+        })
+    })
+}
+
+/// The rows where a variable is bound to an IRI, with that IRI.
+fn resources<'a>(table: &'a Table<Node>, variable: &str) -> Vec<(&'a Row, Resource)> {
+    table
+        .bindings
+        .iter()
+        .filter_map(|row| match row.get(&Variable::from(variable)) {
+            Some(Node::UriNode { uri }) => Some((row, Resource::unsafe_from(uri.to_string()))),
+            _ => None,
+        })
+        .collect()
+}
+
+fn count(row: &Row, variable: &str) -> Result<u64, Error> {
+    match row.get(&Variable::from(variable)) {
+        Some(Node::LiteralNode { literal, .. }) => literal
+            .parse()
+            .map_err(|_| Error::Response(format!("Invalid count {}", literal))),
+        _ => Err(Error::Response(format!("Missing count ?{}", variable))),
+    }
+}
+
+fn sort(counts: &mut [(Resource, u64)]) {
+    counts.sort_by(|(a, m), (b, n)| n.cmp(m).then_with(|| a.as_str().cmp(b.as_str())));
+}
+
+fn uri(iri: &str) -> Node {
+    Node::UriNode {
+        uri: Uri::new(iri.to_string()),
+    }
+}
+
+fn void_term(name: &str) -> Node {
+    uri(&format!("{}{}", VOID, name))
+}
+
+fn add(void: &mut rdf::graph::Graph, subject: &Node, predicate: &Node, object: &Node) {
+    void.add_triple(&Triple::new(subject, predicate, object));
+}
+
+fn add_count(void: &mut rdf::graph::Graph, subject: &Node, name: &str, count: u64) {
+    let count =
+        void.create_literal_node_with_data_type(count.to_string(), &Uri::new(XSD_INTEGER.into()));
+    add(void, subject, &void_term(name), &count);
+}
+
+fn add_dataset(void: &mut rdf::graph::Graph, dataset: &Node, triples: u64) {
+    add(void, dataset, &uri(RDF_TYPE), &void_term("Dataset"));
+    add_count(void, dataset, "triples", triples);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::http::{Dataset, DatasetType};
+    use crate::void::{GraphStatistics, VoidStatistics};
+    use crate::{Graph, Resource};
+    use mockito::{mock, Matcher, Mock};
+
+    fn create_query_mock(name: &str, pattern: &str, body: &str) -> Mock {
+        mock("POST", format!("/{}", name).as_ref())
+            .match_body(Matcher::Regex(pattern.to_string()))
+            .with_status(200)
+            .with_body(body)
+            .create()
+    }
+
+    #[tokio::test]
+    async fn computes_statistics() {
+        let client = reqwest::Client::new();
+        let base = url::Url::parse(&mockito::server_url()).unwrap();
+        let name = "void";
+        let _create_mock = mock("POST", "/$/datasets").with_status(200).create();
+        let count = |n: u64| {
+            format!(
+                r#"{{ "type": "literal", "value": "{}",
+                    "datatype": "http://www.w3.org/2001/XMLSchema#integer" }}"#,
+                n
+            )
+        };
+        let uri = |iri: &str| format!(r#"{{ "type": "uri", "value": "{}" }}"#, iri);
+        let _triples_mock = create_query_mock(
+            name,
+            "subjects",
+            &format!(
+                r#"{{ "head": {{ "vars": ["graph", "triples", "subjects", "objects"] }},
+                "results": {{ "bindings": [
+                    {{ "graph": {}, "triples": {}, "subjects": {}, "objects": {} }},
+                    {{ "triples": {}, "subjects": {}, "objects": {} }}
+                ] }} }}"#,
+                uri("http://example.org/g"),
+                count(3),
+                count(2),
+                count(3),
+                count(1),
+                count(1),
+                count(1)
+            ),
+        );
+        let _classes_mock = create_query_mock(
+            name,
+            "entities",
+            &format!(
+                r#"{{ "head": {{ "vars": ["graph", "class", "entities"] }},
+                "results": {{ "bindings": [
+                    {{ "graph": {}, "class": {}, "entities": {} }}
+                ] }} }}"#,
+                uri("http://example.org/g"),
+                uri("http://example.org/Person"),
+                count(2)
+            ),
+        );
+        let _properties_mock = create_query_mock(
+            name,
+            "property",
+            &format!(
+                r#"{{ "head": {{ "vars": ["graph", "property", "triples"] }},
+                "results": {{ "bindings": [
+                    {{ "graph": {}, "property": {}, "triples": {} }},
+                    {{ "graph": {}, "property": {}, "triples": {} }},
+                    {{ "property": {}, "triples": {} }}
+                ] }} }}"#,
+                uri("http://example.org/g"),
+                uri("http://example.org/name"),
+                count(1),
+                uri("http://example.org/g"),
+                uri("http://www.w3.org/1999/02/22-rdf-syntax-ns#type"),
+                count(2),
+                uri("http://example.org/name"),
+                count(1)
+            ),
+        );
+        let dataset = Dataset::get_or_create(&client, base, name, DatasetType::Mem)
+            .await
+            .unwrap();
+
+        let statistics = dataset.statistics().await.unwrap();
+
+        let graph = Graph::Named(Resource::unsafe_from("http://example.org/g"));
+        assert_eq!(statistics.triples(), 4);
+        assert_eq!(statistics.graphs[0].graph, Graph::Default);
+        assert_eq!(
+            statistics.graph(&graph),
+            Some(&GraphStatistics {
+                graph: graph.clone(),
+                triples: 3,
+                distinct_subjects: 2,
+                distinct_objects: 3,
+                classes: vec![(Resource::unsafe_from("http://example.org/Person"), 2)],
+                properties: vec![
+                    (
+                        Resource::unsafe_from("http://www.w3.org/1999/02/22-rdf-syntax-ns#type"),
+                        2
+                    ),
+                    (Resource::unsafe_from("http://example.org/name"), 1),
+                ],
+            })
+        );
+        let void = statistics.to_void(&Resource::unsafe_from("http://example.org/dataset"));
+        assert_eq!(void.count(), 29);
+    }
+}
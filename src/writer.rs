@@ -0,0 +1,289 @@
+use crate::syntax::{is_pn_chars, is_pn_chars_base, is_pn_chars_u};
+use rdf::node::Node;
+use rdf::triple::Triple;
+use std::collections::{BTreeMap, BTreeSet};
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+
+/// Writes a graph as N-Triples, one triple per line.
+pub fn write_n_triples(graph: &rdf::graph::Graph) -> Result<String, String> {
+    let mut output = String::new();
+    for triple in graph.triples_iter() {
+        check(triple)?;
+        output.push_str(&format!(
+            "{} {} {} .\n",
+            term(triple.subject(), &[]),
+            term(triple.predicate(), &[]),
+            term(triple.object(), &[])
+        ));
+    }
+    Ok(output)
+}
+
+/// Writes a graph as Turtle, declaring the namespaces of the graph that are used as prefixes and
+/// grouping triples by subject and predicate.
+pub fn write_turtle(graph: &rdf::graph::Graph) -> Result<String, String> {
+    let mut prefixes: Vec<(String, String)> = graph
+        .namespaces()
+        .iter()
+        .filter(|(prefix, _)| is_prefix(prefix))
+        .map(|(prefix, uri)| (prefix.to_string(), uri.to_string().to_string()))
+        .collect();
+    prefixes.sort();
+
+    let mut used = BTreeSet::new();
+    for triple in graph.triples_iter() {
+        let nodes = [triple.subject(), triple.predicate(), triple.object()];
+        for (i, node) in nodes.iter().enumerate() {
+            let iri = match node {
+                Node::UriNode { uri } if i == 1 && uri.to_string() == RDF_TYPE => continue,
+                Node::UriNode { uri } => uri.to_string(),
+                Node::LiteralNode {
+                    data_type: Some(data_type),
+                    language: None,
+                    ..
+                } => data_type.to_string(),
+                _ => continue,
+            };
+            if let Some((prefix, _)) = prefixed_name(iri, &prefixes) {
+                used.insert(prefix.to_string());
+            }
+        }
+    }
+
+    let mut output = String::new();
+    for (prefix, iri) in prefixes.iter().filter(|(p, _)| used.contains(p)) {
+        output.push_str(&format!("@prefix {}: {} .\n", prefix, iri_ref(iri)));
+    }
+
+    let mut subjects: Vec<&Node> = vec![];
+    let mut groups: BTreeMap<&Node, Vec<(&Node, Vec<&Node>)>> = BTreeMap::new();
+    for triple in graph.triples_iter() {
+        check(triple)?;
+        let group = groups.entry(triple.subject()).or_insert_with(|| {
+            subjects.push(triple.subject());
+            vec![]
+        });
+        match group.iter_mut().find(|(p, _)| *p == triple.predicate()) {
+            Some((_, objects)) if objects.contains(&triple.object()) => (),
+            Some((_, objects)) => objects.push(triple.object()),
+            None => group.push((triple.predicate(), vec![triple.object()])),
+        }
+    }
+
+    for subject in subjects {
+        output.push('\n');
+        output.push_str(&term(subject, &prefixes));
+        let predicates = groups[subject]
+            .iter()
+            .map(|(predicate, objects)| {
+                let predicate = match predicate {
+                    Node::UriNode { uri } if uri.to_string() == RDF_TYPE => "a".to_string(),
+                    predicate => term(predicate, &prefixes),
+                };
+                let objects = objects
+                    .iter()
+                    .map(|o| term(o, &prefixes))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!(" {} {}", predicate, objects)
+            })
+            .collect::<Vec<_>>()
+            .join(" ;\n   ");
+        output.push_str(&predicates);
+        output.push_str(" .\n");
+    }
+    Ok(output)
+}
+
+fn check(triple: &Triple) -> Result<(), String> {
+    match (triple.subject(), triple.predicate()) {
+        (Node::LiteralNode { literal, .. }, _) => {
+            Err(format!("Literal \"{}\" used as subject", literal))
+        }
+        (_, Node::UriNode { .. }) => Ok(()),
+        (_, predicate) => Err(format!("{:?} used as predicate", predicate)),
+    }
+}
+
+fn is_prefix(prefix: &str) -> bool {
+    let mut chars = prefix.chars();
+    match chars.next() {
+        None => true,
+        Some(c) if is_pn_chars_base(c) => {
+            !prefix.ends_with('.') && chars.all(|c| is_pn_chars(c) || c == '.')
+        }
+        _ => false,
+    }
+}
+
+pub(crate) fn is_local_name(local: &str) -> bool {
+    let mut chars = local.chars();
+    match chars.next() {
+        None => true,
+        Some(c) if is_pn_chars_u(c) || c == ':' || c.is_ascii_digit() => {
+            !local.ends_with('.') && chars.all(|c| is_pn_chars(c) || c == '.' || c == ':')
+        }
+        _ => false,
+    }
+}
+
+fn iri_ref(iri: &str) -> String {
+    let mut output = String::from("<");
+    for c in iri.chars() {
+        match c {
+            '\u{0}'..=' ' | '<' | '>' | '"' | '{' | '}' | '|' | '^' | '`' | '\\' => {
+                output.push_str(&format!("\\u{:04X}", c as u32))
+            }
+            c => output.push(c),
+        }
+    }
+    output.push('>');
+    output
+}
+
+fn prefixed_name<'a>(iri: &'a str, prefixes: &'a [(String, String)]) -> Option<(&'a str, &'a str)> {
+    prefixes
+        .iter()
+        .filter(|(_, namespace)| iri.starts_with(namespace.as_str()))
+        .map(|(prefix, namespace)| (prefix.as_str(), &iri[namespace.len()..]))
+        .filter(|(_, local)| is_local_name(local))
+        .min_by_key(|(_, local)| local.len())
+}
+
+fn iri(iri: &str, prefixes: &[(String, String)]) -> String {
+    prefixed_name(iri, prefixes)
+        .map(|(prefix, local)| format!("{}:{}", prefix, local))
+        .unwrap_or_else(|| iri_ref(iri))
+}
+
+fn string(literal: &str) -> String {
+    let mut output = String::from("\"");
+    for c in literal.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if c < ' ' => output.push_str(&format!("\\u{:04X}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
+
+/// Writes a blank node label, escaping characters that are not allowed or that are `.` as `_`
+/// followed by their code point in hexadecimal and `_`, and `_` itself as `__`, so that
+/// different labels stay different.
+pub(crate) fn blank_node(id: &str) -> String {
+    let mut output = String::from("_:");
+    for (i, c) in id.chars().enumerate() {
+        let allowed = match i {
+            0 => is_pn_chars_u(c) || c.is_ascii_digit(),
+            _ => is_pn_chars(c),
+        };
+        match c {
+            '_' => output.push_str("__"),
+            c if allowed => output.push(c),
+            c => output.push_str(&format!("_{:X}_", c as u32)),
+        }
+    }
+    output
+}
+
+pub(crate) fn term(node: &Node, prefixes: &[(String, String)]) -> String {
+    match node {
+        Node::UriNode { uri } => iri(uri.to_string(), prefixes),
+        Node::BlankNode { id } => blank_node(id),
+        Node::LiteralNode {
+            literal,
+            language: Some(language),
+            ..
+        } => format!("{}@{}", string(literal), language),
+        Node::LiteralNode {
+            literal,
+            data_type: Some(data_type),
+            ..
+        } => format!(
+            "{}^^{}",
+            string(literal),
+            iri(data_type.to_string(), prefixes)
+        ),
+        Node::LiteralNode { literal, .. } => string(literal),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::{check, Dialect};
+    use crate::writer::{blank_node, write_n_triples, write_turtle};
+    use rdf::graph::Graph;
+    use rdf::namespace::Namespace;
+    use rdf::triple::Triple;
+    use rdf::uri::Uri;
+
+    fn example() -> Graph {
+        let mut graph = Graph::new(None);
+        graph.add_namespace(&Namespace::new(
+            "ex".to_string(),
+            Uri::new("http://example.org/".to_string()),
+        ));
+        let uri = |s: &str| graph.create_uri_node(&Uri::new(s.to_string()));
+        let a = uri("http://example.org/a");
+        let b = uri("http://example.org/b");
+        let path = uri("http://example.org/some/path");
+        let rdf_type = uri("http://www.w3.org/1999/02/22-rdf-syntax-ns#type");
+        let label =
+            graph.create_literal_node_with_language("say \"hi\"\n".to_string(), "en".to_string());
+        let blank = graph.create_blank_node_with_id("x".to_string());
+        graph.add_triple(&Triple::new(&a, &rdf_type, &b));
+        graph.add_triple(&Triple::new(&a, &b, &label));
+        graph.add_triple(&Triple::new(&a, &b, &path));
+        graph.add_triple(&Triple::new(&blank, &b, &a));
+        graph
+    }
+
+    #[test]
+    fn writes_turtle() {
+        let turtle = write_turtle(&example()).unwrap();
+
+        assert_eq!(
+            turtle,
+            "@prefix ex: <http://example.org/> .
+
+ex:a a ex:b ;
+    ex:b \"say \\\"hi\\\"\\n\"@en, <http://example.org/some/path> .
+
+_:x ex:b ex:a .
+"
+        );
+        assert_eq!(check(&turtle, Dialect::Turtle), Ok(()));
+    }
+
+    #[test]
+    fn writes_n_triples() {
+        let n_triples = write_n_triples(&example()).unwrap();
+
+        assert_eq!(n_triples.lines().count(), 4);
+        assert_eq!(check(&n_triples, Dialect::NTriples), Ok(()));
+    }
+
+    #[test]
+    fn keeps_blank_nodes_apart() {
+        let labels: Vec<String> = ["a.b", "a_b", "a_2E_b", "-a", "x"]
+            .iter()
+            .map(|id| blank_node(id))
+            .collect();
+
+        assert_eq!(
+            labels,
+            vec!["_:a_2E_b", "_:a__b", "_:a__2E__b", "_:_2D_a", "_:x"]
+        );
+        for label in labels {
+            let turtle = format!("{} <http://example.org/p> 1 .", label);
+            assert_eq!(check(&turtle, Dialect::Turtle), Ok(()));
+        }
+    }
+}
@@ -1,6 +1,6 @@
-use graph_store::http::Dataset;
+use graph_store::http::{Dataset, DatasetType};
 use graph_store::{doc, Resource};
-use graph_store::{DataFile, DescribeQuery, Graph, GraphStore, Selection};
+use graph_store::{DataFile, DescribeQuery, Format, Graph, GraphStore, QuadFormat, Selection};
 use uuid::Uuid;
 
 #[tokio::test]
@@ -15,11 +15,13 @@ async fn create_select_describe_and_delete() {
 <#d> <#e> <#f>",
     );
 
-    let dataset = Dataset::get_or_create(&client, base, &name).await;
+    let dataset = Dataset::get_or_create(&client, base, &name, DatasetType::Mem)
+        .await
+        .unwrap();
 
-    dataset.import(Graph::Default, file).await;
+    dataset.import(Graph::Default, file).await.unwrap();
 
-    let result = dataset.select(Selection::of_triples()).await;
+    let result = dataset.select(Selection::of_triples()).await.unwrap();
     println!("selection: {:?}", result);
 
     let result = dataset
@@ -29,13 +31,110 @@ WHERE {
   ?x ?y ?z
 }",
         ))
-        .await;
+        .await
+        .unwrap();
     println!("description: {:?}", result);
 
-    let result = dataset.describe_everything().await;
+    let result = dataset.describe_everything().await.unwrap();
     println!("description of everything: {:?}", result);
 
-    dataset.delete().await;
+    dataset.delete().await.unwrap();
+}
+
+#[tokio::test]
+async fn import_serialized_graph() {
+    let client = reqwest::Client::new();
+    let base = url::Url::parse("http://localhost:3030").unwrap();
+    let name = format!("test-{}", Uuid::new_v4());
+    let dataset = Dataset::get_or_create(&client, base, &name, DatasetType::Mem)
+        .await
+        .unwrap();
+    let source = Graph::Named(Resource::parse("urn:example:source").unwrap());
+    let copy = Graph::Named(Resource::parse("urn:example:copy").unwrap());
+    let file =
+        DataFile::unsafe_from_turtle("<urn:example:a> <urn:example:b> _:a.b , _:a_b , \"c\"@en .");
+    dataset.import(source.clone(), file).await.unwrap();
+
+    let graph = dataset.get(source).await.unwrap();
+    let file = DataFile::from_graph(&graph, Format::Turtle).unwrap();
+    dataset.import(copy.clone(), file).await.unwrap();
+
+    assert_eq!(dataset.get(copy).await.unwrap().count(), 3);
+
+    dataset.delete().await.unwrap();
+}
+
+#[tokio::test]
+async fn import_checked_turtle() {
+    let client = reqwest::Client::new();
+    let base = url::Url::parse("http://localhost:3030").unwrap();
+    let name = format!("test-{}", Uuid::new_v4());
+    let file = DataFile::from_turtle(
+        "@base <urn:uuid:bc36c84d-30bf-4014-9940-255150891034> .
+
+<#a> <#b> \"c\" .
+<#d> <#e> <#f> .",
+    )
+    .unwrap();
+    assert!(DataFile::from_turtle("<urn:example:a> <urn:example:b>").is_err());
+
+    let dataset = Dataset::get_or_create(&client, base, &name, DatasetType::Mem)
+        .await
+        .unwrap();
+
+    dataset.import(Graph::Default, file).await.unwrap();
+
+    let result = dataset.select(Selection::of_triples()).await.unwrap();
+    assert_eq!(result.bindings.len(), 2);
+
+    dataset.delete().await.unwrap();
+}
+
+#[tokio::test]
+async fn dump_and_restore() {
+    let client = reqwest::Client::new();
+    let base = url::Url::parse("http://localhost:3030").unwrap();
+    let source = Dataset::get_or_create(
+        &client,
+        base.clone(),
+        &format!("test-{}", Uuid::new_v4()),
+        DatasetType::Mem,
+    )
+    .await
+    .unwrap();
+    let target = Dataset::get_or_create(
+        &client,
+        base,
+        &format!("test-{}", Uuid::new_v4()),
+        DatasetType::Mem,
+    )
+    .await
+    .unwrap();
+    let triple = "<urn:example:a> <urn:example:b> \"c\" .";
+    source
+        .import(Graph::Default, DataFile::from_turtle(triple).unwrap())
+        .await
+        .unwrap();
+    source
+        .import(
+            Graph::Named(Resource::parse("urn:example:g").unwrap()),
+            DataFile::from_turtle(triple).unwrap(),
+        )
+        .await
+        .unwrap();
+    let mut dump: Vec<u8> = vec![];
+
+    source.dump(QuadFormat::NQuads, &mut dump).await.unwrap();
+    target
+        .restore(QuadFormat::NQuads, std::io::Cursor::new(dump))
+        .await
+        .unwrap();
+
+    let result = target.select(Selection::of_graphs()).await.unwrap();
+    assert_eq!(result.bindings.len(), 1);
+
+    source.delete().await.unwrap();
+    target.delete().await.unwrap();
 }
 
 #[tokio::test]
@@ -51,15 +150,21 @@ async fn html_files() {
 <#d> <#g> <#a> .",
     );
 
-    let dataset = Dataset::get_or_create(&client, base, &name).await;
+    let dataset = Dataset::get_or_create(&client, base, &name, DatasetType::Mem)
+        .await
+        .unwrap();
 
     dataset
-        .import(Graph::Named(Resource::from("g1")), file)
-        .await;
+        .import(
+            Graph::Named(Resource::parse("http://example.org/g1").unwrap()),
+            file,
+        )
+        .await
+        .unwrap();
 
-    doc::export_to_html(&dataset).await;
+    doc::export_to_html(&dataset).await.unwrap();
 
-    dataset.delete().await;
+    dataset.delete().await.unwrap();
 }
 
 #[tokio::test]
@@ -67,7 +172,9 @@ async fn architecture_documentation() {
     let client = reqwest::Client::new();
     let base = url::Url::parse("http://localhost:3030").unwrap();
     let name = "architecture";
-    let dataset = Dataset::get_or_create(&client, base, &name).await;
+    let dataset = Dataset::get_or_create(&client, base, name, DatasetType::Mem)
+        .await
+        .unwrap();
 
-    doc::export_to_html(&dataset).await;
+    doc::export_to_html(&dataset).await.unwrap();
 }
@@ -0,0 +1,69 @@
+use graph_store::rdf::node::Node;
+use graph_store::rdf::uri::Uri;
+use graph_store::table::Table;
+use graph_store::{sparql, sparql_file, AskQuery, ConstructQuery, DescribeQuery, Selection};
+use std::collections::HashMap;
+
+sparql! {
+    struct Relation = "SELECT ?s (?p AS ?type) ?o WHERE { ?s ?p ?o }";
+}
+
+sparql_file! {
+    struct Label = "tests/queries/labels.rq";
+}
+
+#[test]
+fn expands_to_query_types() {
+    let _: Selection = sparql!("SELECT ?s WHERE { ?s a rdfs:Class }");
+    let _: AskQuery = sparql!("ASK { ?s ?p ?o }");
+    let _: DescribeQuery = sparql!("DESCRIBE <http://example.org/a>");
+    let _: ConstructQuery = sparql!("CONSTRUCT WHERE { ?s ?p ?o }");
+    let _: Selection = sparql_file!("tests/queries/labels.rq");
+    let _: Selection = Relation::selection();
+}
+
+#[test]
+fn reads_rows() {
+    let a = Node::UriNode {
+        uri: Uri::new("http://example.org/a".to_string()),
+    };
+    let mut binding = HashMap::new();
+    binding.insert("s".to_string(), a.clone());
+    let table = Table::from(
+        vec!["s".to_string(), "label".to_string()],
+        vec![binding],
+        |node| node,
+    );
+
+    let rows = Label::rows(table);
+
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].s, Some(a));
+    assert_eq!(rows[0].label, None);
+}
+
+#[test]
+fn escapes_field_names() {
+    let mut binding = HashMap::new();
+    binding.insert(
+        "type".to_string(),
+        Node::BlankNode {
+            id: "b".to_string(),
+        },
+    );
+    let table = Table::from(
+        vec!["s".to_string(), "type".to_string(), "o".to_string()],
+        vec![binding],
+        |node| node,
+    );
+
+    let rows = Relation::rows(table);
+
+    assert_eq!(
+        rows[0].r#type,
+        Some(Node::BlankNode {
+            id: "b".to_string()
+        })
+    );
+    assert_eq!(rows[0].o, None);
+}